@@ -1,51 +1,129 @@
-use names::Generator;
 use nannou::{
     glam::Vec2,
-    noise::{BasicMulti, NoiseFn, Perlin, Seedable},
+    noise::{NoiseFn, Seedable},
     prelude::*,
 };
+use perlin_noise_core::{
+    capture::CaptureConfig,
+    config::{draw_config_error, ConfigFile},
+    export::{write_csv, write_sidecar},
+    metadata::{load_state_from_png, save_png_with_state},
+    perlin::Perlin,
+    plot::plot_lines,
+    seed::{random_seed, seed_name},
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 // interesting variables
 const NOISE_STEP: f32 = 500.;
 const NOISE_SCALE: f32 = 1.;
 
+// the constants above and the window size, overridden by
+// plain_perlin.toml next to the binary while the
+// sketch runs
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct SketchConfig {
+    noise_step: f32,
+    noise_scale: f32,
+    window_width: u32,
+    window_height: u32,
+}
+
+impl Default for SketchConfig {
+    fn default() -> Self {
+        SketchConfig {
+            noise_step: NOISE_STEP,
+            noise_scale: NOISE_SCALE,
+            window_width: 1200,
+            window_height: 600,
+        }
+    }
+}
+
 fn main() {
     nannou::app(model).update(update).run();
 }
 
 struct Model {
+    window: WindowId,
+    capture: CaptureConfig,
+    config: SketchConfig,
+    config_file: ConfigFile,
     noise: Perlin,
     points: Vec<Vec2>,
-    frame_start: u64,
+    // raw (x, value) pairs behind `points`, for exporting
+    samples: Vec<[f64; 2]>,
+    // samples taken since the graph started
+    steps: u64,
+}
+
+// everything needed to redraw a capture, stored in the
+// PNG's text chunks. The samples follow from the seed and
+// config, so only their count is kept.
+#[derive(Serialize, Deserialize)]
+struct SketchState {
+    sketch: String,
+    seed: u32,
+    config: SketchConfig,
+    steps: u64,
+}
+
+#[derive(Serialize)]
+struct ExportParams {
+    sketch: String,
+    seed: u32,
+    seed_name: String,
+    noise: &'static str,
+    noise_step: f32,
+    noise_scale: f32,
 }
 
 fn model(app: &App) -> Model {
-    let _window = app
+    let mut config_file =
+        ConfigFile::from_args(&app.exe_name().unwrap());
+    let config: SketchConfig =
+        config_file.poll().unwrap_or_default();
+
+    let window = app
         .new_window()
-        .size(1200, 600)
+        .size(config.window_width, config.window_height)
         .view(view)
         .key_pressed(key_pressed)
         .mouse_pressed(mouse_pressed)
+        .dropped_file(dropped_file)
         .build()
         .unwrap();
 
     Model {
+        window,
+        capture: CaptureConfig::from_args(),
+        config,
+        config_file,
         noise: Perlin::new(),
         points: vec![],
-        frame_start: 0,
+        samples: vec![],
+        steps: 0,
     }
 }
 
 fn update(app: &App, model: &mut Model, _update: Update) {
+    if let Some(config) = model.config_file.poll() {
+        apply_config(app, model, config);
+    }
+    step(app, model);
+}
+
+// take the next sample
+fn step(app: &App, model: &mut Model) {
     let win_rect = app.window_rect();
 
     // advance by 1/500 per frame
-    let current_step = (app.elapsed_frames()
-        - model.frame_start) as f32
-        / NOISE_STEP;
-    let y = model
-        .noise
-        .get([(current_step * NOISE_SCALE).into(), 0.]);
+    let current_step =
+        model.steps as f32 / model.config.noise_step;
+    let x = current_step * model.config.noise_scale;
+    let y = model.noise.get([x.into(), 0.]);
     let mapped_y = map_range(
         y,
         -1.0,
@@ -54,6 +132,15 @@ fn update(app: &App, model: &mut Model, _update: Update) {
         win_rect.bottom(),
     );
     model.points.push(pt2(current_step, mapped_y));
+    model.samples.push([x.into(), y]);
+    model.steps += 1;
+}
+
+// start the graph over
+fn restart(model: &mut Model) {
+    model.steps = 0;
+    model.points = vec![];
+    model.samples = vec![];
 }
 
 fn view(app: &App, model: &Model, frame: Frame) {
@@ -130,55 +217,172 @@ fn view(app: &App, model: &Model, frame: Frame) {
     let seed_display =
         std::str::from_utf8(&seed_bytes).unwrap();
 
-    draw.text(&format!("{}", seed_display))
+    draw.text(seed_display)
         .font_size(48)
         .wh(win_p.wh())
         .right_justify()
         .align_text_bottom()
         .color(foreground);
 
+    if let Some(error) = &model.config_file.error {
+        draw_config_error(&draw, win_rect, error);
+    }
+
     // draw to frame
     draw.to_frame(app, &frame).unwrap();
 }
 
-fn key_pressed(app: &App, _model: &mut Model, key: Key) {
-    let now = chrono::offset::Local::now();
-
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
     if key == Key::S {
-        app.main_window().capture_frame(format!(
-            "{}{}{}",
-            app.exe_name().unwrap(),
-            now,
-            ".png"
-        ));
+        capture(app, model);
+    }
+    if key == Key::E {
+        export_samples(app, model);
     }
 }
 
-fn mouse_pressed(
+// save the graph with the sketch state embedded, so it
+// can be loaded back in later
+fn capture(app: &App, model: &mut Model) {
+    let path = match model.capture.next_path(
+        &app.exe_name().unwrap(),
+        Some(model.noise.seed()),
+        "png",
+    ) {
+        Ok(path) => path,
+        Err(error) => {
+            eprintln!("failed to capture frame: {}", error);
+            return;
+        }
+    };
+    let values: Vec<f64> =
+        model.samples.iter().map(|[_, y]| *y).collect();
+    let image = plot_lines(
+        &[values],
+        model.config.window_width,
+        model.config.window_height,
+    );
+    let state = SketchState {
+        sketch: app.exe_name().unwrap(),
+        seed: model.noise.seed(),
+        config: model.config.clone(),
+        steps: model.steps,
+    };
+    if let Err(error) =
+        save_png_with_state(&path, &image, &state)
+    {
+        eprintln!("failed to capture frame: {}", error);
+    }
+}
+
+// resample the graph a capture was taken of
+fn load_png(app: &App, model: &mut Model, path: &Path) {
+    let state: SketchState = match load_state_from_png(
+        path,
+        &app.exe_name().unwrap(),
+    ) {
+        Ok(state) => state,
+        Err(error) => {
+            eprintln!(
+                "failed to load {}: {}",
+                path.display(),
+                error
+            );
+            return;
+        }
+    };
+    apply_config(app, model, state.config);
+    model.noise = Perlin::new().set_seed(state.seed);
+    restart(model);
+    for _ in 0..state.steps {
+        step(app, model);
+    }
+}
+
+fn dropped_file(
     app: &App,
     model: &mut Model,
+    path: PathBuf,
+) {
+    load_png(app, model, &path);
+}
+
+// write the raw samples as csv, with the seed and
+// parameters in a json file of the same name
+fn export_samples(app: &App, model: &mut Model) {
+    let path = match model.capture.next_path(
+        &app.exe_name().unwrap(),
+        Some(model.noise.seed()),
+        "csv",
+    ) {
+        Ok(path) => path,
+        Err(error) => {
+            eprintln!(
+                "failed to export samples: {}",
+                error
+            );
+            return;
+        }
+    };
+    let seed = model.noise.seed();
+    let params = ExportParams {
+        sketch: app.exe_name().unwrap(),
+        seed,
+        seed_name: seed_name(seed),
+        noise: "Perlin",
+        noise_step: model.config.noise_step,
+        noise_scale: model.config.noise_scale,
+    };
+
+    let result =
+        write_csv(&path, ["x", "value"], &model.samples)
+            .and_then(|_| {
+                write_sidecar(
+                    path.with_extension("json"),
+                    &params,
+                )
+            });
+    if let Err(error) = result {
+        eprintln!("failed to export samples: {}", error);
+    }
+}
+
+fn mouse_pressed(
+    _app: &App,
+    model: &mut Model,
     _button: MouseButton,
 ) {
-    // generate a new seed using ascii characters, which
-    // tend to be more human-readable than numbers
-    let mut generator = Generator::default();
-    let seed = generator.next().unwrap();
-    let mut bytes: [u8; 4] = [0; 4];
-    for (i, byte) in
-        seed.as_bytes().iter().take(4).enumerate()
+    // clear any old data so that data from previous seeds
+    // doesn't render
+    model.noise = Perlin::new().set_seed(random_seed());
+    restart(model);
+}
+
+fn apply_config(
+    app: &App,
+    model: &mut Model,
+    config: SketchConfig,
+) {
+    let old = std::mem::replace(&mut model.config, config);
+    let config = &model.config;
+    if (old.window_width, old.window_height)
+        != (config.window_width, config.window_height)
     {
-        bytes[i] = *byte;
+        app.window(model.window)
+            .unwrap()
+            .set_inner_size_points(
+                config.window_width as f32,
+                config.window_height as f32,
+            );
     }
-    let seed_number: u32 = u32::from_be_bytes(bytes);
 
-    // instantiate the new noise
-    let noise = Perlin::new();
-    let seeded_noise = noise.set_seed(seed_number);
-
-    // set relevant values on model,
-    // clearing any old data so that data
-    // from previous seeds doesn't render
-    model.noise = seeded_noise;
-    model.frame_start = app.elapsed_frames();
-    model.points = vec![];
+    // anything else changes the graph, so start it over
+    let size_only = SketchConfig {
+        window_width: config.window_width,
+        window_height: config.window_height,
+        ..old
+    };
+    if size_only != *config {
+        restart(model);
+    }
 }
@@ -1,154 +1,558 @@
-use itertools::Itertools;
-use names::Generator;
 use nannou::{
-    color::Lch,
     glam::Vec2,
-    image::{DynamicImage, GenericImage},
-    noise::{BasicMulti, NoiseFn, Perlin, Seedable},
+    image::{DynamicImage, RgbImage},
     prelude::*,
     wgpu::Texture,
 };
 use nannou_egui::{self, egui, Egui};
+use perlin_noise_core::{
+    capture::{CaptureConfig, CaptureWindow},
+    color::{ColorMap, ColorSettings},
+    config::{draw_config_error, ConfigFile},
+    contour::{
+        contours, write_svg, Contour, ContourSettings,
+    },
+    curl::{
+        arrows, lic, sampled_curl, shade_lic, CurlSettings,
+        CurlView,
+    },
+    export::{write_npy, write_sidecar},
+    expr,
+    graph::NoiseGraph,
+    graph_editor::{
+        graph_editor, graph_settings, GraphAction,
+    },
+    metadata::{load_state_from_png, save_png_with_state},
+    noise::{Noise, NoiseConfig},
+    osc::OscListener,
+    preset::{
+        list_presets, load_preset, preset_dir, save_preset,
+    },
+    render::{
+        color_image, color_magnitudes,
+        gradient_magnitudes_2d, render_2d, ColorMode,
+    },
+    seed::{random_seed, seed_name},
+    server::{patch_state, Captured, Command, Server},
+    state::State2d,
+    terrain::{
+        draw_terrain_map, erode, gradients, terrain_map,
+        ErosionCache, ErosionConfig, MapSettings,
+        TerrainMap,
+    },
+    tiles::{TileCache, TileSource},
+    ui::{
+        capture_settings, contour_settings, curl_settings,
+        erosion_settings, map_settings, noise_settings,
+        preset_settings, PresetAction,
+    },
+    viewport::Viewport,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const SKETCH: &str = "perlin-noise-2d";
 
 // interesting variables
 const NOISE_STEP: f64 = 500.;
+// how much one line of scrolling zooms in or out
+const ZOOM_PER_LINE: f64 = 1.1;
+// tiles of samples kept for panning and zooming, 512KB each
+const TILE_CACHE: usize = 256;
+
+// the constants above and the window size, overridden by
+// perlin-noise-2d.toml next to the binary while the sketch
+// runs
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default)]
+struct SketchConfig {
+    noise_step: f64,
+    zoom_per_line: f64,
+    tile_cache: usize,
+    window_width: u32,
+    window_height: u32,
+}
+
+impl Default for SketchConfig {
+    fn default() -> Self {
+        SketchConfig {
+            noise_step: NOISE_STEP,
+            zoom_per_line: ZOOM_PER_LINE,
+            tile_cache: TILE_CACHE,
+            window_width: 1200,
+            window_height: 630,
+        }
+    }
+}
 
 fn main() {
     nannou::app(model).update(update).run();
 }
 
-struct Settings {
-    lightness: f64,
-    chroma: f64,
-    grayscale: bool,
-    hue_center: f64,
-    hue_range: f64,
-}
 struct Model {
-    settings: Settings,
+    config: SketchConfig,
+    config_file: ConfigFile,
+    settings: ColorSettings,
+    // `settings` with their table of hues, brought up to
+    // date before each redraw
+    colors: ColorMap,
+    // what the heights are coloured by when no map is shown
+    color_mode: ColorMode,
     egui: Egui,
     image_window: WindowId,
+    settings_window: WindowId,
     should_redraw: bool,
+    capture: CaptureConfig,
+    // listening when started with `--osc <port>`
+    osc: Option<OscListener>,
+    // serving when started with `--server <port>`
+    server: Option<Server>,
+    // set when the image is redrawn, for notifying server
+    // clients
+    redrawn: bool,
+    // path typed into the "load from PNG" field
+    load_path: String,
+    preset_name: String,
+    presets: Vec<String>,
+    // last mouse position of a drag in progress
+    drag_from: Option<Vec2>,
+    // noise-space point under the mouse
+    cursor: Option<[f64; 2]>,
 
-    noise: BasicMulti,
+    noise_config: NoiseConfig,
+    noise: Noise,
+    // when `use_graph` is set the graph is drawn instead of
+    // `noise`
+    graph: NoiseGraph,
+    use_graph: bool,
+    graph_path: String,
+    expression: String,
+    // report of the last expression that failed to parse
+    expression_error: Option<String>,
+    viewport: Viewport,
+    // applied to the samples before they're coloured
+    erosion: ErosionConfig,
+    // the last view drawn from complete tiles, eroded, by
+    // what it showed and its width
+    eroded: ErosionCache<(TileSource, Viewport, usize)>,
+    // the map of the eroded heights that's drawn
+    maps: MapSettings,
+    contour: ContourSettings,
+    // isolines of `samples` when they're drawn
+    contours: Vec<Contour>,
+    curl: CurlSettings,
+    // middles and vectors of the curl's arrows when
+    // they're drawn, in pixels
+    arrows: Vec<([f64; 2], [f64; 2])>,
+    // the noise sampled so far, which the view is drawn from
+    tiles: TileCache,
     image: DynamicImage,
+    // raw noise values behind `image`, row-major
+    samples: Vec<f64>,
     size: Vec2, // frame_start: u64,
 }
 
+#[derive(Serialize)]
+struct ExportParams<'a> {
+    sketch: String,
+    seed_name: String,
+    noise: &'a NoiseConfig,
+    viewport: &'a Viewport,
+    width: u32,
+    height: u32,
+    graph: Option<&'a NoiseGraph>,
+    settings: &'a ColorSettings,
+    erosion: &'a ErosionConfig,
+    maps: &'a MapSettings,
+}
+
 impl Model {
     fn new(
-        noise: Option<BasicMulti>,
+        config: SketchConfig,
+        config_file: ConfigFile,
+        noise_config: Option<NoiseConfig>,
         egui: Egui,
         image_window: WindowId,
+        settings_window: WindowId,
     ) -> Self {
         let image = DynamicImage::new_rgb8(10, 10);
+        let noise_config = noise_config.unwrap_or_default();
+
+        let presets = list_presets(&preset_dir(SKETCH));
 
         Model {
-            settings: Settings {
-                lightness: 50.,
-                chroma: 80.,
-                grayscale: false,
-                hue_center: 0.0,
-                hue_range: 180.0,
-            },
+            viewport: Viewport::new(1. / config.noise_step),
+            erosion: ErosionConfig::default(),
+            eroded: ErosionCache::default(),
+            maps: MapSettings::default(),
+            contour: ContourSettings::default(),
+            contours: vec![],
+            curl: CurlSettings::default(),
+            arrows: vec![],
+            tiles: TileCache::new(config.tile_cache),
+            config,
+            config_file,
+            settings: ColorSettings::default(),
+            colors: ColorMap::new(&ColorSettings::default()),
+            color_mode: ColorMode::default(),
             egui,
             should_redraw: false,
+            load_path: String::new(),
+            preset_name: String::new(),
+            presets,
+            drag_from: None,
+            cursor: None,
             image_window,
-            noise: noise.unwrap_or(BasicMulti::new()),
-            image: image,
+            settings_window,
+            capture: CaptureConfig::from_args(),
+            osc: OscListener::from_args(),
+            server: Server::from_args(),
+            redrawn: false,
+            noise: noise_config.build(),
+            noise_config,
+            graph: NoiseGraph::default(),
+            use_graph: false,
+            graph_path: "graph.json".to_string(),
+            expression: String::new(),
+            expression_error: None,
+            image,
+            samples: vec![0.; 100],
             size: Vec2::new(10., 10.),
         }
     }
-    fn update_noise(&mut self, noise: BasicMulti) {
-        self.noise = noise;
+    fn update_noise(&mut self, noise_config: NoiseConfig) {
+        self.noise = noise_config.build();
+        self.noise_config = noise_config;
         self.redraw_image();
     }
+    fn state(&self, app: &App) -> State2d {
+        State2d {
+            sketch: app.exe_name().unwrap(),
+            noise: self.noise_config.clone(),
+            graph: self.drawn_graph().cloned(),
+            settings: self.settings.clone(),
+            viewport: self.viewport,
+            erosion: self.erosion.clone(),
+            maps: self.maps.clone(),
+            contour: self.contour.clone(),
+            curl: self.curl.clone(),
+            color_mode: self.color_mode,
+        }
+    }
+    fn restore(&mut self, state: State2d) {
+        self.settings = state.settings;
+        self.viewport = state.viewport;
+        self.erosion = state.erosion;
+        self.maps = state.maps;
+        self.contour = state.contour;
+        self.curl = state.curl;
+        self.color_mode = state.color_mode;
+        self.use_graph = state.graph.is_some();
+        if let Some(graph) = state.graph {
+            self.graph = graph;
+        }
+        self.update_noise(state.noise);
+    }
+    // draw the graph of the expression, or keep the report
+    // of why it doesn't parse
+    fn parse_expression(&mut self) {
+        match expr::parse(&self.expression) {
+            Ok(graph) => {
+                self.graph = graph;
+                self.use_graph = true;
+                self.expression_error = None;
+                self.should_redraw = true;
+            }
+            Err(error) => {
+                self.expression_error =
+                    Some(error.report(&self.expression))
+            }
+        }
+    }
+    fn drawn_graph(&self) -> Option<&NoiseGraph> {
+        self.use_graph.then_some(&self.graph)
+    }
+    // pixel coordinates in the image for a position in the
+    // window, which has its origin in the center and y up
+    fn pixel(&self, position: Vec2) -> (f64, f64) {
+        (
+            (position.x + self.size.x / 2.) as f64,
+            (self.size.y / 2. - position.y) as f64,
+        )
+    }
     fn update_size(&mut self, size: Vec2) {
         let image = DynamicImage::new_rgb8(
             size.x.floor() as u32,
             size.y.floor() as u32,
         );
         self.image = image;
+        self.samples = vec![
+            0.;
+            (size.x.floor() * size.y.floor())
+                as usize
+        ];
         self.size = size;
-
-        self.redraw_image()
     }
+    // draw the view from the tiles sampled so far, which
+    // update() redraws as more arrive
     fn redraw_image(&mut self) {
-        for (x, y) in (0..self.size.x.floor() as u32)
-            .cartesian_product(
-                0..self.size.y.floor() as u32,
+        self.colors.update(&self.settings);
+        let source = match self.drawn_graph() {
+            Some(graph) => TileSource::Graph(graph.clone()),
+            None => {
+                TileSource::Noise(self.noise_config.clone())
+            }
+        };
+        let image = match self.image.as_mut_rgb8() {
+            Some(image) => image,
+            None => return,
+        };
+        let width = image.width() as usize;
+        let complete = self.tiles.render(
+            &source,
+            &self.viewport,
+            width,
+            &mut self.samples,
+        );
+        // views still waiting for tiles change as they
+        // arrive, so only finished ones are kept
+        if complete {
+            self.eroded.erode(
+                (source, self.viewport, width),
+                &self.erosion,
+                &mut self.samples,
+                width,
+                self.viewport.scale,
+            );
+        } else {
+            erode(
+                &self.erosion,
+                &mut self.samples,
+                width,
+                self.viewport.scale,
+            );
+        }
+        let magnitudes = shown_magnitudes(
+            self.color_mode,
+            &self.maps,
+            (!self.use_graph && !self.erosion.is_enabled())
+                .then_some(&self.noise),
+            &self.viewport,
+            &self.samples,
+            width,
+        );
+        draw_samples(
+            &self.colors,
+            &self.maps,
+            magnitudes.as_deref(),
+            &self.samples,
+            self.viewport.scale,
+            image,
+        );
+        shade_curl(
+            &self.curl,
+            &self.samples,
+            self.viewport.scale,
+            self.noise_config.seed,
+            image,
+        );
+        self.arrows = match self.curl.view {
+            CurlView::Arrows => arrows(
+                &sampled_curl(
+                    &self.samples,
+                    width,
+                    self.viewport.scale,
+                ),
+                width,
+                self.curl.arrow_spacing as usize,
+            ),
+            _ => vec![],
+        };
+        self.contours = if self.contour.enabled {
+            contours(
+                &self.samples,
+                width,
+                &self.contour.values(),
             )
+        } else {
+            vec![]
+        };
+        self.redrawn = true;
+    }
+    // the view sampled at every pixel rather than drawn from
+    // tiles, for captures and exports, which are meant to be
+    // reproducible from the state saved with them
+    fn render_exact(&mut self) -> (RgbImage, Vec<f64>) {
+        self.colors.update(&self.settings);
+        let mut image = RgbImage::new(
+            self.size.x.floor() as u32,
+            self.size.y.floor() as u32,
+        );
+        let mut samples = vec![0.; image.len() / 3];
+        if self.use_graph {
+            self.graph.with_noise(&mut |noise| {
+                render_2d(
+                    noise,
+                    &self.viewport,
+                    &self.colors,
+                    &mut image,
+                    &mut samples,
+                )
+            });
+        } else {
+            render_2d(
+                &self.noise,
+                &self.viewport,
+                &self.colors,
+                &mut image,
+                &mut samples,
+            );
+        }
+        // render_2d coloured the heights before erosion
+        if self.erosion.is_enabled()
+            || self.maps.map != TerrainMap::Height
+            || self.color_mode != ColorMode::Value
+            || self.curl.view == CurlView::Lic
         {
-            let value = self.noise.get([
-                x as f64 / NOISE_STEP,
-                y as f64 / NOISE_STEP,
-            ]);
-            if self.settings.grayscale {
-                let mapped_value =
-                    map_range(value, -1.0, 1.0, 0., 255.);
-                if let Some(buffer) =
-                    self.image.as_mut_rgb8()
-                {
-                    buffer.put_pixel(
-                        x,
-                        y,
-                        nannou::image::Rgb::from([
-                            mapped_value as u8,
-                            mapped_value as u8,
-                            mapped_value as u8,
-                        ]),
-                    );
-                }
-            } else {
-                let mapped_hue: f64 = map_range(
-                    value,
-                    -1.0,
-                    1.0,
-                    self.settings.hue_center
-                        - self.settings.hue_range,
-                    self.settings.hue_center
-                        + self.settings.hue_range,
-                );
-                // 0-100, 0-128,181, -180-180
-                let color = Lch::new(
-                    self.settings.lightness,
-                    self.settings.chroma,
-                    mapped_hue,
-                );
-                let rgb_color: Rgb<f64> =
-                    nannou::color::rgb::Rgb::from(color);
-                let (r, g, b) = rgb_color.into_components();
-                let mapped_r =
-                    map_range(r, -1.0, 1.0, 0., 255.);
-                let mapped_g =
-                    map_range(g, -1.0, 1.0, 0., 255.);
-                let mapped_b =
-                    map_range(b, -1.0, 1.0, 0., 255.);
-                if let Some(buffer) =
-                    self.image.as_mut_rgb8()
-                {
-                    buffer.put_pixel(
-                        x,
-                        y,
-                        nannou::image::Rgb::from([
-                            mapped_r as u8,
-                            mapped_g as u8,
-                            mapped_b as u8,
-                        ]),
-                    );
-                }
+            let width = image.width() as usize;
+            erode(
+                &self.erosion,
+                &mut samples,
+                width,
+                self.viewport.scale,
+            );
+            let magnitudes = shown_magnitudes(
+                self.color_mode,
+                &self.maps,
+                (!self.use_graph
+                    && !self.erosion.is_enabled())
+                .then_some(&self.noise),
+                &self.viewport,
+                &samples,
+                width,
+            );
+            draw_samples(
+                &self.colors,
+                &self.maps,
+                magnitudes.as_deref(),
+                &samples,
+                self.viewport.scale,
+                &mut image,
+            );
+            shade_curl(
+                &self.curl,
+                &samples,
+                self.viewport.scale,
+                self.noise_config.seed,
+                &mut image,
+            );
+        }
+        (image, samples)
+    }
+}
+
+// shade the image with the curl's LIC, when it's shown
+fn shade_curl(
+    curl: &CurlSettings,
+    samples: &[f64],
+    spacing: f64,
+    seed: u32,
+    image: &mut RgbImage,
+) {
+    if curl.view != CurlView::Lic {
+        return;
+    }
+    let width = image.width() as usize;
+    let field = sampled_curl(samples, width, spacing);
+    let values = lic(&field, width, curl.lic_length, seed);
+    shade_lic(&values, image);
+}
+
+// the length of the gradient at each sample when it's what
+// the heights are coloured by: the noise's own where it has
+// an analytic one, or else the samples', which is all there
+// is for graphs and eroded heights
+fn shown_magnitudes(
+    color_mode: ColorMode,
+    maps: &MapSettings,
+    noise: Option<&Noise>,
+    viewport: &Viewport,
+    samples: &[f64],
+    width: usize,
+) -> Option<Vec<f64>> {
+    if color_mode != ColorMode::GradientMagnitude
+        || maps.map != TerrainMap::Height
+    {
+        return None;
+    }
+    let magnitudes = match noise
+        .filter(|noise| noise.has_analytic_gradient())
+    {
+        Some(noise) => {
+            let mut magnitudes = vec![0.; samples.len()];
+            gradient_magnitudes_2d(
+                noise,
+                viewport,
+                width,
+                &mut magnitudes,
+            );
+            magnitudes
+        }
+        None => gradients(samples, width, viewport.scale)
+            .iter()
+            .map(|[dx, dy]| dx.hypot(*dy))
+            .collect(),
+    };
+    Some(magnitudes)
+}
+
+// colour the heights or their gradient's `magnitudes`, or
+// draw the map of them that's shown
+fn draw_samples(
+    colors: &ColorMap,
+    maps: &MapSettings,
+    magnitudes: Option<&[f64]>,
+    samples: &[f64],
+    spacing: f64,
+    image: &mut RgbImage,
+) {
+    match maps.map {
+        TerrainMap::Height => match magnitudes {
+            Some(magnitudes) => {
+                color_magnitudes(colors, magnitudes, image)
             }
+            None => color_image(colors, samples, image),
+        },
+        map => {
+            let values = terrain_map(
+                maps,
+                samples,
+                image.width() as usize,
+                spacing,
+            );
+            draw_terrain_map(map, &values, image);
         }
     }
 }
 
 fn model(app: &App) -> Model {
+    let mut config_file = ConfigFile::from_args(SKETCH);
+    let config: SketchConfig =
+        config_file.poll().unwrap_or_default();
+    if let Some(error) = &config_file.error {
+        eprintln!("{}", error);
+    }
+
     let image_window = app
         .new_window()
-        .size(1200, 630)
+        .size(config.window_width, config.window_height)
         .view(view)
         .key_pressed(key_pressed)
         .mouse_pressed(mouse_pressed)
+        .mouse_released(mouse_released)
+        .mouse_moved(mouse_moved)
+        .mouse_wheel(mouse_wheel)
+        .dropped_file(dropped_file)
         .build()
         .unwrap();
 
@@ -162,7 +566,30 @@ fn model(app: &App) -> Model {
 
     let egui = Egui::from_window(&window);
 
-    Model::new(None, egui, image_window)
+    let mut model = Model::new(
+        config,
+        config_file,
+        None,
+        egui,
+        image_window,
+        window_id,
+    );
+    if let Some(expression) = expression_arg() {
+        model.expression = expression;
+        model.parse_expression();
+        if let Some(error) = &model.expression_error {
+            eprintln!("failed to parse --expr:\n{}", error);
+        }
+    }
+    model
+}
+
+// the value of `--expr <expression>`, to draw instead of
+// the default noise
+fn expression_arg() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    args.find(|arg| arg == "--expr")?;
+    args.next()
 }
 fn raw_window_event(
     _app: &App,
@@ -173,16 +600,61 @@ fn raw_window_event(
     model.egui.handle_raw_event(event);
 }
 fn update(app: &App, model: &mut Model, update: Update) {
+    if let Some(config) = model.config_file.poll() {
+        apply_config(app, model, config);
+    }
+    receive_osc(model);
+    serve_requests(app, model);
+
     let win_rect =
         app.window(model.image_window).unwrap().rect();
 
-    if model.size != win_rect.wh() || model.should_redraw {
+    if model.tiles.receive() {
+        model.should_redraw = true;
+    }
+    if model.size != win_rect.wh() {
         model.update_size(win_rect.wh());
+        model.should_redraw = true;
+    }
+    if model.should_redraw {
         model.redraw_image();
         model.should_redraw = false;
     }
+    if std::mem::take(&mut model.redrawn) {
+        notify_redraw(app, model);
+    }
     let egui = &mut model.egui;
     let settings = &mut model.settings;
+    let load_path = &mut model.load_path;
+    let capture_config = &mut model.capture;
+    let preset_name = &mut model.preset_name;
+    let presets = &model.presets;
+    let viewport = &mut model.viewport;
+    let erosion = &mut model.erosion;
+    let maps = &mut model.maps;
+    let contour = &mut model.contour;
+    let curl = &mut model.curl;
+    let color_mode = &mut model.color_mode;
+    let mut export_contours_clicked = false;
+    let cursor = model.cursor;
+    let noise_step = model.config.noise_step;
+    let center = viewport.point(
+        (model.size.x / 2.) as f64,
+        (model.size.y / 2.) as f64,
+    );
+    let mut noise_config = model.noise_config.clone();
+    let mut load_clicked = false;
+    let mut capture_clicked = false;
+    let mut preset_action = None;
+    let graph = &mut model.graph;
+    let use_graph = &mut model.use_graph;
+    let used_graph = *use_graph;
+    let graph_path = &mut model.graph_path;
+    let expression = &mut model.expression;
+    let expression_error =
+        model.expression_error.as_deref();
+    let mut graph_action = None;
+    let mut graph_changed = false;
 
     egui.set_elapsed_time(update.since_start);
     let ctx = egui.begin_frame();
@@ -190,6 +662,11 @@ fn update(app: &App, model: &mut Model, update: Update) {
     egui::Window::new("Settings").show(&ctx, |ui| {
         let mut changed = false;
 
+        noise_settings(ui, &mut noise_config);
+        if ui.button("random seed").clicked() {
+            noise_config.seed = random_seed();
+        }
+
         // Lightness slider
         let slider = egui::Slider::new(
             &mut settings.lightness,
@@ -230,18 +707,342 @@ fn update(app: &App, model: &mut Model, update: Update) {
                 "Grayscale",
             ))
             .changed();
+        ui.horizontal(|ui| {
+            ui.label("Colour by:");
+            for mode in ColorMode::ALL {
+                changed |= ui
+                    .radio_value(
+                        color_mode,
+                        mode,
+                        mode.name(),
+                    )
+                    .changed();
+            }
+        });
         // Random color button
         let clicked = ui.button("update").clicked();
 
+        ui.separator();
+        ui.label("View:");
+        ui.label(format!(
+            "center: ({:.4}, {:.4})",
+            center[0], center[1]
+        ));
+        if let Some([x, y]) = cursor {
+            ui.label(format!(
+                "cursor: ({:.4}, {:.4})",
+                x, y
+            ));
+        }
+        ui.horizontal(|ui| {
+            ui.label("offset:");
+            let speed = viewport.scale;
+            changed |= ui
+                .add(
+                    egui::DragValue::new(
+                        &mut viewport.offset[0],
+                    )
+                    .speed(speed),
+                )
+                .changed();
+            changed |= ui
+                .add(
+                    egui::DragValue::new(
+                        &mut viewport.offset[1],
+                    )
+                    .speed(speed),
+                )
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("pixels per unit:");
+            let mut zoom = 1. / viewport.scale;
+            if ui
+                .add(
+                    egui::DragValue::new(&mut zoom)
+                        .clamp_range(1.0..=1e9),
+                )
+                .changed()
+            {
+                viewport.scale = 1. / zoom;
+                changed = true;
+            }
+        });
+        if ui.button("reset view").clicked() {
+            *viewport = Viewport::new(1. / noise_step);
+            changed = true;
+        }
+
         if clicked || changed {
             model.should_redraw = true;
         }
+
+        ui.separator();
+        if ui
+            .collapsing("Erosion", |ui| {
+                erosion_settings(ui, erosion)
+            })
+            .body_returned
+            .unwrap_or(false)
+        {
+            model.should_redraw = true;
+        }
+        if ui
+            .collapsing("Terrain maps", |ui| {
+                map_settings(ui, maps)
+            })
+            .body_returned
+            .unwrap_or(false)
+        {
+            model.should_redraw = true;
+        }
+        if ui
+            .collapsing("Curl", |ui| {
+                curl_settings(ui, curl)
+            })
+            .body_returned
+            .unwrap_or(false)
+        {
+            model.should_redraw = true;
+        }
+        if let Some((changed, export)) = ui
+            .collapsing("Isolines", |ui| {
+                contour_settings(ui, contour)
+            })
+            .body_returned
+        {
+            model.should_redraw |= changed;
+            export_contours_clicked = export;
+        }
+
+        ui.separator();
+        ui.label("Load from PNG:");
+        ui.text_edit_singleline(load_path);
+        load_clicked = ui.button("load").clicked();
+
+        ui.separator();
+        preset_action = ui
+            .collapsing("Presets", |ui| {
+                preset_settings(ui, preset_name, presets)
+            })
+            .body_returned
+            .flatten();
+
+        ui.separator();
+        graph_action = ui
+            .collapsing("Graph", |ui| {
+                graph_settings(
+                    ui,
+                    graph,
+                    use_graph,
+                    graph_path,
+                    expression,
+                    expression_error,
+                )
+            })
+            .body_returned
+            .flatten();
+
+        ui.separator();
+        capture_clicked = ui
+            .collapsing("Capture", |ui| {
+                capture_settings(
+                    ui,
+                    capture_config,
+                    &CaptureWindow::ALL,
+                )
+            })
+            .body_returned
+            .unwrap_or(false);
     });
+    if *use_graph {
+        graph_changed = graph_editor(&ctx, graph);
+    }
+    // end the egui frame, releasing the borrow of the model
+    drop(ctx);
+
+    if graph_changed || model.use_graph != used_graph {
+        model.should_redraw = true;
+    }
+    match graph_action {
+        Some(GraphAction::Save) => {
+            if let Err(error) =
+                model.graph.save(&model.graph_path)
+            {
+                eprintln!(
+                    "failed to save graph: {}",
+                    error
+                );
+            }
+        }
+        Some(GraphAction::Load) => {
+            match NoiseGraph::load(&model.graph_path) {
+                Ok(graph) => {
+                    model.graph = graph;
+                    model.use_graph = true;
+                    model.should_redraw = true;
+                }
+                Err(error) => {
+                    eprintln!(
+                        "failed to load graph: {}",
+                        error
+                    )
+                }
+            }
+        }
+        Some(GraphAction::Parse) => {
+            model.parse_expression()
+        }
+        None => {}
+    }
+    if noise_config != model.noise_config {
+        model.update_noise(noise_config);
+    }
+    if load_clicked {
+        let path = PathBuf::from(&model.load_path);
+        load_png(model, &path);
+    }
+    if capture_clicked {
+        capture(app, model);
+    }
+    if export_contours_clicked {
+        export_contours(app, model);
+    }
+    match preset_action {
+        Some(PresetAction::Save(name)) => {
+            let directory = preset_dir(SKETCH);
+            if let Err(error) = save_preset(
+                &directory,
+                &name,
+                &model.state(app),
+            ) {
+                eprintln!(
+                    "failed to save preset: {}",
+                    error
+                );
+            }
+            model.presets = list_presets(&directory);
+        }
+        Some(PresetAction::Load(name)) => {
+            match load_preset(&preset_dir(SKETCH), &name) {
+                Ok(state) => model.restore(state),
+                Err(error) => {
+                    eprintln!(
+                        "failed to load preset: {}",
+                        error
+                    )
+                }
+            }
+        }
+        None => {}
+    }
+}
+
+// apply the parameters sent over OSC since the last frame
+fn receive_osc(model: &mut Model) {
+    let controls = match &mut model.osc {
+        Some(osc) => osc.controls(),
+        None => return,
+    };
+    let mut noise_config = model.noise_config.clone();
+    let settings = model.settings.clone();
+    for control in controls {
+        // there's no animation to speed up in 2D
+        control
+            .apply(&mut noise_config, &mut model.settings);
+    }
+    if noise_config != model.noise_config {
+        model.update_noise(noise_config);
+    } else if model.settings != settings {
+        model.should_redraw = true;
+    }
+}
+
+// sent to server clients after every redraw
+#[derive(Serialize)]
+struct RedrawEvent {
+    event: &'static str,
+    frame: u64,
+    seed: u32,
+}
+
+// answer the requests made to the server since the last
+// frame
+fn serve_requests(app: &App, model: &mut Model) {
+    let requests = match &model.server {
+        Some(server) => server.requests(),
+        None => return,
+    };
+    for request in requests {
+        match &request.command {
+            Command::GetState => {
+                request.respond(Ok(model.state(app)))
+            }
+            Command::SetState(patch) => {
+                let result = patch_state(
+                    &model.state(app),
+                    patch.clone(),
+                )
+                .map(|state| {
+                    model.restore(state);
+                    model.state(app)
+                });
+                request.respond(result);
+            }
+            Command::Capture => {
+                let result = capture(app, model)
+                    .map(|path| Captured { path })
+                    .ok_or_else(|| {
+                        "failed to capture image"
+                            .to_string()
+                    });
+                request.respond(result);
+            }
+        }
+    }
+}
+
+fn notify_redraw(app: &App, model: &Model) {
+    if let Some(server) = &model.server {
+        server.notify(&RedrawEvent {
+            event: "redraw",
+            frame: app.elapsed_frames(),
+            seed: model.noise_config.seed,
+        });
+    }
+}
+
+// apply a reloaded config, redrawing if it changes what's
+// drawn
+fn apply_config(
+    app: &App,
+    model: &mut Model,
+    config: SketchConfig,
+) {
+    if (config.window_width, config.window_height)
+        != (
+            model.config.window_width,
+            model.config.window_height,
+        )
+    {
+        // update() notices the new size and redraws
+        app.window(model.image_window)
+            .unwrap()
+            .set_inner_size_points(
+                config.window_width as f32,
+                config.window_height as f32,
+            );
+    }
+    model.tiles.set_capacity(config.tile_cache);
+    if config.noise_step != model.config.noise_step {
+        model.viewport.scale = 1. / config.noise_step;
+        model.should_redraw = true;
+    }
+    model.config = config;
 }
 
 fn view(app: &App, model: &Model, frame: Frame) {
     let background = rgb(0.439, 0.039, 0.467);
-    let foreground = rgb(0.855, 0.31, 0.671);
 
     // set up containing rectangles
     let win_rect = app.window_rect();
@@ -250,64 +1051,345 @@ fn view(app: &App, model: &Model, frame: Frame) {
     let draw = app.draw();
     draw.background().color(background);
 
-    let texture = Texture::from_image(app, &model.image);
-    draw.texture(&texture);
+    if model.contour.enabled && model.contour.lines_only {
+        draw.background().color(WHITE);
+    } else {
+        let texture =
+            Texture::from_image(app, &model.image);
+        draw.texture(&texture);
+    }
+    draw_arrows(&draw, model);
+    draw_contours(&draw, model);
 
     // display noise seed
-    let seed = model.noise.seed();
-    let seed_bytes = seed.to_be_bytes();
-    let seed_display =
-        std::str::from_utf8(&seed_bytes).unwrap();
-
-    draw.text(&format!("{}", seed_display))
+    draw.text(&seed_name(model.noise_config.seed))
         .font_size(48)
         .wh(win_p.wh())
         .right_justify()
         .align_text_bottom()
         .color(background);
 
+    if let Some(error) = &model.config_file.error {
+        draw_config_error(&draw, win_rect, error);
+    }
+
     // draw to frame
     draw.to_frame(app, &frame).unwrap();
 }
-fn egui_view(app: &App, model: &Model, frame: Frame) {
-    model.egui.draw_to_frame(&frame).unwrap();
+// draw the curl's arrows over the image
+fn draw_arrows(draw: &Draw, model: &Model) {
+    let half = model.size / 2.;
+    // window points for pixel coordinates, y up
+    let point = |[x, y]: [f64; 2]| {
+        pt2(
+            x as f32 + 0.5 - half.x,
+            half.y - 0.5 - y as f32,
+        )
+    };
+    for ([x, y], [dx, dy]) in &model.arrows {
+        draw.arrow()
+            .start(point([x - dx / 2., y - dy / 2.]))
+            .end(point([x + dx / 2., y + dy / 2.]))
+            .weight(2.)
+            .head_length(6.)
+            .head_width(4.)
+            .color(WHITE);
+    }
+}
+
+// draw the isolines over the image, from the middle of
+// each sample's pixel
+fn draw_contours(draw: &Draw, model: &Model) {
+    let [r, g, b] = model.contour.color;
+    let half = model.size / 2.;
+    for contour in &model.contours {
+        for line in &contour.lines {
+            draw.polyline()
+                .weight(model.contour.line_weight)
+                .color(rgb8(r, g, b))
+                .points(line.iter().map(|[x, y]| {
+                    pt2(
+                        *x as f32 + 0.5 - half.x,
+                        half.y - 0.5 - *y as f32,
+                    )
+                }));
+        }
+    }
 }
-fn key_pressed(app: &App, _model: &mut Model, key: Key) {
-    let now = chrono::offset::Local::now();
 
+fn egui_view(_app: &App, model: &Model, frame: Frame) {
+    model.egui.draw_to_frame(&frame).unwrap();
+}
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
     if key == Key::S {
-        app.main_window().capture_frame(format!(
-            "{}{}{}",
-            app.exe_name().unwrap(),
-            now,
-            ".png"
-        ));
+        capture(app, model);
+    }
+    if key == Key::E {
+        export_samples(app, model);
+    }
+    if key == Key::R {
+        randomize_seed(model);
     }
 }
 
+// save the configured capture target. The noise image is
+// saved with the sketch state embedded, so it can be
+// loaded back in later.
+//
+// Returns where the capture goes, or `None` if it failed.
+fn capture(
+    app: &App,
+    model: &mut Model,
+) -> Option<PathBuf> {
+    let path = match model.capture.next_path(
+        &app.exe_name().unwrap(),
+        Some(model.noise_config.seed),
+        "png",
+    ) {
+        Ok(path) => path,
+        Err(error) => {
+            eprintln!("failed to capture image: {}", error);
+            return None;
+        }
+    };
+
+    match model.capture.window {
+        CaptureWindow::Image => {
+            let (image, _) = model.render_exact();
+            if let Err(error) = save_png_with_state(
+                &path,
+                &image,
+                &model.state(app),
+            ) {
+                eprintln!(
+                    "failed to capture image: {}",
+                    error
+                );
+                return None;
+            }
+        }
+        CaptureWindow::Noise => app
+            .window(model.image_window)
+            .unwrap()
+            .capture_frame(&path),
+        CaptureWindow::Settings => app
+            .window(model.settings_window)
+            .unwrap()
+            .capture_frame(&path),
+    }
+    Some(path)
+}
+
+fn load_png(model: &mut Model, path: &Path) {
+    match load_state_from_png(path, SKETCH) {
+        Ok(state) => model.restore(state),
+        Err(error) => eprintln!(
+            "failed to load {}: {}",
+            path.display(),
+            error
+        ),
+    }
+}
+
+fn dropped_file(
+    _app: &App,
+    model: &mut Model,
+    path: PathBuf,
+) {
+    load_png(model, &path);
+}
+
+// write the raw samples as a (height, width) npy array,
+// with the seed and parameters in a json file of the same
+// name, and the terrain map shown if there is one
+fn export_samples(app: &App, model: &mut Model) {
+    let path = match model.capture.next_path(
+        &app.exe_name().unwrap(),
+        Some(model.noise_config.seed),
+        "npy",
+    ) {
+        Ok(path) => path,
+        Err(error) => {
+            eprintln!(
+                "failed to export samples: {}",
+                error
+            );
+            return;
+        }
+    };
+    let (_, samples) = model.render_exact();
+    let params = ExportParams {
+        sketch: app.exe_name().unwrap(),
+        seed_name: seed_name(model.noise_config.seed),
+        noise: &model.noise_config,
+        viewport: &model.viewport,
+        graph: model.drawn_graph(),
+        width: model.size.x.floor() as u32,
+        height: model.size.y.floor() as u32,
+        settings: &model.settings,
+        erosion: &model.erosion,
+        maps: &model.maps,
+    };
+
+    let result = write_npy(
+        &path,
+        &[
+            model.size.y.floor() as usize,
+            model.size.x.floor() as usize,
+        ],
+        &samples,
+    )
+    .and_then(|_| {
+        write_sidecar(path.with_extension("json"), &params)
+    })
+    .and_then(|_| export_map(model, &path, &samples));
+    if let Err(error) = result {
+        eprintln!("failed to export samples: {}", error);
+    }
+}
+
+// write the isolines of the view as an SVG, traced from
+// samples taken at every pixel like exported samples are
+fn export_contours(app: &App, model: &mut Model) {
+    let path = match model.capture.next_path(
+        &app.exe_name().unwrap(),
+        Some(model.noise_config.seed),
+        "svg",
+    ) {
+        Ok(path) => path,
+        Err(error) => {
+            eprintln!(
+                "failed to export isolines: {}",
+                error
+            );
+            return;
+        }
+    };
+    let (image, samples) = model.render_exact();
+    let contours = contours(
+        &samples,
+        image.width() as usize,
+        &model.contour.values(),
+    );
+    if let Err(error) = write_svg(
+        &path,
+        image.width(),
+        image.height(),
+        &model.contour,
+        &contours,
+    ) {
+        eprintln!("failed to export isolines: {}", error);
+    }
+}
+
+// write the map shown of the exported heights next to
+// them, as `<name>-<map>.npy`, with the normal map's
+// components along a third axis
+fn export_map(
+    model: &Model,
+    path: &Path,
+    samples: &[f64],
+) -> std::io::Result<()> {
+    let map = model.maps.map;
+    if map == TerrainMap::Height {
+        return Ok(());
+    }
+    let (width, height) = (
+        model.size.x.floor() as usize,
+        model.size.y.floor() as usize,
+    );
+    let values = terrain_map(
+        &model.maps,
+        samples,
+        width,
+        model.viewport.scale,
+    );
+    let mut shape = vec![height, width];
+    if map.channels() > 1 {
+        shape.push(map.channels());
+    }
+    let stem = path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    write_npy(
+        path.with_file_name(format!(
+            "{}-{}.npy",
+            stem,
+            map.name()
+        )),
+        &shape,
+        &values,
+    )
+}
+
 fn mouse_pressed(
     app: &App,
     model: &mut Model,
-    _button: MouseButton,
+    button: MouseButton,
 ) {
-    // generate a new seed using ascii characters, which
-    // tend to be more human-readable than numbers
-    let mut generator = Generator::default();
-    let seed = generator.next().unwrap();
-    let mut bytes: [u8; 4] = [0; 4];
-    for (i, byte) in
-        seed.as_bytes().iter().take(4).enumerate()
-    {
-        bytes[i] = *byte;
+    if button == MouseButton::Left {
+        model.drag_from = Some(app.mouse.position());
+    }
+}
+
+fn mouse_released(
+    _app: &App,
+    model: &mut Model,
+    button: MouseButton,
+) {
+    if button == MouseButton::Left {
+        model.drag_from = None;
+    }
+}
+
+fn mouse_moved(
+    _app: &App,
+    model: &mut Model,
+    position: Point2,
+) {
+    if let Some(from) = model.drag_from {
+        // window y goes up, image y goes down
+        let delta = position - from;
+        model.viewport.pan(delta.x as f64, -delta.y as f64);
+        model.drag_from = Some(position);
+        model.should_redraw = true;
     }
-    let seed_number: u32 = u32::from_be_bytes(bytes);
+    let (x, y) = model.pixel(position);
+    model.cursor = Some(model.viewport.point(x, y));
+}
+
+fn mouse_wheel(
+    app: &App,
+    model: &mut Model,
+    delta: MouseScrollDelta,
+    _phase: TouchPhase,
+) {
+    let lines = match delta {
+        MouseScrollDelta::LineDelta(_, y) => y as f64,
+        // roughly one line per 50 pixels of trackpad scroll
+        MouseScrollDelta::PixelDelta(position) => {
+            position.y / 50.
+        }
+    };
+    let (x, y) = model.pixel(app.mouse.position());
+    model.viewport.zoom_about(
+        x,
+        y,
+        model.config.zoom_per_line.powf(lines),
+    );
+    model.should_redraw = true;
+}
 
-    // instantiate the new noise
-    let noise = BasicMulti::new();
-    let seeded_noise = noise.set_seed(seed_number);
+fn randomize_seed(model: &mut Model) {
+    // reseed the noise, keeping the rest of its settings
+    let noise_config = NoiseConfig {
+        seed: random_seed(),
+        ..model.noise_config.clone()
+    };
 
     // set relevant values on model,
     // clearing any old data so that data
     // from previous seeds doesn't render
-    model.update_noise(seeded_noise);
+    model.update_noise(noise_config);
 }
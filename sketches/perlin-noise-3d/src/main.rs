@@ -1,164 +1,330 @@
-use itertools::Itertools;
-use names::Generator;
 use nannou::{
-    color::Lch,
     glam::Vec2,
-    image::{DynamicImage, GenericImage},
-    noise::{BasicMulti, NoiseFn, Perlin, Seedable},
+    image::DynamicImage,
     prelude::*,
-    wgpu::Texture,
+    wgpu::{self, Texture},
 };
 use nannou_egui::{self, egui, Egui};
-use rayon::prelude::*;
+use perlin_noise_core::{
+    capture::{CaptureConfig, CaptureWindow},
+    color::{ColorMap, ColorSettings},
+    config::{draw_config_error, ConfigFile},
+    export::{write_npy, write_sidecar},
+    expr,
+    graph::NoiseGraph,
+    graph_editor::{
+        graph_editor, graph_settings, GraphAction,
+    },
+    metadata::{load_state_from_png, save_png_with_state},
+    noise::{Noise, NoiseConfig},
+    osc::{Control, OscListener},
+    preset::{
+        list_presets, load_preset, preset_dir, save_preset,
+    },
+    render::{render_3d, sample_3d},
+    seed::{random_seed, seed_name},
+    server::{patch_state, Captured, Command, Server},
+    state::State3d,
+    timeline::{Parameter, Timeline},
+    ui::{
+        capture_settings, noise_settings, preset_settings,
+        timeline_settings, PresetAction,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+const SKETCH: &str = "perlin-noise-3d";
 
 // interesting variables
 const NOISE_STEP: f64 = 500.;
+// how far along z the noise moves each frame
+const Z_STEP: f64 = 1. / 200.;
+// slices in an exported volume
+const EXPORT_DEPTH: usize = 64;
+// frames drawn by `--headless` without `--frames`
+const HEADLESS_FRAMES: u64 = 100;
+
+// the constants above and the window size, overridden by
+// perlin-noise-3d.toml next to the binary while the sketch
+// runs
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default)]
+struct SketchConfig {
+    noise_step: f64,
+    z_step: f64,
+    // slices in an exported volume, `z_step` apart
+    export_depth: usize,
+    window_width: u32,
+    window_height: u32,
+}
+
+impl Default for SketchConfig {
+    fn default() -> Self {
+        SketchConfig {
+            noise_step: NOISE_STEP,
+            z_step: Z_STEP,
+            export_depth: EXPORT_DEPTH,
+            window_width: 1080,
+            window_height: 1920,
+        }
+    }
+}
+
+// with the `dhat-heap` feature every allocation is
+// recorded, and the profile is written to dhat-heap.json
+// when the sketch exits
+#[cfg(feature = "dhat-heap")]
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+#[cfg(feature = "dhat-heap")]
+static PROFILER: std::sync::Mutex<Option<dhat::Profiler>> =
+    std::sync::Mutex::new(None);
 
 fn main() {
-    nannou::app(model).update(update).run();
+    #[cfg(feature = "dhat-heap")]
+    {
+        *PROFILER.lock().unwrap() =
+            Some(dhat::Profiler::new_heap());
+    }
+
+    if std::env::args().any(|arg| arg == "--headless") {
+        let result = run_headless(
+            frames_arg().unwrap_or(HEADLESS_FRAMES),
+        );
+        stop_profiler();
+        if let Err(error) = result {
+            eprintln!("failed to run headless: {}", error);
+            std::process::exit(1);
+        }
+    } else {
+        nannou::app(model).update(update).exit(exit).run();
+    }
 }
 
-struct Settings {
-    lightness: f64,
-    chroma: f64,
-    grayscale: bool,
-    hue_center: f64,
-    hue_range: f64,
+fn exit(_app: &App, _model: Model) {
+    stop_profiler();
 }
+
+// write the heap profile, if there is one
+fn stop_profiler() {
+    #[cfg(feature = "dhat-heap")]
+    PROFILER.lock().unwrap().take();
+}
+
 struct Model {
-    settings: Settings,
+    config: SketchConfig,
+    config_file: ConfigFile,
+    settings: ColorSettings,
+    // `settings` with their table of hues, brought up to
+    // date before each redraw
+    colors: ColorMap,
     egui: Egui,
     image_window: WindowId,
+    settings_window: WindowId,
     should_redraw: bool,
+    capture: CaptureConfig,
+    // listening when started with `--osc <port>`
+    osc: Option<OscListener>,
+    // serving when started with `--server <port>`
+    server: Option<Server>,
+    // multiplier on `config.z_step`, set over OSC
+    speed: f64,
+    // the sketch quits after this many frames when started
+    // with `--frames <n>`
+    frames: Option<u64>,
+    // path typed into the "load from PNG" field
+    load_path: String,
+    preset_name: String,
+    presets: Vec<String>,
 
-    noise: BasicMulti,
+    noise_config: NoiseConfig,
+    noise: Noise,
+    // when `use_graph` is set the graph is drawn instead of
+    // `noise`
+    graph: NoiseGraph,
+    use_graph: bool,
+    graph_path: String,
+    expression: String,
+    // report of the last expression that failed to parse
+    expression_error: Option<String>,
     image: DynamicImage,
     size: Vec2, // frame_start: u64,
-    points: Vec<(u32, u32)>,
+    // raw noise values behind `image`, row-major, and the
+    // z coordinate of the slice they were sampled at
+    samples: Vec<f64>,
+    drawn_z: f64,
+    // the slice drawn next, moved along every frame
+    z: f64,
+    // size of the noise's features, relative to
+    // `config.noise_step`, set by the timeline
+    scale: f64,
+
+    timeline: Timeline,
+    // position of the timeline's clock, in seconds
+    time: f64,
+    playing: bool,
+}
+
+#[derive(Serialize)]
+struct ExportParams<'a> {
+    sketch: String,
+    seed_name: String,
+    noise: &'a NoiseConfig,
+    noise_step: f64,
+    // the first slice, and the distance between slices
+    z: f64,
+    z_step: f64,
+    width: u32,
+    height: u32,
+    depth: usize,
+    graph: Option<&'a NoiseGraph>,
+    settings: &'a ColorSettings,
 }
 
 impl Model {
     fn new(
-        noise: Option<BasicMulti>,
+        config: SketchConfig,
+        config_file: ConfigFile,
+        noise_config: Option<NoiseConfig>,
         egui: Egui,
         image_window: WindowId,
+        settings_window: WindowId,
     ) -> Self {
         let image = DynamicImage::new_rgb8(10, 10);
+        let noise_config = noise_config.unwrap_or_default();
+
+        let presets = list_presets(&preset_dir(SKETCH));
 
         Model {
-            settings: Settings {
-                lightness: 50.,
-                chroma: 80.,
-                grayscale: false,
-                hue_center: 0.0,
-                hue_range: 180.0,
-            },
+            config,
+            config_file,
+            settings: ColorSettings::default(),
+            colors: ColorMap::new(&ColorSettings::default()),
             egui,
             should_redraw: false,
+            load_path: String::new(),
+            preset_name: String::new(),
+            presets,
             image_window,
-            noise: noise.unwrap_or(BasicMulti::new()),
-            image: image,
+            settings_window,
+            capture: CaptureConfig::from_args(),
+            osc: OscListener::from_args(),
+            server: Server::from_args(),
+            speed: 1.,
+            frames: frames_arg(),
+            noise: noise_config.build(),
+            noise_config,
+            graph: NoiseGraph::default(),
+            use_graph: false,
+            graph_path: "graph.json".to_string(),
+            expression: String::new(),
+            expression_error: None,
+            image,
             size: Vec2::new(10., 10.),
-            points: vec![],
+            samples: vec![0.; 100],
+            drawn_z: 0.,
+            z: 0.,
+            scale: 1.,
+            timeline: Timeline::default(),
+            time: 0.,
+            playing: false,
         }
     }
-    fn update_noise(&mut self, noise: BasicMulti) {
-        self.noise = noise;
+    fn update_noise(&mut self, noise_config: NoiseConfig) {
+        self.noise = noise_config.build();
+        self.noise_config = noise_config;
         // self.redraw_image();
     }
+    fn state(&self, app: &App) -> State3d {
+        State3d {
+            sketch: app.exe_name().unwrap(),
+            noise: self.noise_config.clone(),
+            graph: self.drawn_graph().cloned(),
+            settings: self.settings.clone(),
+            z: self.drawn_z,
+            timeline: self.timeline.clone(),
+        }
+    }
+    fn restore(&mut self, state: State3d) {
+        self.settings = state.settings;
+        self.z = state.z;
+        self.timeline = state.timeline;
+        self.time = 0.;
+        self.use_graph = state.graph.is_some();
+        if let Some(graph) = state.graph {
+            self.graph = graph;
+        }
+        self.update_noise(state.noise);
+    }
+    // draw the graph of the expression, or keep the report
+    // of why it doesn't parse
+    fn parse_expression(&mut self) {
+        match expr::parse(&self.expression) {
+            Ok(graph) => {
+                self.graph = graph;
+                self.use_graph = true;
+                self.expression_error = None;
+                self.should_redraw = true;
+            }
+            Err(error) => {
+                self.expression_error =
+                    Some(error.report(&self.expression))
+            }
+        }
+    }
+    fn drawn_graph(&self) -> Option<&NoiseGraph> {
+        self.use_graph.then_some(&self.graph)
+    }
     fn update_size(&mut self, size: Vec2) {
         let image = DynamicImage::new_rgb8(
             size.x.floor() as u32,
             size.y.floor() as u32,
         );
         self.image = image;
+        self.samples = vec![
+            0.;
+            (size.x.floor() * size.y.floor())
+                as usize
+        ];
         self.size = size;
-        self.points = (0..size.x.floor() as u32)
-            .cartesian_product(0..size.y as u32)
-            .collect()
-
-        // self.redraw_image()
-    }
-    fn redraw_image(&mut self, t: u64) {
-        for (x, y, value) in self
-            .points
-            .par_iter()
-            .map(|(x, y)| {
-                let value = self.noise.get([
-                    *x as f64 / NOISE_STEP,
-                    *y as f64 / NOISE_STEP,
-                    t as f64 / 200.,
-                ]);
-                (x, y, value)
-            })
-            .collect::<Vec<(&u32, &u32, f64)>>()
-        {
-            if self.settings.grayscale {
-                let mapped_value =
-                    map_range(value, -1.0, 1.0, 0., 255.);
-                if let Some(buffer) =
-                    self.image.as_mut_rgb8()
-                {
-                    buffer.put_pixel(
-                        *x,
-                        *y,
-                        nannou::image::Rgb::from([
-                            mapped_value as u8,
-                            mapped_value as u8,
-                            mapped_value as u8,
-                        ]),
-                    );
-                }
-            } else {
-                let mapped_hue: f64 = map_range(
-                    value,
-                    -1.0,
-                    1.0,
-                    self.settings.hue_center
-                        - self.settings.hue_range,
-                    self.settings.hue_center
-                        + self.settings.hue_range,
-                );
-                // 0-100, 0-128,181, -180-180
-                let color = Lch::new(
-                    self.settings.lightness,
-                    self.settings.chroma,
-                    mapped_hue,
-                );
-                let rgb_color: Rgb<f64> =
-                    nannou::color::rgb::Rgb::from(color);
-                let (r, g, b) = rgb_color.into_components();
-                let mapped_r =
-                    map_range(r, -1.0, 1.0, 0., 255.);
-                let mapped_g =
-                    map_range(g, -1.0, 1.0, 0., 255.);
-                let mapped_b =
-                    map_range(b, -1.0, 1.0, 0., 255.);
-                if let Some(buffer) =
-                    self.image.as_mut_rgb8()
-                {
-                    buffer.put_pixel(
-                        *x,
-                        *y,
-                        nannou::image::Rgb::from([
-                            mapped_r as u8,
-                            mapped_g as u8,
-                            mapped_b as u8,
-                        ]),
-                    );
-                }
-            }
+    }
+    fn redraw_image(&mut self) {
+        self.colors.update(&self.settings);
+        if let Some(image) = self.image.as_mut_rgb8() {
+            render_3d(
+                &self.noise,
+                self.use_graph.then_some(&self.graph),
+                self.config.noise_step * self.scale,
+                self.z,
+                &self.colors,
+                image,
+                &mut self.samples,
+            );
         }
+        self.drawn_z = self.z;
     }
 }
 
 fn model(app: &App) -> Model {
+    let mut config_file = ConfigFile::from_args(SKETCH);
+    let config: SketchConfig =
+        config_file.poll().unwrap_or_default();
+    if let Some(error) = &config_file.error {
+        eprintln!("{}", error);
+    }
+
     let image_window = app
         .new_window()
-        .size(1080, 1920)
+        .size(config.window_width, config.window_height)
         .view(view)
         .key_pressed(key_pressed)
         .mouse_pressed(mouse_pressed)
+        .dropped_file(dropped_file)
         .build()
         .unwrap();
 
@@ -172,10 +338,143 @@ fn model(app: &App) -> Model {
 
     let egui = Egui::from_window(&window);
 
-    Model::new(None, egui, image_window)
+    let mut model = Model::new(
+        config,
+        config_file,
+        None,
+        egui,
+        image_window,
+        window_id,
+    );
+    if let Some(path) = load_arg() {
+        load_png(&mut model, &path);
+    }
+    if let Some(expression) = expression_arg() {
+        model.expression = expression;
+        model.parse_expression();
+        if let Some(error) = &model.expression_error {
+            eprintln!("failed to parse --expr:\n{}", error);
+        }
+    }
+    model
+}
+
+// the value of `--expr <expression>`, to draw instead of
+// the default noise
+fn expression_arg() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    args.find(|arg| arg == "--expr")?;
+    args.next()
+}
+
+// the value of `--load <capture>`, to start from instead
+// of the defaults
+fn load_arg() -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    args.find(|arg| arg == "--load")?;
+    args.next().map(PathBuf::from)
+}
+
+// the value of `--frames <n>`
+fn frames_arg() -> Option<u64> {
+    let mut args = std::env::args().skip(1);
+    args.find(|arg| arg == "--frames")?;
+    match args.next().map(|frames| frames.parse()) {
+        Some(Ok(frames)) => Some(frames),
+        _ => {
+            eprintln!("--frames takes a number of frames");
+            None
+        }
+    }
+}
+
+// draw `frames` frames the way update() does, and upload
+// each one to a texture as view() does, without windows or
+// egui, which need a display. `--load`, `--expr` and the
+// config file apply as they do to the sketch.
+fn run_headless(frames: u64) -> Result<(), Box<dyn Error>> {
+    let mut config_file = ConfigFile::from_args(SKETCH);
+    let config: SketchConfig =
+        config_file.poll().unwrap_or_default();
+    if let Some(error) = &config_file.error {
+        eprintln!("{}", error);
+    }
+    let mut state = match load_arg() {
+        Some(path) => load_state_from_png(&path, SKETCH)
+            .map_err(|error| {
+                format!(
+                    "failed to load {}: {}",
+                    path.display(),
+                    error
+                )
+            })?,
+        None => State3d {
+            sketch: SKETCH.to_string(),
+            noise: NoiseConfig::default(),
+            graph: None,
+            settings: ColorSettings::default(),
+            z: 0.,
+            timeline: Timeline::default(),
+        },
+    };
+    if let Some(expression) = expression_arg() {
+        let graph =
+            expr::parse(&expression).map_err(|error| {
+                format!(
+                    "failed to parse --expr:\n{}",
+                    error.report(&expression)
+                )
+            })?;
+        state.graph = Some(graph);
+    }
+    // a device of our own, as there's no window to share
+    // one with
+    let adapter = wgpu::AdapterMap::default()
+        .get_or_request(
+            wgpu::RequestAdapterOptions {
+                power_preference:
+                    wgpu::DEFAULT_POWER_PREFERENCE,
+                force_fallback_adapter: false,
+                compatible_surface: None,
+            },
+            &wgpu::Instance::new(wgpu::DEFAULT_BACKENDS),
+        )
+        .ok_or("no graphics adapter to upload frames to")?;
+    let device = adapter.get_or_request_device(
+        wgpu::default_device_descriptor(),
+    );
+
+    let noise = state.noise.build();
+    let colors = ColorMap::new(&state.settings);
+    let mut image = DynamicImage::new_rgb8(
+        config.window_width,
+        config.window_height,
+    );
+    let mut samples = vec![
+        0.;
+        (config.window_width * config.window_height)
+            as usize
+    ];
+    for _ in 0..frames {
+        if let Some(image) = image.as_mut_rgb8() {
+            render_3d(
+                &noise,
+                state.graph.as_ref(),
+                config.noise_step,
+                state.z,
+                &colors,
+                image,
+                &mut samples,
+            );
+        }
+        Texture::from_image(&*device, &image);
+        device.device().poll(wgpu::Maintain::Poll);
+        state.z += config.z_step;
+    }
+    Ok(())
 }
 fn raw_window_event(
-    app: &App,
+    _app: &App,
     model: &mut Model,
     event: &nannou::winit::event::WindowEvent,
 ) {
@@ -183,6 +482,18 @@ fn raw_window_event(
     model.egui.handle_raw_event(event);
 }
 fn update(app: &App, model: &mut Model, update: Update) {
+    if model.frames.is_some_and(|frames| {
+        app.elapsed_frames() >= frames
+    }) {
+        app.quit();
+        return;
+    }
+    if let Some(config) = model.config_file.poll() {
+        apply_config(app, model, config);
+    }
+    receive_osc(model);
+    serve_requests(app, model);
+
     let win_rect =
         app.window(model.image_window).unwrap().rect();
 
@@ -190,9 +501,40 @@ fn update(app: &App, model: &mut Model, update: Update) {
         model.update_size(win_rect.wh());
         model.should_redraw = false;
     }
-    model.redraw_image(app.elapsed_frames());
+    if model.playing {
+        apply_timeline(model);
+    }
+    model.redraw_image();
+    notify_redraw(app, model);
+    model.z += model.config.z_step * model.speed;
+    if model.playing {
+        match model.timeline.step(model.time) {
+            Some(time) => model.time = time,
+            None => model.playing = false,
+        }
+    }
     let egui = &mut model.egui;
     let settings = &mut model.settings;
+    let load_path = &mut model.load_path;
+    let capture_config = &mut model.capture;
+    let preset_name = &mut model.preset_name;
+    let presets = &model.presets;
+    let mut noise_config = model.noise_config.clone();
+    let mut load_clicked = false;
+    let mut capture_clicked = false;
+    let mut preset_action = None;
+    let graph = &mut model.graph;
+    let use_graph = &mut model.use_graph;
+    let graph_path = &mut model.graph_path;
+    let expression = &mut model.expression;
+    let expression_error =
+        model.expression_error.as_deref();
+    let mut graph_action = None;
+    let timeline = &mut model.timeline;
+    let time = &mut model.time;
+    let playing = &mut model.playing;
+    let (scale, z) = (model.scale, model.drawn_z);
+    let mut timeline_changed = false;
 
     egui.set_elapsed_time(update.since_start);
     let ctx = egui.begin_frame();
@@ -200,6 +542,8 @@ fn update(app: &App, model: &mut Model, update: Update) {
     egui::Window::new("Settings").show(&ctx, |ui| {
         let mut changed = false;
 
+        noise_settings(ui, &mut noise_config);
+
         // Lightness slider
         let slider = egui::Slider::new(
             &mut settings.lightness,
@@ -234,6 +578,13 @@ fn update(app: &App, model: &mut Model, update: Update) {
             ))
             .changed();
 
+        // doesn't change the current slice
+        ui.label("Speed:");
+        ui.add(egui::Slider::new(
+            &mut model.speed,
+            0.0..=10.0,
+        ));
+
         ui.label(app.fps());
 
         // Random color button
@@ -242,12 +593,278 @@ fn update(app: &App, model: &mut Model, update: Update) {
         if clicked || changed {
             model.should_redraw = true;
         }
+
+        ui.separator();
+        ui.label("Load from PNG:");
+        ui.text_edit_singleline(load_path);
+        load_clicked = ui.button("load").clicked();
+
+        ui.separator();
+        preset_action = ui
+            .collapsing("Presets", |ui| {
+                preset_settings(ui, preset_name, presets)
+            })
+            .body_returned
+            .flatten();
+
+        ui.separator();
+        graph_action = ui
+            .collapsing("Graph", |ui| {
+                graph_settings(
+                    ui,
+                    graph,
+                    use_graph,
+                    graph_path,
+                    expression,
+                    expression_error,
+                )
+            })
+            .body_returned
+            .flatten();
+
+        ui.separator();
+        timeline_changed = ui
+            .collapsing("Timeline", |ui| {
+                let current = |parameter: Parameter| {
+                    parameter
+                        .get(&noise_config, settings)
+                        .unwrap_or(match parameter {
+                            Parameter::Scale => scale,
+                            _ => z,
+                        })
+                };
+                timeline_settings(
+                    ui, timeline, time, playing, &current,
+                )
+            })
+            .body_returned
+            .unwrap_or(false);
+
+        ui.separator();
+        capture_clicked = ui
+            .collapsing("Capture", |ui| {
+                capture_settings(
+                    ui,
+                    capture_config,
+                    &CaptureWindow::ALL,
+                )
+            })
+            .body_returned
+            .unwrap_or(false);
     });
+    if *use_graph {
+        // the image is redrawn every frame anyway
+        graph_editor(&ctx, graph);
+    }
+    // end the egui frame, releasing the borrow of the model
+    drop(ctx);
+
+    match graph_action {
+        Some(GraphAction::Save) => {
+            if let Err(error) =
+                model.graph.save(&model.graph_path)
+            {
+                eprintln!(
+                    "failed to save graph: {}",
+                    error
+                );
+            }
+        }
+        Some(GraphAction::Load) => {
+            match NoiseGraph::load(&model.graph_path) {
+                Ok(graph) => {
+                    model.graph = graph;
+                    model.use_graph = true;
+                }
+                Err(error) => {
+                    eprintln!(
+                        "failed to load graph: {}",
+                        error
+                    )
+                }
+            }
+        }
+        Some(GraphAction::Parse) => {
+            model.parse_expression()
+        }
+        None => {}
+    }
+    if noise_config != model.noise_config {
+        model.update_noise(noise_config);
+    }
+    if timeline_changed {
+        apply_timeline(model);
+    }
+    if load_clicked {
+        let path = PathBuf::from(&model.load_path);
+        load_png(model, &path);
+    }
+    if capture_clicked {
+        capture(app, model);
+    }
+    match preset_action {
+        Some(PresetAction::Save(name)) => {
+            let directory = preset_dir(SKETCH);
+            if let Err(error) = save_preset(
+                &directory,
+                &name,
+                &model.state(app),
+            ) {
+                eprintln!(
+                    "failed to save preset: {}",
+                    error
+                );
+            }
+            model.presets = list_presets(&directory);
+        }
+        Some(PresetAction::Load(name)) => {
+            match load_preset(&preset_dir(SKETCH), &name) {
+                Ok(state) => model.restore(state),
+                Err(error) => {
+                    eprintln!(
+                        "failed to load preset: {}",
+                        error
+                    )
+                }
+            }
+        }
+        None => {}
+    }
+}
+
+// set the animated parameters to their values at the
+// timeline's current time
+fn apply_timeline(model: &mut Model) {
+    let mut noise_config = model.noise_config.clone();
+    model.scale = 1.;
+    for (parameter, value) in
+        model.timeline.values_at(model.time)
+    {
+        match parameter {
+            Parameter::Scale => model.scale = value,
+            Parameter::Z => model.z = value,
+            parameter => {
+                parameter.set(
+                    value,
+                    &mut noise_config,
+                    &mut model.settings,
+                );
+            }
+        }
+    }
+    if noise_config != model.noise_config {
+        model.update_noise(noise_config);
+    }
+}
+
+// apply the parameters sent over OSC since the last frame
+fn receive_osc(model: &mut Model) {
+    let controls = match &mut model.osc {
+        Some(osc) => osc.controls(),
+        None => return,
+    };
+    let mut noise_config = model.noise_config.clone();
+    for control in controls {
+        match control {
+            Control::TimeSpeed(speed) => {
+                model.speed = speed
+            }
+            control => {
+                control.apply(
+                    &mut noise_config,
+                    &mut model.settings,
+                );
+            }
+        }
+    }
+    if noise_config != model.noise_config {
+        model.update_noise(noise_config);
+    }
+}
+
+// sent to server clients after every redraw
+#[derive(Serialize)]
+struct RedrawEvent {
+    event: &'static str,
+    frame: u64,
+    seed: u32,
+    // the slice that was drawn
+    z: f64,
+}
+
+// answer the requests made to the server since the last
+// frame
+fn serve_requests(app: &App, model: &mut Model) {
+    let requests = match &model.server {
+        Some(server) => server.requests(),
+        None => return,
+    };
+    for request in requests {
+        match &request.command {
+            Command::GetState => {
+                request.respond(Ok(model.state(app)))
+            }
+            Command::SetState(patch) => {
+                let result = patch_state(
+                    &model.state(app),
+                    patch.clone(),
+                )
+                .map(|state| {
+                    model.restore(state);
+                    model.state(app)
+                });
+                request.respond(result);
+            }
+            Command::Capture => {
+                let result = capture(app, model)
+                    .map(|path| Captured { path })
+                    .ok_or_else(|| {
+                        "failed to capture image"
+                            .to_string()
+                    });
+                request.respond(result);
+            }
+        }
+    }
+}
+
+fn notify_redraw(app: &App, model: &Model) {
+    if let Some(server) = &model.server {
+        server.notify(&RedrawEvent {
+            event: "redraw",
+            frame: app.elapsed_frames(),
+            seed: model.noise_config.seed,
+            z: model.drawn_z,
+        });
+    }
+}
+
+// apply a reloaded config. The image is redrawn every
+// frame, so only the window size needs applying.
+fn apply_config(
+    app: &App,
+    model: &mut Model,
+    config: SketchConfig,
+) {
+    if (config.window_width, config.window_height)
+        != (
+            model.config.window_width,
+            model.config.window_height,
+        )
+    {
+        // update() notices the new size
+        app.window(model.image_window)
+            .unwrap()
+            .set_inner_size_points(
+                config.window_width as f32,
+                config.window_height as f32,
+            );
+    }
+    model.config = config;
 }
 
 fn view(app: &App, model: &Model, frame: Frame) {
     let background = rgb(0.439, 0.039, 0.467);
-    let foreground = rgb(0.855, 0.31, 0.671);
 
     // set up containing rectangles
     let win_rect = app.window_rect();
@@ -260,24 +877,24 @@ fn view(app: &App, model: &Model, frame: Frame) {
     draw.texture(&texture);
 
     // display noise seed
-    let seed = model.noise.seed();
-    let seed_bytes = seed.to_be_bytes();
-    let seed_display =
-        std::str::from_utf8(&seed_bytes).unwrap();
-
-    draw.text(&format!("{}", seed_display))
+    draw.text(&seed_name(model.noise_config.seed))
         .font_size(48)
         .wh(win_p.wh())
         .right_justify()
         .align_text_bottom()
         .color(background);
 
+    if let Some(error) = &model.config_file.error {
+        draw_config_error(&draw, win_rect, error);
+    }
+
     // draw to frame
     draw.to_frame(app, &frame).unwrap();
 
     // let file_path = captured_frame_path(app, &frame);
     // app.main_window().capture_frame(file_path);
 }
+#[allow(dead_code)]
 fn captured_frame_path(
     app: &App,
     frame: &Frame,
@@ -292,45 +909,171 @@ fn captured_frame_path(
         // The extension will be PNG. We also support tiff, bmp, gif, jpeg, webp and some others.
         .with_extension("png")
 }
-fn egui_view(app: &App, model: &Model, frame: Frame) {
+fn egui_view(_app: &App, model: &Model, frame: Frame) {
     model.egui.draw_to_frame(&frame).unwrap();
 }
-fn key_pressed(app: &App, _model: &mut Model, key: Key) {
-    let now = chrono::offset::Local::now();
-
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
     if key == Key::S {
-        app.main_window().capture_frame(format!(
-            "{}{}{}",
-            app.exe_name().unwrap(),
-            now,
-            ".png"
-        ));
+        capture(app, model);
+    }
+    if key == Key::E {
+        export_samples(app, model);
     }
 }
 
-fn mouse_pressed(
+// save the configured capture target. The noise image is
+// saved with the sketch state embedded, so it can be
+// loaded back in later.
+//
+// Returns where the capture goes, or `None` if it failed.
+fn capture(
     app: &App,
     model: &mut Model,
-    _button: MouseButton,
+) -> Option<PathBuf> {
+    let path = match model.capture.next_path(
+        &app.exe_name().unwrap(),
+        Some(model.noise_config.seed),
+        "png",
+    ) {
+        Ok(path) => path,
+        Err(error) => {
+            eprintln!("failed to capture image: {}", error);
+            return None;
+        }
+    };
+
+    match model.capture.window {
+        CaptureWindow::Image => {
+            let image = match model.image.as_rgb8() {
+                Some(image) => image,
+                None => {
+                    eprintln!(
+                        "failed to capture image: the noise \
+                         image isn't RGB"
+                    );
+                    return None;
+                }
+            };
+            if let Err(error) = save_png_with_state(
+                &path,
+                image,
+                &model.state(app),
+            ) {
+                eprintln!(
+                    "failed to capture image: {}",
+                    error
+                );
+                return None;
+            }
+        }
+        CaptureWindow::Noise => app
+            .window(model.image_window)
+            .unwrap()
+            .capture_frame(&path),
+        CaptureWindow::Settings => app
+            .window(model.settings_window)
+            .unwrap()
+            .capture_frame(&path),
+    }
+    Some(path)
+}
+
+fn load_png(model: &mut Model, path: &Path) {
+    match load_state_from_png(path, SKETCH) {
+        Ok(state) => model.restore(state),
+        Err(error) => eprintln!(
+            "failed to load {}: {}",
+            path.display(),
+            error
+        ),
+    }
+}
+
+fn dropped_file(
+    _app: &App,
+    model: &mut Model,
+    path: PathBuf,
 ) {
-    // generate a new seed using ascii characters, which
-    // tend to be more human-readable than numbers
-    let mut generator = Generator::default();
-    let seed = generator.next().unwrap();
-    let mut bytes: [u8; 4] = [0; 4];
-    for (i, byte) in
-        seed.as_bytes().iter().take(4).enumerate()
+    load_png(model, &path);
+}
+
+// write a volume of raw samples, `export_depth` slices
+// `z_step` apart from the current one, as a
+// (depth, height, width) npy array, with the seed and
+// parameters in a json file of the same name
+fn export_samples(app: &App, model: &mut Model) {
+    let path = match model.capture.next_path(
+        &app.exe_name().unwrap(),
+        Some(model.noise_config.seed),
+        "npy",
+    ) {
+        Ok(path) => path,
+        Err(error) => {
+            eprintln!(
+                "failed to export samples: {}",
+                error
+            );
+            return;
+        }
+    };
+    let width = model.size.x.floor() as usize;
+    let height = model.size.y.floor() as usize;
+    let depth = model.config.export_depth.max(1);
+    let z_step = model.config.z_step;
+    let noise_step = model.config.noise_step * model.scale;
+    let params = ExportParams {
+        sketch: app.exe_name().unwrap(),
+        seed_name: seed_name(model.noise_config.seed),
+        noise: &model.noise_config,
+        noise_step,
+        graph: model.drawn_graph(),
+        z: model.drawn_z,
+        z_step,
+        width: width as u32,
+        height: height as u32,
+        depth,
+        settings: &model.settings,
+    };
+
+    let mut volume = vec![0.; depth * height * width];
+    for (slice, samples) in
+        volume.chunks_mut(height * width).enumerate()
     {
-        bytes[i] = *byte;
+        sample_3d(
+            &model.noise,
+            model.drawn_graph(),
+            noise_step,
+            model.drawn_z + slice as f64 * z_step,
+            width,
+            samples,
+        );
+    }
+    let result =
+        write_npy(&path, &[depth, height, width], &volume)
+            .and_then(|_| {
+                write_sidecar(
+                    path.with_extension("json"),
+                    &params,
+                )
+            });
+    if let Err(error) = result {
+        eprintln!("failed to export samples: {}", error);
     }
-    let seed_number: u32 = u32::from_be_bytes(bytes);
+}
 
-    // instantiate the new noise
-    let noise = BasicMulti::new();
-    let seeded_noise = noise.set_seed(seed_number);
+fn mouse_pressed(
+    _app: &App,
+    model: &mut Model,
+    _button: MouseButton,
+) {
+    // reseed the noise, keeping the rest of its settings
+    let noise_config = NoiseConfig {
+        seed: random_seed(),
+        ..model.noise_config.clone()
+    };
 
     // set relevant values on model,
     // clearing any old data so that data
     // from previous seeds doesn't render
-    model.update_noise(seeded_noise);
+    model.update_noise(noise_config);
 }
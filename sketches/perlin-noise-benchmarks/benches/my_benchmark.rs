@@ -1,82 +1,419 @@
 use criterion::{
-    black_box, criterion_group, criterion_main, Criterion,
+    black_box, criterion_group, BenchmarkId, Criterion,
+    Throughput,
 };
-use itertools::Itertools;
-use nannou::noise::BasicMulti;
-use nannou::noise::NoiseFn;
-use nannou::{image::DynamicImage, math::map_range};
-use rayon::iter::{
-    IntoParallelRefIterator, ParallelIterator,
+use nannou::{
+    image::RgbImage,
+    noise::{BasicMulti, NoiseFn},
 };
+use perlin_noise_core::{
+    color::{ColorMap, ColorSettings},
+    expr,
+    graph::NoiseGraph,
+    noise::{NoiseConfig, NoiseType},
+    perlin::Perlin,
+    render::{render_2d, render_3d},
+    viewport::Viewport,
+};
+use rayon::ThreadPoolBuilder;
+use std::thread;
 
-fn get_noise(
-    noise: &BasicMulti,
-    points: &[(u32, u32)],
-    size: (u32, u32),
-) -> DynamicImage {
-    let mut image: DynamicImage =
-        DynamicImage::new_rgb8(size.0, size.1);
-    for (x, y, mapped_value) in points
-        .par_iter()
-        .map(|(x, y)| {
-            let value = noise
-                .get([*x as f64 / 500., *y as f64 / 500.]);
-            let mapped_value =
-                map_range(value, -1.0, 1.0, 0., 255.) as u8;
-            (x, y, mapped_value)
-        })
-        .collect::<Vec<_>>()
+// the sketches' default scale
+const PIXELS_PER_UNIT: f64 = 500.;
+// the grid sampled by the benchmarks that compare noise
+// functions rather than whole images
+const GRID: usize = 256;
+
+fn grayscale() -> ColorSettings {
+    ColorSettings {
+        grayscale: true,
+        ..ColorSettings::default()
+    }
+}
+
+fn grid_points() -> impl Iterator<Item = [f64; 2]> {
+    (0..GRID * GRID).map(|index| {
+        [
+            (index % GRID) as f64 / PIXELS_PER_UNIT,
+            (index / GRID) as f64 / PIXELS_PER_UNIT,
+        ]
+    })
+}
+
+// rendering a whole frame the way the 3D sketch does, in
+// parallel and in grayscale, with its default noise
+fn resolution_benchmark(c: &mut Criterion) {
+    let noise = NoiseConfig::default().build();
+    let colors = ColorMap::new(&grayscale());
+    for (width, height) in [(1920, 1080), (3840, 2160)] {
+        let mut image = RgbImage::new(width, height);
+        let mut samples =
+            vec![0.; (width * height) as usize];
+        c.bench_function(
+            &format!("noise {}x{}", width, height),
+            |b| {
+                b.iter(|| {
+                    render_3d(
+                        black_box(&noise),
+                        None,
+                        PIXELS_PER_UNIT,
+                        0.,
+                        &colors,
+                        &mut image,
+                        &mut samples,
+                    )
+                })
+            },
+        );
+    }
+}
+
+// sampling each noise type in 2D on one thread
+fn noise_type_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("noise type");
+    group.throughput(Throughput::Elements(
+        (GRID * GRID) as u64,
+    ));
+    for noise_type in NoiseType::ALL {
+        let noise = NoiseConfig {
+            noise_type,
+            ..NoiseConfig::default()
+        }
+        .build();
+        group.bench_function(
+            BenchmarkId::from_parameter(noise_type.name()),
+            |b| {
+                b.iter(|| {
+                    grid_points()
+                        .map(|point| noise.get(point))
+                        .sum::<f64>()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+// the same grid sampled in 2D, 3D and 4D, with a generator
+// and a fractal straight from `noise`, which has the only
+// 4D implementations
+fn dimension_benchmark(c: &mut Criterion) {
+    fn bench<T>(
+        group: &mut criterion::BenchmarkGroup<
+            criterion::measurement::WallTime,
+        >,
+        name: &str,
+        noise: &T,
+    ) where
+        T: NoiseFn<[f64; 2]>
+            + NoiseFn<[f64; 3]>
+            + NoiseFn<[f64; 4]>,
     {
-        if let Some(buffer) = image.as_mut_rgb8() {
-            buffer.put_pixel(
-                *x,
-                *y,
-                nannou::image::Rgb::from([
-                    mapped_value,
-                    mapped_value,
-                    mapped_value,
-                ]),
-            );
+        group.bench_function(
+            BenchmarkId::new(name, "2D"),
+            |b| {
+                b.iter(|| {
+                    grid_points()
+                        .map(|point| noise.get(point))
+                        .sum::<f64>()
+                })
+            },
+        );
+        group.bench_function(
+            BenchmarkId::new(name, "3D"),
+            |b| {
+                b.iter(|| {
+                    grid_points()
+                        .map(|[x, y]| {
+                            noise.get([x, y, 0.5])
+                        })
+                        .sum::<f64>()
+                })
+            },
+        );
+        group.bench_function(
+            BenchmarkId::new(name, "4D"),
+            |b| {
+                b.iter(|| {
+                    grid_points()
+                        .map(|[x, y]| {
+                            noise.get([x, y, 0.5, 0.5])
+                        })
+                        .sum::<f64>()
+                })
+            },
+        );
+    }
+
+    let mut group = c.benchmark_group("dimensions");
+    group.throughput(Throughput::Elements(
+        (GRID * GRID) as u64,
+    ));
+    bench(&mut group, "Perlin", &Perlin::new());
+    bench(&mut group, "BasicMulti", &BasicMulti::new());
+    group.finish();
+}
+
+// sampling the grid a point at a time and a row at a time,
+// which is done with SIMD for the Perlin based noise types
+// and OpenSimplex
+fn rows_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rows");
+    group.throughput(Throughput::Elements(
+        (GRID * GRID) as u64,
+    ));
+    let step = 1. / PIXELS_PER_UNIT;
+    let mut row = vec![0.; GRID];
+    for noise_type in [
+        NoiseType::Perlin,
+        NoiseType::OpenSimplex,
+        NoiseType::Fbm,
+        NoiseType::BasicMulti,
+    ] {
+        let noise = NoiseConfig {
+            noise_type,
+            ..NoiseConfig::default()
         }
+        .build();
+        let name = noise_type.name();
+        group.bench_function(
+            BenchmarkId::new(name, "2D points"),
+            |b| {
+                b.iter(|| {
+                    grid_points()
+                        .map(|point| noise.get(point))
+                        .sum::<f64>()
+                })
+            },
+        );
+        group.bench_function(
+            BenchmarkId::new(name, "2D rows"),
+            |b| {
+                b.iter(|| {
+                    for y in 0..GRID {
+                        noise.row_2d(
+                            [0., y as f64 * step],
+                            step,
+                            &mut row,
+                        );
+                        black_box(&row);
+                    }
+                })
+            },
+        );
+        group.bench_function(
+            BenchmarkId::new(name, "3D points"),
+            |b| {
+                b.iter(|| {
+                    grid_points()
+                        .map(|[x, y]| {
+                            noise.get([x, y, 0.5])
+                        })
+                        .sum::<f64>()
+                })
+            },
+        );
+        group.bench_function(
+            BenchmarkId::new(name, "3D rows"),
+            |b| {
+                b.iter(|| {
+                    for y in 0..GRID {
+                        noise.row_3d(
+                            [0., y as f64 * step, 0.5],
+                            step,
+                            &mut row,
+                        );
+                        black_box(&row);
+                    }
+                })
+            },
+        );
     }
-    image
+    group.finish();
 }
 
-fn res_1920x1080_benchmark(c: &mut Criterion) {
-    let noise = BasicMulti::new();
-    let points = (0..1920)
-        .cartesian_product(0..1080)
-        .collect::<Vec<(u32, u32)>>();
-    c.bench_function("noise 1920x1080", |b| {
+// turning a 4K frame of samples into colours, without
+// sampling: grayscale, Lch converted a pixel at a time, and
+// Lch from the hue table the renderers use
+fn color_benchmark(c: &mut Criterion) {
+    let mut image = RgbImage::new(3840, 2160);
+    let mut samples = vec![0.; 3840 * 2160];
+    render_3d(
+        &NoiseConfig::default().build(),
+        None,
+        PIXELS_PER_UNIT,
+        0.,
+        &ColorMap::new(&grayscale()),
+        &mut image,
+        &mut samples,
+    );
+
+    let mut group = c.benchmark_group("colour 3840x2160");
+    group.throughput(Throughput::Elements(
+        samples.len() as u64
+    ));
+    group.sample_size(10);
+    let settings = ColorSettings::default();
+    let colors = ColorMap::new(&settings);
+    group.bench_function("grayscale", |b| {
+        let colors = ColorMap::new(&grayscale());
         b.iter(|| {
-            get_noise(
-                black_box(&noise),
-                black_box(&points),
-                black_box((1920, 1080)),
-            )
+            for (pixel, &value) in
+                image.pixels_mut().zip(&samples)
+            {
+                pixel.0 = colors.color(value);
+            }
+        })
+    });
+    group.bench_function("Lch exact", |b| {
+        b.iter(|| {
+            for (pixel, &value) in
+                image.pixels_mut().zip(&samples)
+            {
+                pixel.0 = settings.color(value);
+            }
         })
     });
+    group.bench_function("Lch table", |b| {
+        b.iter(|| {
+            for (pixel, &value) in
+                image.pixels_mut().zip(&samples)
+            {
+                pixel.0 = colors.color(value);
+            }
+        })
+    });
+    group.finish();
+
+    // what a change of lightness or chroma costs
+    c.bench_function("Lch table", |b| {
+        b.iter(|| ColorMap::new(black_box(&settings)))
+    });
 }
 
-fn res_3840x2160_benchmark(c: &mut Criterion) {
-    let noise = BasicMulti::new();
-    let points = (0..3840)
-        .cartesian_product(0..2160)
-        .collect::<Vec<(u32, u32)>>();
-    c.bench_function("noise 3840x2160", |b| {
+// the 2D sketch's renderer, which runs on one thread, and
+// the 3D sketch's parallel one with rayon limited to 1,
+// 2, 4.. threads up to one per core. Against render_2d,
+// render_3d on one thread also pays for sampling in 3D.
+fn threads_benchmark(c: &mut Criterion) {
+    let noise = NoiseConfig::default().build();
+    let colors = ColorMap::new(&grayscale());
+    let viewport = Viewport::new(1. / PIXELS_PER_UNIT);
+    let mut image = RgbImage::new(1920, 1080);
+    let mut samples = vec![0.; 1920 * 1080];
+
+    let mut group = c.benchmark_group("threads 1920x1080");
+    group.throughput(Throughput::Elements(
+        samples.len() as u64
+    ));
+    group.sample_size(20);
+    group.bench_function("render_2d", |b| {
         b.iter(|| {
-            get_noise(
+            render_2d(
                 black_box(&noise),
-                black_box(&points),
-                black_box((3840, 2160)),
+                &viewport,
+                &colors,
+                &mut image,
+                &mut samples,
             )
         })
     });
+
+    let cores = thread::available_parallelism()
+        .map_or(1, |cores| cores.get());
+    let counts = (0..)
+        .map(|power| 1 << power)
+        .take_while(|&threads| threads < cores)
+        .chain([cores]);
+    for threads in counts {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        group.bench_function(
+            BenchmarkId::new("render_3d", threads),
+            |b| {
+                b.iter(|| {
+                    pool.install(|| {
+                        render_3d(
+                            black_box(&noise),
+                            None,
+                            PIXELS_PER_UNIT,
+                            0.,
+                            &colors,
+                            &mut image,
+                            &mut samples,
+                        )
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn expression_benchmark(
+    c: &mut Criterion,
+    source: &str,
+    graph: &NoiseGraph,
+) {
+    let viewport = Viewport::new(1. / PIXELS_PER_UNIT);
+    let colors = ColorMap::new(&grayscale());
+    let mut image = RgbImage::new(1920, 1080);
+    let mut samples = vec![0.; 1920 * 1080];
+    c.bench_function(
+        &format!("{} 1920x1080", source),
+        |b| {
+            b.iter(|| {
+                graph.with_noise(&mut |noise| {
+                    render_2d(
+                        noise,
+                        black_box(&viewport),
+                        &colors,
+                        &mut image,
+                        &mut samples,
+                    )
+                })
+            })
+        },
+    );
 }
 
 criterion_group!(
     benches,
-    res_1920x1080_benchmark,
-    res_3840x2160_benchmark
+    resolution_benchmark,
+    noise_type_benchmark,
+    dimension_benchmark,
+    rows_benchmark,
+    color_benchmark,
+    threads_benchmark
 );
-criterion_main!(benches);
+// `cargo bench -- --expr <expression>` benchmarks drawing
+// the expression instead. Criterion rejects arguments it
+// doesn't know, so its own options don't apply then.
+fn main() {
+    let mut args = std::env::args().skip(1);
+    if args.any(|arg| arg == "--expr") {
+        let source = args.next().unwrap_or_default();
+        match expr::parse(&source) {
+            Ok(graph) => {
+                let mut criterion = Criterion::default();
+                expression_benchmark(
+                    &mut criterion,
+                    &source,
+                    &graph,
+                );
+                criterion.final_summary();
+            }
+            Err(error) => {
+                eprintln!("{}", error.report(&source))
+            }
+        }
+        return;
+    }
+
+    benches();
+    Criterion::default()
+        .configure_from_args()
+        .final_summary();
+}
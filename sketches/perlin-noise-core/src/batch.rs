@@ -0,0 +1,708 @@
+//! Sampling noise four points at a time with SIMD.
+//!
+//! Perlin noise, the fractals built on it and OpenSimplex
+//! noise are written again over `wide`'s `f64x4`, following
+//! `noise` 0.7 operation for operation, so a batch matches
+//! the scalar functions to within rounding. Only the
+//! permutation table lookups, and picking the vertices of
+//! OpenSimplex's cells, are done a lane at a time.
+use crate::noise::{NoiseConfig, NoiseType};
+use nannou::noise::{BasicMulti, RidgedMulti};
+use rand_07::{seq::SliceRandom, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use wide::f64x4;
+
+/// Points sampled at once.
+pub const LANES: usize = 4;
+
+// the gradients of 3D Perlin noise, indexed by the low four
+// bits of a corner's hash
+#[rustfmt::skip]
+pub(crate) const GRADIENTS_3D: [[f64; 3]; 16] = [
+    [1., 1., 0.], [-1., 1., 0.], [1., -1., 0.], [-1., -1., 0.],
+    [1., 0., 1.], [-1., 0., 1.], [1., 0., -1.], [-1., 0., -1.],
+    [0., 1., 1.], [0., -1., 1.], [0., 1., -1.], [0., -1., -1.],
+    [1., 1., 0.], [-1., 1., 0.], [0., -1., 1.], [0., -1., -1.],
+];
+
+// OpenSimplex's lattice, from `noise`
+pub(crate) const STRETCH_2D: f64 = -0.211_324_865_405_187;
+pub(crate) const SQUISH_2D: f64 = 0.366_025_403_784_439;
+pub(crate) const STRETCH_3D: f64 = -1. / 6.;
+pub(crate) const SQUISH_3D: f64 = 1. / 3.;
+pub(crate) const NORM_2D: f64 = 1. / 14.;
+pub(crate) const NORM_3D: f64 = 1. / 14.;
+
+const DIAG: f64 = std::f64::consts::FRAC_1_SQRT_2;
+const DIAG2: f64 = 0.577_350_269_189_625_8;
+
+// the gradients of OpenSimplex noise, indexed by a
+// vertex's hash
+#[rustfmt::skip]
+pub(crate) const SIMPLEX_GRADIENTS_2D: [[f64; 2]; 8] = [
+    [1., 0.], [-1., 0.], [0., 1.], [0., -1.],
+    [DIAG, DIAG], [-DIAG, DIAG], [DIAG, -DIAG], [-DIAG, -DIAG],
+];
+
+// the twelve edges twice, then the eight corners
+#[rustfmt::skip]
+pub(crate) const SIMPLEX_GRADIENTS_3D: [[f64; 3]; 32] = [
+    [DIAG, DIAG, 0.], [-DIAG, DIAG, 0.], [DIAG, -DIAG, 0.], [-DIAG, -DIAG, 0.],
+    [DIAG, 0., DIAG], [-DIAG, 0., DIAG], [DIAG, 0., -DIAG], [-DIAG, 0., -DIAG],
+    [0., DIAG, DIAG], [0., -DIAG, DIAG], [0., DIAG, -DIAG], [0., -DIAG, -DIAG],
+    [DIAG, DIAG, 0.], [-DIAG, DIAG, 0.], [DIAG, -DIAG, 0.], [-DIAG, -DIAG, 0.],
+    [DIAG, 0., DIAG], [-DIAG, 0., DIAG], [DIAG, 0., -DIAG], [-DIAG, 0., -DIAG],
+    [0., DIAG, DIAG], [0., -DIAG, DIAG], [0., DIAG, -DIAG], [0., -DIAG, -DIAG],
+    [DIAG2, DIAG2, DIAG2], [-DIAG2, DIAG2, DIAG2],
+    [DIAG2, -DIAG2, DIAG2], [-DIAG2, -DIAG2, DIAG2],
+    [DIAG2, DIAG2, -DIAG2], [-DIAG2, DIAG2, -DIAG2],
+    [DIAG2, -DIAG2, -DIAG2], [-DIAG2, -DIAG2, -DIAG2],
+];
+
+// a vertex of an OpenSimplex cell, and its distance from
+// the cell's origin in the squished lattice
+type SimplexVertex = ([i64; 3], [f64; 3]);
+
+/// The vertices whose contributions OpenSimplex noise adds
+/// up for a point whose offsets into its stretched cell sum
+/// to `region`, in the order `noise` adds them.
+pub(crate) fn simplex_vertices_3d(
+    region: f64,
+) -> &'static [SimplexVertex] {
+    // the tetrahedron at (0, 0, 0). `noise` uses the 2D
+    // constant for t1.
+    const LOW: [SimplexVertex; 4] = {
+        let t0 = SQUISH_3D;
+        let t1 = SQUISH_2D + 1.;
+        [
+            ([0, 0, 0], [0., 0., 0.]),
+            ([1, 0, 0], [t1, t0, t0]),
+            ([0, 1, 0], [t0, t1, t0]),
+            ([0, 0, 1], [t0, t0, t1]),
+        ]
+    };
+    // the tetrahedron at (1, 1, 1)
+    const HIGH: [SimplexVertex; 4] = {
+        let t0 = 2. * SQUISH_3D;
+        let t1 = 1. + 2. * SQUISH_3D;
+        let t2 = t1 + SQUISH_3D;
+        [
+            ([1, 1, 0], [t1, t1, t0]),
+            ([1, 0, 1], [t1, t0, t1]),
+            ([0, 1, 1], [t0, t1, t1]),
+            ([1, 1, 1], [t2, t2, t2]),
+        ]
+    };
+    // the octahedron between them
+    const MIDDLE: [SimplexVertex; 6] = {
+        let t0 = SQUISH_3D;
+        let t1 = 1. + SQUISH_3D;
+        let t2 = 2. * SQUISH_3D;
+        let t3 = 1. + 2. * SQUISH_3D;
+        [
+            ([1, 0, 0], [t1, t0, t0]),
+            ([0, 1, 0], [t0, t1, t0]),
+            ([0, 0, 1], [t0, t0, t1]),
+            ([1, 1, 0], [t3, t3, t2]),
+            ([1, 0, 1], [t3, t2, t3]),
+            ([0, 1, 1], [t2, t3, t3]),
+        ]
+    };
+    if region <= 1. {
+        &LOW
+    } else if region >= 2. {
+        &HIGH
+    } else {
+        &MIDDLE
+    }
+}
+
+// a Perlin permutation table. `noise` keeps its own
+// private, so this one is shuffled from the seed the same
+// way, with the same generator.
+#[derive(Clone, Debug)]
+pub(crate) struct PermutationTable([u8; 256]);
+
+impl PermutationTable {
+    pub(crate) fn new(seed: u32) -> Self {
+        let mut rng_seed = [0; 16];
+        rng_seed[0] = 1;
+        for i in 1..4 {
+            rng_seed[i * 4..i * 4 + 4]
+                .copy_from_slice(&seed.to_le_bytes());
+        }
+        let mut rng = XorShiftRng::from_seed(rng_seed);
+        let mut values: Vec<u8> = (0..=255).collect();
+        values.shuffle(&mut rng);
+        let mut table = [0; 256];
+        table.copy_from_slice(&values);
+        PermutationTable(table)
+    }
+
+    pub(crate) fn hash2(&self, x: i64, y: i64) -> usize {
+        let x = self.0[(x & 0xff) as usize] as usize;
+        self.0[x ^ (y & 0xff) as usize] as usize
+    }
+
+    pub(crate) fn hash3(
+        &self,
+        x: i64,
+        y: i64,
+        z: i64,
+    ) -> usize {
+        self.0[self.hash2(x, y) ^ (z & 0xff) as usize]
+            as usize
+    }
+
+    pub(crate) fn hash4(
+        &self,
+        [x, y, z, w]: [i64; 4],
+    ) -> usize {
+        self.0[self.hash3(x, y, z) ^ (w & 0xff) as usize]
+            as usize
+    }
+}
+
+fn s_curve5(t: f64x4) -> f64x4 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn clamp(value: f64x4, low: f64, high: f64) -> f64x4 {
+    value.max(f64x4::splat(low)).min(f64x4::splat(high))
+}
+
+// `noise`'s scale_shift
+fn scale_shift(value: f64x4, n: f64) -> f64x4 {
+    value.abs().mul_add(f64x4::splat(n), f64x4::splat(-1.))
+}
+
+fn perlin_2d(
+    table: &PermutationTable,
+    [x, y]: [f64x4; 2],
+) -> f64x4 {
+    let floored = [x.floor(), y.floor()];
+    let near = [x - floored[0], y - floored[1]];
+    let far = [near[0] - 1., near[1] - 1.];
+
+    // each corner's gradient is (±1, ±1), picked by the
+    // low two bits of its hash. Corners are numbered by
+    // their offsets from the cell's origin, x in the lowest
+    // bit.
+    let cells = floored.map(|floored| floored.to_array());
+    let mut signs = [[[0.; LANES]; 2]; 4];
+    for lane in 0..LANES {
+        let [x, y] = cells.map(|cell| cell[lane] as i64);
+        for (corner, signs) in signs.iter_mut().enumerate()
+        {
+            let hash = table.hash2(
+                x + (corner & 1) as i64,
+                y + (corner >> 1) as i64,
+            );
+            signs[0][lane] =
+                if hash & 1 == 0 { 1. } else { -1. };
+            signs[1][lane] =
+                if hash & 2 == 0 { 1. } else { -1. };
+        }
+    }
+    let dot = |corner: usize, x: f64x4, y: f64x4| {
+        let [sx, sy] = signs[corner].map(f64x4::from);
+        sx * x + sy * y
+    };
+    let [nx, ny] = near;
+    let [fx, fy] = far;
+    let g00 = dot(0b00, nx, ny);
+    let g10 = dot(0b01, fx, ny);
+    let g01 = dot(0b10, nx, fy);
+    let g11 = dot(0b11, fx, fy);
+
+    let u = s_curve5(near[0]);
+    let v = s_curve5(near[1]);
+
+    let k0 = g00;
+    let k1 = g10 - g00;
+    let k2 = g01 - g00;
+    let k3 = g00 + g11 - g10 - g01;
+    let unscaled = k0 + k1 * u + k2 * v + k3 * u * v;
+
+    clamp(unscaled * 2f64.sqrt(), -1., 1.)
+}
+
+#[allow(clippy::many_single_char_names)]
+fn perlin_3d(
+    table: &PermutationTable,
+    [x, y, z]: [f64x4; 3],
+) -> f64x4 {
+    let floored = [x.floor(), y.floor(), z.floor()];
+    let near =
+        [x - floored[0], y - floored[1], z - floored[2]];
+    let far = near.map(|near| near - 1.);
+
+    // corners are numbered by their offsets from the
+    // cell's origin, x in the lowest bit
+    let cells = floored.map(|floored| floored.to_array());
+    let mut gradients = [[[0.; LANES]; 3]; 8];
+    for lane in 0..LANES {
+        let [x, y, z] = cells.map(|cell| cell[lane] as i64);
+        for (corner, gradient) in
+            gradients.iter_mut().enumerate()
+        {
+            let hash = table.hash3(
+                x + (corner & 1) as i64,
+                y + (corner >> 1 & 1) as i64,
+                z + (corner >> 2) as i64,
+            );
+            let [gx, gy, gz] = GRADIENTS_3D[hash & 0b1111];
+            gradient[0][lane] = gx;
+            gradient[1][lane] = gy;
+            gradient[2][lane] = gz;
+        }
+    }
+    let dot =
+        |corner: usize, x: f64x4, y: f64x4, z: f64x4| {
+            let [gx, gy, gz] =
+                gradients[corner].map(f64x4::from);
+            gx * x + gy * y + gz * z
+        };
+    let [nx, ny, nz] = near;
+    let [fx, fy, fz] = far;
+    let g000 = dot(0b000, nx, ny, nz);
+    let g100 = dot(0b001, fx, ny, nz);
+    let g010 = dot(0b010, nx, fy, nz);
+    let g110 = dot(0b011, fx, fy, nz);
+    let g001 = dot(0b100, nx, ny, fz);
+    let g101 = dot(0b101, fx, ny, fz);
+    let g011 = dot(0b110, nx, fy, fz);
+    let g111 = dot(0b111, fx, fy, fz);
+
+    let a = s_curve5(near[0]);
+    let b = s_curve5(near[1]);
+    let c = s_curve5(near[2]);
+
+    let k0 = g000;
+    let k1 = g100 - g000;
+    let k2 = g010 - g000;
+    let k3 = g001 - g000;
+    let k4 = g000 + g110 - g100 - g010;
+    let k5 = g000 + g101 - g100 - g001;
+    let k6 = g000 + g011 - g010 - g001;
+    let k7 = g100 + g010 + g001 + g111
+        - g000
+        - g110
+        - g101
+        - g011;
+    let unscaled = k0
+        + k1 * a
+        + k2 * b
+        + k3 * c
+        + k4 * a * b
+        + k5 * a * c
+        + k6 * b * c
+        + k7 * a * b * c;
+
+    clamp(unscaled * (2. / 3f64.sqrt()), -1., 1.)
+}
+
+// the contributions of a vertex per lane, with gradients
+// `gradients` at `offset` from the points, to OpenSimplex
+// noise
+fn surflets<const D: usize>(
+    gradients: [[f64; LANES]; D],
+    offset: [f64x4; D],
+) -> f64x4 {
+    let mut length = f64x4::ZERO;
+    let mut along = f64x4::ZERO;
+    for (offset, gradient) in
+        offset.into_iter().zip(gradients)
+    {
+        length += offset * offset;
+        along += offset * f64x4::from(gradient);
+    }
+    // vertices further than the attenuation reaches add
+    // nothing
+    let attenuation =
+        (f64x4::splat(2.) - length).max(f64x4::ZERO);
+    let squared = attenuation * attenuation;
+    squared * squared * along
+}
+
+fn open_simplex_2d(
+    table: &PermutationTable,
+    [x, y]: [f64x4; 2],
+) -> f64x4 {
+    let stretch = (x + y) * STRETCH_2D;
+    let stretched = [x + stretch, y + stretch];
+    let floored =
+        stretched.map(|stretched| stretched.floor());
+    let squish = (floored[0] + floored[1]) * SQUISH_2D;
+    let region = stretched[0] - floored[0] + stretched[1]
+        - floored[1];
+    let origin = [
+        x - (floored[0] + squish),
+        y - (floored[1] + squish),
+    ];
+
+    let t0 = SQUISH_2D;
+    let t1 = SQUISH_2D + 1.;
+    let t2 = SQUISH_2D + t1;
+    // the rhombus is split into two triangles along the
+    // diagonal from (1, 0) to (0, 1), and the third vertex
+    // is the corner of the lane's triangle off it
+    let cells = floored.map(|floored| floored.to_array());
+    let regions = region.to_array();
+    let mut gradients = [[[0.; LANES]; 2]; 3];
+    let mut third_shift = [0.; LANES];
+    for lane in 0..LANES {
+        let [x, y] = cells.map(|cell| cell[lane] as i64);
+        let third = if regions[lane] > 1. {
+            third_shift[lane] = t2;
+            [1, 1]
+        } else {
+            [0, 0]
+        };
+        for (vertex, [dx, dy]) in
+            [[1, 0], [0, 1], third].into_iter().enumerate()
+        {
+            let hash = table.hash2(x + dx, y + dy);
+            let [gx, gy] = SIMPLEX_GRADIENTS_2D[hash % 8];
+            gradients[vertex][0][lane] = gx;
+            gradients[vertex][1][lane] = gy;
+        }
+    }
+    let third_shift = f64x4::from(third_shift);
+    let shifts = [
+        [f64x4::splat(t1), f64x4::splat(t0)],
+        [f64x4::splat(t0), f64x4::splat(t1)],
+        [third_shift, third_shift],
+    ];
+    let mut value = f64x4::ZERO;
+    for (gradients, [sx, sy]) in
+        gradients.into_iter().zip(shifts)
+    {
+        value += surflets(
+            gradients,
+            [origin[0] - sx, origin[1] - sy],
+        );
+    }
+    value * NORM_2D
+}
+
+fn open_simplex_3d(
+    table: &PermutationTable,
+    [x, y, z]: [f64x4; 3],
+) -> f64x4 {
+    let stretch = (x + y + z) * STRETCH_3D;
+    let stretched = [x + stretch, y + stretch, z + stretch];
+    let floored =
+        stretched.map(|stretched| stretched.floor());
+    let squish =
+        (floored[0] + floored[1] + floored[2]) * SQUISH_3D;
+    let region = (stretched[0] - floored[0])
+        + (stretched[1] - floored[1])
+        + (stretched[2] - floored[2]);
+    let origin = [
+        x - (floored[0] + squish),
+        y - (floored[1] + squish),
+        z - (floored[2] + squish),
+    ];
+
+    // lanes in a tetrahedron have four vertices and ones in
+    // the octahedron six, so the last two of a tetrahedron's
+    // have no gradient and add nothing
+    let cells = floored.map(|floored| floored.to_array());
+    let regions = region.to_array();
+    let mut gradients = [[[0.; LANES]; 3]; 6];
+    let mut shifts = [[[0.; LANES]; 3]; 6];
+    for lane in 0..LANES {
+        let [x, y, z] = cells.map(|cell| cell[lane] as i64);
+        for (vertex, &([dx, dy, dz], shift)) in
+            simplex_vertices_3d(regions[lane])
+                .iter()
+                .enumerate()
+        {
+            let hash = table.hash3(x + dx, y + dy, z + dz);
+            let gradient = SIMPLEX_GRADIENTS_3D[hash % 32];
+            for axis in 0..3 {
+                gradients[vertex][axis][lane] =
+                    gradient[axis];
+                shifts[vertex][axis][lane] = shift[axis];
+            }
+        }
+    }
+    let mut value = f64x4::ZERO;
+    for (gradients, shift) in
+        gradients.into_iter().zip(shifts)
+    {
+        let [sx, sy, sz] = shift.map(f64x4::from);
+        value += surflets(
+            gradients,
+            [
+                origin[0] - sx,
+                origin[1] - sy,
+                origin[2] - sz,
+            ],
+        );
+    }
+    value * NORM_3D
+}
+
+// four points in 2D or 3D, so the fractals are written once
+trait Points: Copy {
+    fn scale(self, factor: f64) -> Self;
+    fn perlin(self, table: &PermutationTable) -> f64x4;
+    fn open_simplex(
+        self,
+        table: &PermutationTable,
+    ) -> f64x4;
+}
+
+impl Points for [f64x4; 2] {
+    fn scale(self, factor: f64) -> Self {
+        self.map(|axis| axis * factor)
+    }
+
+    fn perlin(self, table: &PermutationTable) -> f64x4 {
+        perlin_2d(table, self)
+    }
+
+    fn open_simplex(
+        self,
+        table: &PermutationTable,
+    ) -> f64x4 {
+        open_simplex_2d(table, self)
+    }
+}
+
+impl Points for [f64x4; 3] {
+    fn scale(self, factor: f64) -> Self {
+        self.map(|axis| axis * factor)
+    }
+
+    fn perlin(self, table: &PermutationTable) -> f64x4 {
+        perlin_3d(table, self)
+    }
+
+    fn open_simplex(
+        self,
+        table: &PermutationTable,
+    ) -> f64x4 {
+        open_simplex_3d(table, self)
+    }
+}
+
+/// Noise that samples four points at a time, for the noise
+/// types built on Perlin noise and for OpenSimplex noise.
+#[derive(Clone, Debug)]
+pub struct BatchNoise {
+    noise_type: NoiseType,
+    // a table per octave, just one for the others
+    tables: Vec<PermutationTable>,
+    frequency: f64,
+    lacunarity: f64,
+    persistence: f64,
+}
+
+impl BatchNoise {
+    /// The SIMD version of the noise `config` describes, or
+    /// `None` when it only has a scalar one: for
+    /// SuperSimplex, value and Worley noise, and for any
+    /// domain warp.
+    pub fn new(config: &NoiseConfig) -> Option<Self> {
+        if config.warp.iterations > 0 {
+            return None;
+        }
+        let octaves = match config.noise_type {
+            NoiseType::Perlin | NoiseType::OpenSimplex => 1,
+            noise_type if noise_type.is_fractal() => config
+                .octaves
+                .clamp(1, BasicMulti::MAX_OCTAVES),
+            _ => return None,
+        };
+        // fractals seed their octaves with consecutive
+        // seeds
+        let tables = (0..octaves)
+            .map(|octave| {
+                PermutationTable::new(
+                    config.seed.wrapping_add(octave as u32),
+                )
+            })
+            .collect();
+        Some(BatchNoise {
+            noise_type: config.noise_type,
+            tables,
+            frequency: config.frequency,
+            lacunarity: config.lacunarity,
+            persistence: config.persistence,
+        })
+    }
+
+    pub fn get2(&self, points: [f64x4; 2]) -> f64x4 {
+        self.get(points)
+    }
+
+    pub fn get3(&self, points: [f64x4; 3]) -> f64x4 {
+        self.get(points)
+    }
+
+    /// Sample `row.len()` points, starting at `start` and
+    /// `step` apart along x.
+    pub fn row_2d(
+        &self,
+        start: [f64; 2],
+        step: f64,
+        row: &mut [f64],
+    ) {
+        for_lanes(start[0], step, row, |x| {
+            self.get2([x, f64x4::splat(start[1])])
+        });
+    }
+
+    /// Sample `row.len()` points, starting at `start` and
+    /// `step` apart along x.
+    pub fn row_3d(
+        &self,
+        start: [f64; 3],
+        step: f64,
+        row: &mut [f64],
+    ) {
+        for_lanes(start[0], step, row, |x| {
+            self.get3([
+                x,
+                f64x4::splat(start[1]),
+                f64x4::splat(start[2]),
+            ])
+        });
+    }
+
+    fn get<P: Points>(&self, points: P) -> f64x4 {
+        match self.noise_type {
+            NoiseType::Fbm => self.fbm(points),
+            NoiseType::Billow => self.billow(points),
+            NoiseType::BasicMulti => {
+                self.basic_multi(points)
+            }
+            NoiseType::HybridMulti => {
+                self.hybrid_multi(points)
+            }
+            NoiseType::RidgedMulti => {
+                self.ridged_multi(points)
+            }
+            NoiseType::OpenSimplex => {
+                points.open_simplex(&self.tables[0])
+            }
+            _ => points.perlin(&self.tables[0]),
+        }
+    }
+
+    fn amplitude(&self, octave: usize) -> f64 {
+        self.persistence.powi(octave as i32)
+    }
+
+    fn fbm<P: Points>(&self, points: P) -> f64x4 {
+        let mut points = points.scale(self.frequency);
+        let mut result = f64x4::ZERO;
+        for (octave, table) in
+            self.tables.iter().enumerate()
+        {
+            let signal = points.perlin(table)
+                * self.amplitude(octave);
+            result += signal;
+            points = points.scale(self.lacunarity);
+        }
+        let scale =
+            2. - self.amplitude(self.tables.len() - 1);
+        result / scale
+    }
+
+    fn billow<P: Points>(&self, points: P) -> f64x4 {
+        let mut points = points.scale(self.frequency);
+        let mut result = f64x4::ZERO;
+        for (octave, table) in
+            self.tables.iter().enumerate()
+        {
+            let signal =
+                scale_shift(points.perlin(table), 2.)
+                    * self.amplitude(octave);
+            result += signal;
+            points = points.scale(self.lacunarity);
+        }
+        result * 0.5
+    }
+
+    fn basic_multi<P: Points>(&self, points: P) -> f64x4 {
+        let mut points = points.scale(self.frequency);
+        let mut result = points.perlin(&self.tables[0]);
+        for (octave, table) in
+            self.tables.iter().enumerate().skip(1)
+        {
+            points = points.scale(self.lacunarity);
+            let signal = points.perlin(table)
+                * self.amplitude(octave);
+            result += signal * result;
+        }
+        result * 0.5
+    }
+
+    fn hybrid_multi<P: Points>(&self, points: P) -> f64x4 {
+        let mut points = points.scale(self.frequency);
+        let mut result = points.perlin(&self.tables[0])
+            * self.persistence;
+        let mut weight = result;
+        for (octave, table) in
+            self.tables.iter().enumerate().skip(1)
+        {
+            weight = weight.max(f64x4::ONE);
+            points = points.scale(self.lacunarity);
+            let signal = points.perlin(table)
+                * self.amplitude(octave);
+            result += weight * signal;
+            weight *= signal;
+        }
+        result * 3.
+    }
+
+    fn ridged_multi<P: Points>(&self, points: P) -> f64x4 {
+        let mut points = points.scale(self.frequency);
+        let mut result = f64x4::ZERO;
+        let mut weight = f64x4::ONE;
+        for (octave, table) in
+            self.tables.iter().enumerate()
+        {
+            let mut signal =
+                f64x4::ONE - points.perlin(table).abs();
+            signal *= signal;
+            signal *= weight;
+            weight = clamp(
+                signal / RidgedMulti::DEFAULT_ATTENUATION,
+                0.,
+                1.,
+            );
+            result += signal * self.amplitude(octave);
+            points = points.scale(self.lacunarity);
+        }
+        let scale =
+            2. - 0.5f64.powi(self.tables.len() as i32 - 1);
+        scale_shift(result, 2. / scale)
+    }
+}
+
+// fill `row` four values at a time from `sample`, given the
+// x coordinates of the four points. The last lanes of a row
+// that isn't a multiple of four repeat its last point.
+fn for_lanes(
+    start: f64,
+    step: f64,
+    row: &mut [f64],
+    sample: impl Fn(f64x4) -> f64x4,
+) {
+    let last = row.len().saturating_sub(1);
+    for (chunk, values) in row.chunks_mut(LANES).enumerate()
+    {
+        let mut x = [0.; LANES];
+        for (lane, x) in x.iter_mut().enumerate() {
+            let index = (chunk * LANES + lane).min(last);
+            *x = start + index as f64 * step;
+        }
+        let samples = sample(f64x4::from(x)).to_array();
+        values.copy_from_slice(&samples[..values.len()]);
+    }
+}
@@ -0,0 +1,140 @@
+//! Render the frames of a 3D sketch preset's timeline
+//! without opening a window.
+//!
+//! ```text
+//! animate <preset file> [--frames <n>]
+//!         [--size <width>x<height>]
+//!         [--noise-step <pixels per unit>] [--z-step <z>]
+//! ```
+//!
+//! Frame `n` is the timeline at `n / 60` seconds, as the
+//! sketch's clock plays it, starting from the preset's z and
+//! moving `--z-step` along z each frame unless z is
+//! animated. Without `--frames` one play through of the
+//! timeline is rendered. The frames are numbered PNGs in a
+//! directory named like the sketch's captures, each one
+//! loadable by the sketch.
+use nannou::image::RgbImage;
+use perlin_noise_core::{
+    capture::CaptureConfig,
+    color::ColorMap,
+    metadata::save_png_with_state,
+    render::render_3d,
+    state::State3d,
+    timeline::{Parameter, FRAME_RATE},
+};
+use std::{env, error::Error, fs, path::PathBuf, process};
+
+const SKETCH: &str = "perlin-noise-3d";
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("animate: {}", error);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut preset = None;
+    let mut frames = None;
+    // the 3D sketch's defaults
+    let (mut width, mut height) = (1080, 1920);
+    let mut noise_step = 500.;
+    let mut z_step = 1. / 200.;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().ok_or_else(|| {
+                format!("{} needs a value", arg)
+            })
+        };
+        match arg.as_str() {
+            "--frames" => frames = Some(value()?.parse()?),
+            "--size" => {
+                let size = value()?;
+                let (w, h) = size.split_once('x').ok_or(
+                    "--size takes <width>x<height>",
+                )?;
+                width = w.parse()?;
+                height = h.parse()?;
+            }
+            "--noise-step" => {
+                noise_step = value()?.parse()?
+            }
+            "--z-step" => z_step = value()?.parse()?,
+            // read by CaptureConfig::from_args
+            "--capture-dir" | "--capture-template" => {
+                value()?;
+            }
+            _ if preset.is_none()
+                && !arg.starts_with("--") =>
+            {
+                preset = Some(PathBuf::from(arg))
+            }
+            _ => {
+                return Err(format!(
+                    "unknown argument {}",
+                    arg
+                )
+                .into())
+            }
+        }
+    }
+
+    let preset = preset.ok_or("no preset file given")?;
+    let start: State3d = serde_json::from_str(
+        &fs::read_to_string(&preset)?,
+    )?;
+    let frames =
+        frames.unwrap_or_else(|| start.timeline.frames());
+
+    let mut capture = CaptureConfig::from_args();
+    let directory = capture
+        .next_path(SKETCH, Some(start.noise.seed), "png")?
+        .with_extension("");
+    fs::create_dir_all(&directory)?;
+
+    let mut image = RgbImage::new(width, height);
+    let mut samples = vec![0.; (width * height) as usize];
+    let mut state = start.clone();
+    let mut colors = ColorMap::new(&state.settings);
+    for frame in 0..frames {
+        let time = frame as f64 / FRAME_RATE;
+        let mut scale = 1.;
+        for (parameter, value) in
+            start.timeline.values_at(time)
+        {
+            match parameter {
+                Parameter::Scale => scale = value,
+                Parameter::Z => state.z = value,
+                parameter => {
+                    parameter.set(
+                        value,
+                        &mut state.noise,
+                        &mut state.settings,
+                    );
+                }
+            }
+        }
+
+        colors.update(&state.settings);
+        render_3d(
+            &state.noise.build(),
+            state.graph.as_ref(),
+            noise_step * scale,
+            state.z,
+            &colors,
+            &mut image,
+            &mut samples,
+        );
+        let path =
+            directory.join(format!("{:05}.png", frame));
+        save_png_with_state(&path, &image, &state)?;
+
+        state.z += z_step;
+    }
+
+    println!("{}", directory.display());
+    Ok(())
+}
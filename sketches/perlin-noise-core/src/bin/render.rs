@@ -0,0 +1,119 @@
+//! Render noise to a PNG without opening a window.
+//!
+//! ```text
+//! render [--graph <file> | --expr <expression>]
+//!        [--size <width>x<height>]
+//!        [--scale <pixels per unit>] [--grayscale]
+//!        [--out <file>]
+//! ```
+//!
+//! Without `--graph` or `--expr` the default noise is rendered. Without
+//! `--out` the image is named like the sketches' captures.
+use nannou::image::RgbImage;
+use perlin_noise_core::{
+    capture::CaptureConfig,
+    color::{ColorMap, ColorSettings},
+    expr,
+    graph::NoiseGraph,
+    metadata::save_png_with_state,
+    render::render_2d,
+    viewport::Viewport,
+};
+use serde::Serialize;
+use std::{env, error::Error, path::PathBuf, process};
+
+#[derive(Serialize)]
+struct RenderState {
+    sketch: String,
+    graph: NoiseGraph,
+    settings: ColorSettings,
+    viewport: Viewport,
+}
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("render: {}", error);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut graph = NoiseGraph::default();
+    let (mut width, mut height) = (1200, 630);
+    let mut pixels_per_unit = 500.;
+    let mut settings = ColorSettings::default();
+    let mut out = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().ok_or_else(|| {
+                format!("{} needs a value", arg)
+            })
+        };
+        match arg.as_str() {
+            "--graph" => {
+                graph = NoiseGraph::load(value()?)?
+            }
+            "--expr" => {
+                let source = value()?;
+                graph = expr::parse(&source).map_err(
+                    |error| error.report(&source),
+                )?
+            }
+            "--size" => {
+                let size = value()?;
+                let (w, h) = size.split_once('x').ok_or(
+                    "--size takes <width>x<height>",
+                )?;
+                width = w.parse()?;
+                height = h.parse()?;
+            }
+            "--scale" => {
+                pixels_per_unit = value()?.parse()?
+            }
+            "--grayscale" => settings.grayscale = true,
+            "--out" => out = Some(PathBuf::from(value()?)),
+            // read by CaptureConfig::from_args
+            "--capture-dir" | "--capture-template" => {
+                value()?;
+            }
+            _ => {
+                return Err(format!(
+                    "unknown argument {}",
+                    arg
+                )
+                .into())
+            }
+        }
+    }
+
+    let viewport = Viewport::new(1. / pixels_per_unit);
+    let colors = ColorMap::new(&settings);
+    let mut image = RgbImage::new(width, height);
+    let mut samples = vec![0.; (width * height) as usize];
+    graph.with_noise(&mut |noise| {
+        render_2d(
+            noise,
+            &viewport,
+            &colors,
+            &mut image,
+            &mut samples,
+        )
+    });
+
+    let path = match out {
+        Some(path) => path,
+        None => CaptureConfig::from_args()
+            .next_path("render", None, "png")?,
+    };
+    let state = RenderState {
+        sketch: "render".to_string(),
+        graph,
+        settings,
+        viewport,
+    };
+    save_png_with_state(&path, &image, &state)?;
+    println!("{}", path.display());
+    Ok(())
+}
@@ -0,0 +1,331 @@
+//! Render a batch of seeds, or a grid of parameter values,
+//! to a labelled contact sheet, and each image on its own.
+//!
+//! ```text
+//! sweep [--seeds <n>] [--vary <parameter>=<values>]...
+//!       [--graph <file> | --expr <expression>]
+//!       [--size <width>x<height>]
+//!       [--scale <pixels per unit>] [--columns <n>]
+//!       [--grayscale]
+//! ```
+//!
+//! Parameters are named like the OSC addresses: `seed`,
+//! `octaves`, `frequency`, `lacunarity`, `persistence`,
+//! `hue_center`, `hue_range`, `lightness`, `chroma` and
+//! `grayscale`. Values are a comma separated list, or
+//! `<first>..<last>` for every integer in between.
+//! `--seeds <n>` varies the seed over `n` random seeds.
+//!
+//! Up to two parameters can vary: the first across the
+//! sheet, the second down it. Noise
+//! parameters apply to every generator of a graph.
+//!
+//! The sheet is named like the sketches' captures, and the
+//! images go in a directory of the same name. They carry the
+//! 2D sketch's state, so dropping one onto it continues from
+//! there.
+use nannou::image::RgbImage;
+use perlin_noise_core::{
+    capture::CaptureConfig,
+    color::{ColorMap, ColorSettings},
+    contact_sheet::{contact_sheet, Thumbnail},
+    contour::ContourSettings,
+    curl::CurlSettings,
+    expr,
+    graph::{NodeKind, NoiseGraph},
+    metadata::save_png_with_state,
+    noise::NoiseConfig,
+    osc::Control,
+    render::{render_2d, ColorMode},
+    seed::{random_seed, seed_name},
+    state::State2d,
+    terrain::{ErosionConfig, MapSettings},
+    viewport::Viewport,
+};
+use std::{
+    env, error::Error, fs, num::ParseFloatError, process,
+};
+
+// the 2D sketch's default window width and scale, which
+// thumbnails show the same area as
+const SKETCH_WIDTH: f64 = 1200.;
+const SKETCH_PIXELS_PER_UNIT: f64 = 500.;
+
+// one parameter's values, with how each is labelled
+type Sweep = Vec<(String, Control)>;
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("sweep: {}", error);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut sweeps: Vec<Sweep> = vec![];
+    let mut graph = None;
+    let (mut width, mut height) = (320, 180);
+    let mut pixels_per_unit = None;
+    let mut columns = None;
+    let mut settings = ColorSettings::default();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().ok_or_else(|| {
+                format!("{} needs a value", arg)
+            })
+        };
+        match arg.as_str() {
+            "--seeds" => {
+                let count: usize = value()?.parse()?;
+                sweeps.push(random_seeds(count));
+            }
+            "--vary" => {
+                sweeps.push(parse_sweep(&value()?)?)
+            }
+            "--graph" => {
+                graph = Some(NoiseGraph::load(value()?)?)
+            }
+            "--expr" => {
+                let source = value()?;
+                graph = Some(expr::parse(&source).map_err(
+                    |error| error.report(&source),
+                )?)
+            }
+            "--size" => {
+                let size = value()?;
+                let (w, h) = size.split_once('x').ok_or(
+                    "--size takes <width>x<height>",
+                )?;
+                width = w.parse()?;
+                height = h.parse()?;
+            }
+            "--scale" => {
+                pixels_per_unit = Some(value()?.parse()?)
+            }
+            "--columns" => {
+                columns = Some(value()?.parse()?)
+            }
+            "--grayscale" => settings.grayscale = true,
+            // read by CaptureConfig::from_args
+            "--capture-dir" | "--capture-template" => {
+                value()?;
+            }
+            _ => {
+                return Err(format!(
+                    "unknown argument {}",
+                    arg
+                )
+                .into())
+            }
+        }
+    }
+
+    if sweeps.is_empty() {
+        return Err(
+            "nothing to sweep, pass --seeds or --vary"
+                .into(),
+        );
+    }
+    if sweeps.len() > 2 {
+        return Err(
+            "at most two parameters can vary".into()
+        );
+    }
+    // a single parameter wraps into a roughly square sheet,
+    // two make a grid
+    let columns = match (columns, sweeps.len()) {
+        (Some(columns), _) => columns,
+        (None, 1) => {
+            (sweeps[0].len() as f64).sqrt().ceil() as u32
+        }
+        (None, _) => sweeps[0].len() as u32,
+    };
+
+    let pixels_per_unit = pixels_per_unit.unwrap_or(
+        SKETCH_PIXELS_PER_UNIT * width as f64
+            / SKETCH_WIDTH,
+    );
+    let viewport = Viewport::new(1. / pixels_per_unit);
+
+    let mut capture = CaptureConfig::from_args();
+    let sheet_path =
+        capture.next_path("sweep", None, "png")?;
+    let directory = sheet_path.with_extension("");
+    fs::create_dir_all(&directory)?;
+
+    let mut thumbnails = vec![];
+    for (i, values) in
+        combinations(&sweeps).iter().enumerate()
+    {
+        let mut noise_config = NoiseConfig::default();
+        let mut graph = graph.clone();
+        let mut settings = settings.clone();
+        let mut labels = vec![];
+        for (label, control) in values {
+            control.apply(&mut noise_config, &mut settings);
+            for node in
+                graph.iter_mut().flat_map(|g| &mut g.nodes)
+            {
+                if let NodeKind::Generator(config) =
+                    &mut node.kind
+                {
+                    control.apply(config, &mut settings);
+                }
+            }
+            labels.push(label.as_str());
+        }
+
+        let colors = ColorMap::new(&settings);
+        let mut image = RgbImage::new(width, height);
+        let mut samples =
+            vec![0.; (width * height) as usize];
+        match &graph {
+            Some(graph) => graph.with_noise(&mut |noise| {
+                render_2d(
+                    noise,
+                    &viewport,
+                    &colors,
+                    &mut image,
+                    &mut samples,
+                )
+            }),
+            None => render_2d(
+                &noise_config.build(),
+                &viewport,
+                &colors,
+                &mut image,
+                &mut samples,
+            ),
+        }
+
+        let number = i + 1;
+        let state = State2d {
+            sketch: "perlin-noise-2d".to_string(),
+            noise: noise_config.clone(),
+            graph: graph.clone(),
+            settings: settings.clone(),
+            viewport,
+            erosion: ErosionConfig::default(),
+            maps: MapSettings::default(),
+            contour: ContourSettings::default(),
+            curl: CurlSettings::default(),
+            color_mode: ColorMode::default(),
+        };
+        let path =
+            directory.join(format!("{:02}.png", number));
+        save_png_with_state(&path, &image, &state)?;
+
+        thumbnails.push(Thumbnail {
+            image,
+            label: format!(
+                "{}: {}",
+                number,
+                labels.join(" ")
+            ),
+        });
+    }
+
+    let sheet = contact_sheet(&thumbnails, columns);
+    sheet.save(&sheet_path)?;
+    println!("{}", sheet_path.display());
+    Ok(())
+}
+
+fn random_seeds(count: usize) -> Sweep {
+    (0..count)
+        .map(|_| {
+            let seed = random_seed();
+            (
+                format!("seed={}", seed_name(seed)),
+                Control::Seed(seed),
+            )
+        })
+        .collect()
+}
+
+// `<parameter>=<values>`
+fn parse_sweep(arg: &str) -> Result<Sweep, Box<dyn Error>> {
+    let (name, values) = arg
+        .split_once('=')
+        .ok_or("--vary takes <parameter>=<values>")?;
+    let bad_value = |error| {
+        format!("bad values for {}: {}", name, error)
+    };
+    let values: Vec<f64> = match values.split_once("..") {
+        Some((first, last)) => {
+            let range = first
+                .parse::<i64>()
+                .and_then(|first| Ok(first..=last.parse()?))
+                .map_err(|error| {
+                    bad_value(error.to_string())
+                })?;
+            range.map(|value| value as f64).collect()
+        }
+        None => values
+            .split(',')
+            .map(|value| value.trim().parse())
+            .collect::<Result<_, ParseFloatError>>()
+            .map_err(|error| {
+                bad_value(error.to_string())
+            })?,
+    };
+    if values.is_empty() {
+        return Err(
+            format!("no values for {}", name).into()
+        );
+    }
+
+    values
+        .into_iter()
+        .map(|value| {
+            let control =
+                control(name, value).ok_or_else(|| {
+                    format!("can't vary {}", name)
+                })?;
+            Ok((format!("{}={}", name, value), control))
+        })
+        .collect()
+}
+
+// the control a sweep varies, named as at the end of its
+// OSC address. Speed is left out, as a still has no time.
+fn control(name: &str, value: f64) -> Option<Control> {
+    let control = match name {
+        "seed" => Control::Seed(value as u32),
+        "octaves" => {
+            Control::Octaves(value.clamp(1., 12.) as usize)
+        }
+        "frequency" => Control::Frequency(value),
+        "lacunarity" => Control::Lacunarity(value),
+        "persistence" => Control::Persistence(value),
+        "hue_center" => Control::HueCenter(value),
+        "hue_range" => Control::HueRange(value),
+        "lightness" => Control::Lightness(value),
+        "chroma" => Control::Chroma(value),
+        "grayscale" => Control::Grayscale(value != 0.),
+        _ => return None,
+    };
+    Some(control)
+}
+
+// a value of each sweep for every thumbnail, in the order
+// they're laid out: along the first sweep, then down the
+// second
+fn combinations(
+    sweeps: &[Sweep],
+) -> Vec<Vec<&(String, Control)>> {
+    match sweeps {
+        [across] => {
+            across.iter().map(|a| vec![a]).collect()
+        }
+        [across, down] => down
+            .iter()
+            .flat_map(|d| {
+                across.iter().map(move |a| vec![a, d])
+            })
+            .collect(),
+        _ => unreachable!("checked by run"),
+    }
+}
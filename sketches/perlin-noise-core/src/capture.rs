@@ -0,0 +1,162 @@
+//! Naming and configuration of captures and exports.
+use crate::seed::seed_name;
+use serde::{Deserialize, Serialize};
+use std::{env, fs, io, path::PathBuf};
+
+/// What the capture key saves.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+pub enum CaptureWindow {
+    /// The CPU-side noise image at full resolution, with the
+    /// sketch state embedded. Only sketches that render to an
+    /// image have one.
+    Image,
+    /// A screen grab of the window the noise is drawn in.
+    Noise,
+    /// A screen grab of the settings window.
+    Settings,
+}
+
+impl CaptureWindow {
+    pub const ALL: [CaptureWindow; 3] = [
+        CaptureWindow::Image,
+        CaptureWindow::Noise,
+        CaptureWindow::Settings,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CaptureWindow::Image => {
+                "Image (full resolution)"
+            }
+            CaptureWindow::Noise => "Noise window",
+            CaptureWindow::Settings => "Settings window",
+        }
+    }
+}
+
+/// Where captures and exports are written and how they are
+/// named.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CaptureConfig {
+    pub directory: PathBuf,
+    /// File name without extension. `{sketch}`, `{seed}`,
+    /// `{date}` and `{n}` are replaced by the sketch name,
+    /// the seed's name, the local time and a counter.
+    pub template: String,
+    pub window: CaptureWindow,
+    // the next value for `{n}`
+    #[serde(skip)]
+    counter: u32,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        CaptureConfig {
+            directory: PathBuf::from("."),
+            template: "{sketch}-{seed}-{date}-{n}"
+                .to_string(),
+            window: CaptureWindow::Image,
+            counter: 1,
+        }
+    }
+}
+
+impl CaptureConfig {
+    /// The default configuration, overridden by the
+    /// `--capture-dir <dir>` and `--capture-template
+    /// <template>` command line arguments.
+    pub fn from_args() -> Self {
+        let mut config = CaptureConfig::default();
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--capture-dir" => {
+                    if let Some(directory) = args.next() {
+                        config.directory = directory.into();
+                    }
+                }
+                "--capture-template" => {
+                    if let Some(template) = args.next() {
+                        config.template = template;
+                    }
+                }
+                _ => {}
+            }
+        }
+        config
+    }
+
+    /// The path for the next capture or export, creating the
+    /// output directory if needed.
+    ///
+    /// `{n}` counts up from 1 and skips numbers whose file
+    /// already exists, so earlier sessions are never
+    /// overwritten. Templates without `{n}` get a `-{n}`
+    /// suffix when their name is taken.
+    pub fn next_path(
+        &mut self,
+        sketch: &str,
+        seed: Option<u32>,
+        extension: &str,
+    ) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.directory)?;
+
+        let date = chrono::offset::Local::now()
+            .format("%Y-%m-%dT%H-%M-%S")
+            .to_string();
+        let seed = match seed {
+            Some(seed) => sanitize(&seed_name(seed)),
+            None => "noseed".to_string(),
+        };
+        let mut name = self
+            .template
+            .replace("{sketch}", sketch)
+            .replace("{seed}", &seed)
+            .replace("{date}", &date);
+        if !self.template.contains("{n}") {
+            let path = self
+                .directory
+                .join(format!("{}.{}", name, extension));
+            if !path.exists() {
+                return Ok(path);
+            }
+            name.push_str("-{n}");
+        }
+        loop {
+            let path = self.directory.join(format!(
+                "{}.{}",
+                name.replace(
+                    "{n}",
+                    &self.counter.to_string()
+                ),
+                extension
+            ));
+            self.counter += 1;
+            if !path.exists() {
+                return Ok(path);
+            }
+        }
+    }
+}
+
+// seeds can be any u32, so their names aren't necessarily
+// printable
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,169 @@
+//! Mapping noise values to colours.
+use nannou::{
+    color::{Lch, Rgb},
+    math::map_range,
+};
+use serde::{Deserialize, Serialize};
+
+/// How noise values in -1..1 are turned into colours: either
+/// grayscale, or an Lch hue around `hue_center` at a fixed
+/// lightness and chroma.
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+pub struct ColorSettings {
+    pub lightness: f64,
+    pub chroma: f64,
+    pub grayscale: bool,
+    pub hue_center: f64,
+    pub hue_range: f64,
+}
+
+impl Default for ColorSettings {
+    fn default() -> Self {
+        ColorSettings {
+            lightness: 50.,
+            chroma: 80.,
+            grayscale: false,
+            hue_center: 0.0,
+            hue_range: 180.0,
+        }
+    }
+}
+
+impl ColorSettings {
+    /// The colour of `value`, converting its hue exactly.
+    /// `ColorMap` is faster for colouring whole images.
+    pub fn color(&self, value: f64) -> [u8; 3] {
+        if self.grayscale {
+            gray(value)
+        } else {
+            let channels = lch_channels(
+                self.lightness,
+                self.chroma,
+                self.hue(value),
+            );
+            channels.map(|channel| channel as u8)
+        }
+    }
+
+    // the hue `value` is coloured with, in degrees
+    fn hue(&self, value: f64) -> f64 {
+        map_range(
+            value,
+            -1.0,
+            1.0,
+            self.hue_center - self.hue_range,
+            self.hue_center + self.hue_range,
+        )
+    }
+}
+
+// hues in a `ColorMap`'s table, around the whole circle
+const HUES: usize = 4096;
+
+/// `ColorSettings` for colouring many values: at a fixed
+/// lightness and chroma the colour only depends on the hue,
+/// so Lch colours are interpolated from a table of hues
+/// rather than converted one at a time.
+#[derive(Clone, Debug)]
+pub struct ColorMap {
+    settings: ColorSettings,
+    // channels of `HUES` evenly spaced hues from 0 degrees,
+    // with the first repeated at the end, 0..255 but not
+    // yet rounded
+    table: Vec<[f64; 3]>,
+}
+
+impl ColorMap {
+    pub fn new(settings: &ColorSettings) -> Self {
+        ColorMap {
+            settings: settings.clone(),
+            table: hue_table(
+                settings.lightness,
+                settings.chroma,
+            ),
+        }
+    }
+
+    pub fn settings(&self) -> &ColorSettings {
+        &self.settings
+    }
+
+    /// Follow changes to `settings`, rebuilding the table
+    /// if the lightness or chroma have changed.
+    pub fn update(&mut self, settings: &ColorSettings) {
+        if settings == &self.settings {
+            return;
+        }
+        if settings.lightness != self.settings.lightness
+            || settings.chroma != self.settings.chroma
+        {
+            self.table = hue_table(
+                settings.lightness,
+                settings.chroma,
+            );
+        }
+        self.settings = settings.clone();
+    }
+
+    /// The colour of `value`, within one level per channel
+    /// of `ColorSettings::color`.
+    pub fn color(&self, value: f64) -> [u8; 3] {
+        if self.settings.grayscale {
+            return gray(value);
+        }
+        // `ColorSettings::hue`, but in table positions and
+        // without map_range's generic conversions
+        let ColorSettings {
+            hue_center,
+            hue_range,
+            ..
+        } = self.settings;
+        let turns = (hue_center + hue_range * value) / 360.;
+        let position =
+            (turns - turns.floor()) * HUES as f64;
+        // NaN and the hues that round up to 360 go to the
+        // ends of the table
+        let index = (position as usize).min(HUES - 1);
+        let fraction = position - index as f64;
+        let [low, high] =
+            [self.table[index], self.table[index + 1]];
+        [0, 1, 2].map(|channel| {
+            (low[channel]
+                + (high[channel] - low[channel]) * fraction)
+                as u8
+        })
+    }
+}
+
+fn hue_table(lightness: f64, chroma: f64) -> Vec<[f64; 3]> {
+    (0..=HUES)
+        .map(|index| {
+            let hue = index as f64 / HUES as f64 * 360.;
+            lch_channels(lightness, chroma, hue)
+        })
+        .collect()
+}
+
+fn gray(value: f64) -> [u8; 3] {
+    let mapped_value =
+        map_range(value, -1.0, 1.0, 0., 255.);
+    [mapped_value as u8; 3]
+}
+
+// the channels of an Lch colour, scaled to 0..255 before
+// rounding down
+fn lch_channels(
+    lightness: f64,
+    chroma: f64,
+    hue: f64,
+) -> [f64; 3] {
+    // 0-100, 0-128,181, -180-180
+    let color = Lch::new(lightness, chroma, hue);
+    let rgb_color: Rgb<f64> = Rgb::from(color);
+    let (r, g, b) = rgb_color.into_components();
+    [r, g, b].map(|channel| {
+        map_range(channel, -1.0, 1.0, 0., 255.)
+    })
+}
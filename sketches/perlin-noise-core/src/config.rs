@@ -0,0 +1,111 @@
+//! Sketch constants read from a TOML file next to the
+//! binary, reloaded while the sketch runs whenever the file
+//! changes.
+use nannou::{geom::Rect, prelude::*};
+use serde::de::DeserializeOwned;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+// how often the file's modification time is checked
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct ConfigFile {
+    path: PathBuf,
+    // modification time of the last version read
+    modified: Option<SystemTime>,
+    checked: Option<Instant>,
+    /// Why the last version of the file couldn't be used,
+    /// for showing in the sketch.
+    pub error: Option<String>,
+}
+
+impl ConfigFile {
+    /// `<sketch>.toml` in the directory of the running
+    /// binary, or the file passed as `--config <file>`.
+    pub fn from_args(sketch: &str) -> Self {
+        let mut args = env::args().skip(1);
+        let path = match args.find(|arg| arg == "--config")
+        {
+            Some(_) => args.next().map(PathBuf::from),
+            None => None,
+        }
+        .unwrap_or_else(|| {
+            let name = format!("{}.toml", sketch);
+            env::current_exe()
+                .map(|exe| exe.with_file_name(&name))
+                .unwrap_or_else(|_| PathBuf::from(name))
+        });
+        ConfigFile {
+            path,
+            modified: None,
+            checked: None,
+            error: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The config, if the file changed since the last call.
+    ///
+    /// Checks at most every half second. A missing file
+    /// isn't an error, the sketch keeps its defaults.
+    pub fn poll<T: DeserializeOwned>(
+        &mut self,
+    ) -> Option<T> {
+        let now = Instant::now();
+        if let Some(checked) = self.checked {
+            if now - checked < POLL_INTERVAL {
+                return None;
+            }
+        }
+        self.checked = Some(now);
+
+        let modified = fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok()?;
+        if self.modified == Some(modified) {
+            return None;
+        }
+        self.modified = Some(modified);
+
+        let result = fs::read_to_string(&self.path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| {
+                toml::from_str(&text)
+                    .map_err(|error| error.to_string())
+            });
+        match result {
+            Ok(config) => {
+                self.error = None;
+                Some(config)
+            }
+            Err(error) => {
+                self.error = Some(format!(
+                    "{}: {}",
+                    self.path.display(),
+                    error
+                ));
+                None
+            }
+        }
+    }
+}
+
+/// Show a config error in the top left of `rect`.
+pub fn draw_config_error(
+    draw: &Draw,
+    rect: Rect,
+    error: &str,
+) {
+    draw.text(error)
+        .font_size(16)
+        .wh(rect.pad(25.).wh())
+        .left_justify()
+        .align_text_top()
+        .color(RED);
+}
@@ -0,0 +1,106 @@
+//! Composing rendered thumbnails into one labelled image.
+use nannou::{
+    image::{imageops, Rgb, RgbImage},
+    text::{font, rt::point, Font, Scale},
+};
+
+// space around and between the thumbnails
+const PADDING: u32 = 8;
+// height of the strip under each thumbnail for its label
+const LABEL_HEIGHT: u32 = 22;
+const FONT_SIZE: f32 = 14.;
+const BACKGROUND: Rgb<u8> = Rgb([24, 24, 24]);
+const TEXT: [u8; 3] = [230, 230, 230];
+
+pub struct Thumbnail {
+    pub image: RgbImage,
+    pub label: String,
+}
+
+/// Lay `thumbnails` out left to right in rows of `columns`,
+/// each with its label underneath.
+///
+/// Every thumbnail should have the size of the first one.
+/// Labels wider than their thumbnail are cut off.
+pub fn contact_sheet(
+    thumbnails: &[Thumbnail],
+    columns: u32,
+) -> RgbImage {
+    let (width, height) =
+        thumbnails.first().map_or((0, 0), |thumbnail| {
+            thumbnail.image.dimensions()
+        });
+    let columns = columns.max(1);
+    let rows = (thumbnails.len() as u32).div_ceil(columns);
+    let cell_width = width + PADDING;
+    let cell_height = height + LABEL_HEIGHT + PADDING;
+
+    let mut sheet = RgbImage::from_pixel(
+        columns * cell_width + PADDING,
+        rows * cell_height + PADDING,
+        BACKGROUND,
+    );
+    let font = font::default_notosans();
+    for (i, thumbnail) in thumbnails.iter().enumerate() {
+        let i = i as u32;
+        let x = PADDING + i % columns * cell_width;
+        let y = PADDING + i / columns * cell_height;
+        imageops::replace(
+            &mut sheet,
+            &thumbnail.image,
+            x,
+            y,
+        );
+        draw_label(
+            &mut sheet,
+            &font,
+            &thumbnail.label,
+            [x, y + height],
+            width,
+        );
+    }
+    sheet
+}
+
+// draw `text` into the label strip with its top left at
+// `corner`, blending the glyphs' coverage over what's there
+fn draw_label(
+    image: &mut RgbImage,
+    font: &Font,
+    text: &str,
+    corner: [u32; 2],
+    width: u32,
+) {
+    let scale = Scale::uniform(FONT_SIZE);
+    let ascent = font.v_metrics(scale).ascent;
+    let baseline = corner[1] as f32
+        + (LABEL_HEIGHT as f32 - FONT_SIZE) / 2.
+        + ascent;
+    let right = (corner[0] + width) as i32;
+    let bottom = (corner[1] + LABEL_HEIGHT) as i32;
+
+    let origin = point(corner[0] as f32 + 2., baseline);
+    for glyph in font.layout(text, scale, origin) {
+        let bounds = match glyph.pixel_bounding_box() {
+            Some(bounds) => bounds,
+            None => continue,
+        };
+        glyph.draw(|x, y, coverage| {
+            let x = bounds.min.x + x as i32;
+            let y = bounds.min.y + y as i32;
+            if x < 0 || y < 0 || x >= right || y >= bottom {
+                return;
+            }
+            let pixel =
+                image.get_pixel_mut(x as u32, y as u32);
+            for (channel, text) in
+                pixel.0.iter_mut().zip(TEXT)
+            {
+                *channel = (*channel as f32
+                    * (1. - coverage)
+                    + text as f32 * coverage)
+                    as u8;
+            }
+        });
+    }
+}
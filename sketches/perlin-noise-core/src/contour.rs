@@ -0,0 +1,285 @@
+//! Isolines of a sampled 2D field by marching squares, for
+//! drawing over the noise and for plotting as SVG.
+//!
+//! Lines are in sample coordinates: the sample at column
+//! `x`, row `y` is at `(x, y)`, so they line up with the
+//! image's pixels with half a pixel's offset to their
+//! centres.
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// Which isolines to draw and how.
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+#[serde(default)]
+pub struct ContourSettings {
+    pub enabled: bool,
+    /// Number of levels, evenly spaced from `lower` to
+    /// `upper` inclusive.
+    pub levels: usize,
+    pub lower: f64,
+    pub upper: f64,
+    pub line_weight: f32,
+    pub color: [u8; 3],
+    /// Draw only the lines, on white, as a plotter would.
+    pub lines_only: bool,
+}
+
+impl Default for ContourSettings {
+    fn default() -> Self {
+        ContourSettings {
+            enabled: false,
+            levels: 9,
+            lower: -0.8,
+            upper: 0.8,
+            line_weight: 1.,
+            color: [0, 0, 0],
+            lines_only: false,
+        }
+    }
+}
+
+impl ContourSettings {
+    /// The values isolines are drawn at.
+    pub fn values(&self) -> Vec<f64> {
+        match self.levels {
+            0 => vec![],
+            1 => vec![(self.lower + self.upper) / 2.],
+            levels => (0..levels)
+                .map(|level| {
+                    let t =
+                        level as f64 / (levels - 1) as f64;
+                    self.lower
+                        + (self.upper - self.lower) * t
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The isolines of a field at one value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contour {
+    pub level: f64,
+    /// Connected lines, closed ones ending where they
+    /// start.
+    pub lines: Vec<Vec<[f64; 2]>>,
+}
+
+/// The isolines of `samples`, `width` to a row, at each of
+/// `levels`.
+pub fn contours(
+    samples: &[f64],
+    width: usize,
+    levels: &[f64],
+) -> Vec<Contour> {
+    levels
+        .par_iter()
+        .map(|&level| Contour {
+            level,
+            lines: isolines(samples, width, level),
+        })
+        .collect()
+}
+
+// a crossing of the line between two neighbouring samples:
+// the sample's index and whether the line goes right
+// (false) or down (true) from it
+type Edge = (usize, bool);
+
+/// The lines along which `samples`, `width` to a row, cross
+/// `level`, joined up across cells. Samples equal to the
+/// level count as below it.
+pub fn isolines(
+    samples: &[f64],
+    width: usize,
+    level: f64,
+) -> Vec<Vec<[f64; 2]>> {
+    if width < 2 || samples.len() < width * 2 {
+        return vec![];
+    }
+    let height = samples.len() / width;
+    let above = |index: usize| samples[index] > level;
+    // where along an edge the field crosses the level
+    let point = |(index, down): Edge| {
+        let other =
+            if down { index + width } else { index + 1 };
+        let (a, b) = (samples[index], samples[other]);
+        let t = (level - a) / (b - a);
+        let (x, y) = (
+            (index % width) as f64,
+            (index / width) as f64,
+        );
+        if down {
+            [x, y + t]
+        } else {
+            [x + t, y]
+        }
+    };
+
+    // each crossing's neighbours along the lines, of which
+    // there are at most two, one in each cell beside it
+    let mut links: HashMap<Edge, Vec<Edge>> =
+        HashMap::new();
+    for y in 0..height - 1 {
+        for x in 0..width - 1 {
+            let nw = y * width + x;
+            let corners =
+                [nw, nw + 1, nw + width + 1, nw + width]
+                    .map(above);
+            let case = corners.iter().enumerate().fold(
+                0,
+                |case, (bit, &above)| {
+                    case | (above as usize) << bit
+                },
+            );
+            if case == 0 || case == 15 {
+                continue;
+            }
+            let top = (nw, false);
+            let right = (nw + 1, true);
+            let bottom = (nw + width, false);
+            let left = (nw, true);
+            // the edges between corners on either side
+            let crossed: Vec<Edge> =
+                [top, right, bottom, left]
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(side, _)| {
+                        corners[*side]
+                            != corners[(side + 1) % 4]
+                    })
+                    .map(|(_, edge)| edge)
+                    .collect();
+            let segments = if crossed.len() == 2 {
+                vec![(crossed[0], crossed[1])]
+            } else {
+                // a saddle: the middle, taken as the mean of
+                // the corners, decides which diagonal pair
+                // of corners is joined
+                let middle = (samples[nw]
+                    + samples[nw + 1]
+                    + samples[nw + width]
+                    + samples[nw + width + 1])
+                    / 4.;
+                if (middle > level) == corners[0] {
+                    vec![(top, right), (bottom, left)]
+                } else {
+                    vec![(left, top), (right, bottom)]
+                }
+            };
+            for (a, b) in segments {
+                links.entry(a).or_default().push(b);
+                links.entry(b).or_default().push(a);
+            }
+        }
+    }
+
+    // follow lines from the map's edges first, so that open
+    // lines are walked from one end, then the loops left
+    let mut starts: Vec<Edge> =
+        links.keys().copied().collect();
+    starts.sort_by_key(|edge| (links[edge].len(), *edge));
+    let mut lines = vec![];
+    for start in starts {
+        if !links.contains_key(&start) {
+            continue;
+        }
+        let mut line = vec![point(start)];
+        let mut current = start;
+        while let Some(next) = links
+            .get_mut(&current)
+            .and_then(|next| next.pop())
+        {
+            if links[&current].is_empty() {
+                links.remove(&current);
+            }
+            if let Some(back) = links.get_mut(&next) {
+                if let Some(position) = back
+                    .iter()
+                    .position(|edge| *edge == current)
+                {
+                    back.swap_remove(position);
+                }
+                if back.is_empty() {
+                    links.remove(&next);
+                }
+            }
+            line.push(point(next));
+            current = next;
+        }
+        links.remove(&current);
+        if line.len() > 1 {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+/// Write contours as an SVG of `width` by `height`, with a
+/// path for each line, grouped by level, and `settings`'
+/// colour and line weight.
+pub fn write_svg(
+    path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    settings: &ContourSettings,
+    contours: &[Contour],
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let [r, g, b] = settings.color;
+    writeln!(
+        file,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+        width, height, width, height
+    )?;
+    writeln!(
+        file,
+        r##"<g fill="none" stroke="#{:02x}{:02x}{:02x}" stroke-width="{}" stroke-linejoin="round" stroke-linecap="round">"##,
+        r, g, b, settings.line_weight
+    )?;
+    for contour in contours {
+        writeln!(
+            file,
+            r#"<g data-level="{}">"#,
+            contour.level
+        )?;
+        for line in &contour.lines {
+            let closed = line.len() > 2
+                && line.first() == line.last();
+            let points = if closed {
+                &line[..line.len() - 1]
+            } else {
+                &line[..]
+            };
+            write!(file, r#"<path d=""#)?;
+            for (index, [x, y]) in points.iter().enumerate()
+            {
+                // to the middle of the pixel the sample's
+                // drawn as
+                write!(
+                    file,
+                    "{}{:.2} {:.2}",
+                    if index == 0 { "M" } else { " L" },
+                    x + 0.5,
+                    y + 0.5
+                )?;
+            }
+            if closed {
+                write!(file, " Z")?;
+            }
+            writeln!(file, r#""/>"#)?;
+        }
+        writeln!(file, "</g>")?;
+    }
+    writeln!(file, "</g>")?;
+    writeln!(file, "</svg>")?;
+    file.flush()
+}
@@ -0,0 +1,305 @@
+//! Curl noise: divergence-free vector fields made from
+//! the configured noise, for flows, particles and
+//! fluid-like effects, and ways of drawing them.
+//!
+//! In 2D the noise is a stream function, whose curl runs
+//! along its contours. In 3D three copies of the noise, far
+//! apart in noise space, are the components of a vector
+//! potential. Derivatives are taken by central differences.
+use crate::terrain::gradients;
+use nannou::{image::RgbImage, noise::NoiseFn};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// noise-space distance finite differences are taken over
+const EPSILON: f64 = 1e-4;
+// where the second and third components of the 3D vector
+// potential are sampled, far enough from the first and
+// each other not to be related to them
+const POTENTIAL_OFFSETS: [[f64; 3]; 2] =
+    [[123.4, -51.7, 317.9], [-289.3, 211.1, -97.5]];
+
+/// The change in `noise` per unit of noise space along x
+/// and y at `point`.
+pub fn gradient_2d<N>(
+    noise: &N,
+    [x, y]: [f64; 2],
+) -> [f64; 2]
+where
+    N: NoiseFn<[f64; 2]> + ?Sized,
+{
+    [
+        (noise.get([x + EPSILON, y])
+            - noise.get([x - EPSILON, y]))
+            / (2. * EPSILON),
+        (noise.get([x, y + EPSILON])
+            - noise.get([x, y - EPSILON]))
+            / (2. * EPSILON),
+    ]
+}
+
+/// The change in `noise` per unit of noise space along x,
+/// y and z at `point`.
+pub fn gradient_3d<N>(
+    noise: &N,
+    point: [f64; 3],
+) -> [f64; 3]
+where
+    N: NoiseFn<[f64; 3]> + ?Sized,
+{
+    [0, 1, 2].map(|axis| {
+        let mut ahead = point;
+        let mut behind = point;
+        ahead[axis] += EPSILON;
+        behind[axis] -= EPSILON;
+        (noise.get(ahead) - noise.get(behind))
+            / (2. * EPSILON)
+    })
+}
+
+/// The curl of `noise` taken as a stream function,
+/// `(dn/dy, -dn/dx)`: at right angles to the gradient and
+/// as long as it.
+pub fn curl_2d<N>(noise: &N, point: [f64; 2]) -> [f64; 2]
+where
+    N: NoiseFn<[f64; 2]> + ?Sized,
+{
+    let [dx, dy] = gradient_2d(noise, point);
+    [dy, -dx]
+}
+
+/// The curl of a vector potential whose components are
+/// `noise` at `point` and at two points far from it.
+pub fn curl_3d<N>(noise: &N, point: [f64; 3]) -> [f64; 3]
+where
+    N: NoiseFn<[f64; 3]> + ?Sized,
+{
+    let offset = |[x, y, z]: [f64; 3]| {
+        [point[0] + x, point[1] + y, point[2] + z]
+    };
+    let [_, a_y, a_z] = gradient_3d(noise, point);
+    let [b_x, _, b_z] =
+        gradient_3d(noise, offset(POTENTIAL_OFFSETS[0]));
+    let [c_x, c_y, _] =
+        gradient_3d(noise, offset(POTENTIAL_OFFSETS[1]));
+    [c_y - b_z, a_z - c_x, b_x - a_y]
+}
+
+/// The 2D curl of `samples`, `width` to a row and
+/// `spacing` apart in noise space, at each sample, with y
+/// down the rows.
+pub fn sampled_curl(
+    samples: &[f64],
+    width: usize,
+    spacing: f64,
+) -> Vec<[f64; 2]> {
+    let mut field = gradients(samples, width, spacing);
+    field.par_iter_mut().for_each(|vector| {
+        *vector = [vector[1], -vector[0]]
+    });
+    field
+}
+
+/// How the 2D sketch shows the curl of the noise.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+pub enum CurlView {
+    Off,
+    /// Arrows on a grid over the image.
+    Arrows,
+    /// Line integral convolution: white noise smeared
+    /// along the flow, shading the image.
+    Lic,
+}
+
+impl CurlView {
+    pub const ALL: [CurlView; 3] =
+        [CurlView::Off, CurlView::Arrows, CurlView::Lic];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CurlView::Off => "off",
+            CurlView::Arrows => "arrows",
+            CurlView::Lic => "LIC",
+        }
+    }
+}
+
+/// Which view of the curl to draw, and its spacing or
+/// length.
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+#[serde(default)]
+pub struct CurlSettings {
+    pub view: CurlView,
+    /// Pixels between arrows.
+    pub arrow_spacing: u32,
+    /// Pixels the flow is followed each way from a pixel
+    /// for LIC.
+    pub lic_length: usize,
+}
+
+impl Default for CurlSettings {
+    fn default() -> Self {
+        CurlSettings {
+            view: CurlView::Off,
+            arrow_spacing: 32,
+            lic_length: 15,
+        }
+    }
+}
+
+/// An arrow for every `spacing` pixels of `field`, `width`
+/// vectors to a row, as its middle and the vector from its
+/// tail to its head, scaled so the longest is `spacing`
+/// long.
+pub fn arrows(
+    field: &[[f64; 2]],
+    width: usize,
+    spacing: usize,
+) -> Vec<([f64; 2], [f64; 2])> {
+    if width == 0 || spacing == 0 {
+        return vec![];
+    }
+    let height = field.len() / width;
+    let mut arrows = vec![];
+    for y in (spacing / 2..height).step_by(spacing) {
+        for x in (spacing / 2..width).step_by(spacing) {
+            arrows.push((
+                [x as f64, y as f64],
+                field[y * width + x],
+            ));
+        }
+    }
+    let longest = arrows
+        .iter()
+        .map(|(_, [x, y])| x.hypot(*y))
+        .fold(0., f64::max);
+    if longest > 0. {
+        let scale = spacing as f64 / longest;
+        for (_, vector) in &mut arrows {
+            *vector = vector.map(|v| v * scale);
+        }
+    }
+    arrows
+}
+
+/// Line integral convolution of `field`, `width` vectors
+/// to a row: for each pixel, the mean of a white noise
+/// texture from `seed` along the flow `length` pixels each
+/// way, with the contrast raised, from 0 to 1.
+pub fn lic(
+    field: &[[f64; 2]],
+    width: usize,
+    length: usize,
+    seed: u32,
+) -> Vec<f64> {
+    let mut values = vec![0.5; field.len()];
+    if width == 0 {
+        return values;
+    }
+    let height = field.len() / width;
+    let texture: Vec<f64> = (0..field.len())
+        .map(|index| {
+            white_noise(
+                (index % width) as u32,
+                (index / width) as u32,
+                seed,
+            )
+        })
+        .collect();
+    // a pixel's step along the flow, or none where it's
+    // still
+    let directions: Vec<[f64; 2]> = field
+        .par_iter()
+        .map(|&[x, y]| {
+            let speed = (x * x + y * y).sqrt();
+            if speed > 0. {
+                [x / speed, y / speed]
+            } else {
+                [0., 0.]
+            }
+        })
+        .collect();
+    values.par_chunks_mut(width).enumerate().for_each(
+        |(y, row)| {
+            for (x, value) in row.iter_mut().enumerate() {
+                let start = y * width + x;
+                let mut sum = texture[start];
+                let mut count = 1;
+                for direction in [1., -1.] {
+                    let [mut px, mut py] =
+                        [x as f64 + 0.5, y as f64 + 0.5];
+                    let mut index = start;
+                    for _ in 0..length {
+                        let [dx, dy] = directions[index];
+                        if dx == 0. && dy == 0. {
+                            break;
+                        }
+                        px += direction * dx;
+                        py += direction * dy;
+                        if px < 0.
+                            || py < 0.
+                            || px >= width as f64
+                            || py >= height as f64
+                        {
+                            break;
+                        }
+                        index = py as usize * width
+                            + px as usize;
+                        sum += texture[index];
+                        count += 1;
+                    }
+                }
+                // the mean of more samples varies less,
+                // so stretch it back out to about the
+                // same contrast
+                let mean = sum / count as f64;
+                *value = ((mean - 0.5)
+                    * (count as f64).sqrt()
+                    * 1.5
+                    + 0.5)
+                    .clamp(0., 1.);
+            }
+        },
+    );
+    values
+}
+
+/// Darken each pixel of `image` by a LIC value, from a
+/// quarter of its colour at 0 to its own colour at 1, so the
+/// darkest streaks still show the colour beneath.
+pub fn shade_lic(values: &[f64], image: &mut RgbImage) {
+    image
+        .par_chunks_mut(3)
+        .zip(values.par_iter())
+        .for_each(|(pixel, &value)| {
+            let shade = 0.25 + 0.75 * value;
+            for channel in pixel {
+                *channel =
+                    (*channel as f64 * shade).round() as u8;
+            }
+        });
+}
+
+// a value from 0 to 1 for each pixel and seed
+fn white_noise(x: u32, y: u32, seed: u32) -> f64 {
+    let mut hash = (x as u64) << 32 | y as u64;
+    hash ^=
+        (seed as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    // splitmix64's finaliser
+    hash = (hash ^ (hash >> 30))
+        .wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27))
+        .wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^= hash >> 31;
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,400 @@
+//! Sampling noise together with its gradient, worked out
+//! analytically instead of by finite differences.
+//!
+//! Perlin, OpenSimplex and fBm noise are written again with
+//! their derivatives, following `noise` 0.7 the way `batch`
+//! does, so the values match the scalar functions to within
+//! rounding. Where noise is clamped to -1..1 its gradient is
+//! zero.
+use crate::{
+    batch::{
+        simplex_vertices_3d, PermutationTable,
+        GRADIENTS_3D, NORM_2D, NORM_3D,
+        SIMPLEX_GRADIENTS_2D, SIMPLEX_GRADIENTS_3D,
+        SQUISH_2D, SQUISH_3D, STRETCH_2D, STRETCH_3D,
+    },
+    noise::{NoiseConfig, NoiseType},
+};
+use nannou::noise::BasicMulti;
+use std::array;
+
+fn dot<const D: usize>(a: [f64; D], b: [f64; D]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn s_curve5(t: f64) -> f64 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn s_curve5_slope(t: f64) -> f64 {
+    30. * t * t * (t - 1.) * (t - 1.)
+}
+
+// Perlin noise, with `corner` the gradient of each corner
+// of the cell around `point`, scaled by `scale` and
+// clamped
+fn perlin<const D: usize>(
+    point: [f64; D],
+    corner: impl Fn([i64; D]) -> [f64; D],
+    scale: f64,
+) -> (f64, [f64; D]) {
+    let floored = point.map(f64::floor);
+    let cell = floored.map(|floored| floored as i64);
+    let near: [f64; D] =
+        array::from_fn(|axis| point[axis] - floored[axis]);
+    let curve = near.map(s_curve5);
+    let slope = near.map(s_curve5_slope);
+
+    // each corner's dot product, weighted by how near the
+    // point is to it along every axis. Corners are
+    // numbered by their offsets from the cell's origin, x
+    // in the lowest bit.
+    let mut value = 0.;
+    let mut gradient = [0.; D];
+    for index in 0..1 << D {
+        let offset: [i64; D] = array::from_fn(|axis| {
+            (index >> axis & 1) as i64
+        });
+        let vector = corner(array::from_fn(|axis| {
+            cell[axis] + offset[axis]
+        }));
+        let along = dot(
+            vector,
+            array::from_fn(|axis| {
+                near[axis] - offset[axis] as f64
+            }),
+        );
+        let weights: [f64; D] = array::from_fn(|axis| {
+            if offset[axis] == 1 {
+                curve[axis]
+            } else {
+                1. - curve[axis]
+            }
+        });
+        let weight: f64 = weights.iter().product();
+        value += weight * along;
+        for (axis, gradient) in
+            gradient.iter_mut().enumerate()
+        {
+            let others: f64 = weights
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != axis)
+                .map(|(_, weight)| weight)
+                .product();
+            let weight_slope = if offset[axis] == 1 {
+                slope[axis]
+            } else {
+                -slope[axis]
+            };
+            *gradient += weight_slope * others * along
+                + weight * vector[axis];
+        }
+    }
+
+    let value = value * scale;
+    if value.abs() > 1. {
+        (value.clamp(-1., 1.), [0.; D])
+    } else {
+        (value, gradient.map(|slope| slope * scale))
+    }
+}
+
+// the contribution of an OpenSimplex vertex with gradient
+// `vector` at `offset` from it, and its gradient
+fn surflet<const D: usize>(
+    vector: [f64; D],
+    offset: [f64; D],
+) -> (f64, [f64; D]) {
+    let attenuation = 2. - dot(offset, offset);
+    if attenuation <= 0. {
+        return (0., [0.; D]);
+    }
+    let along = dot(offset, vector);
+    let cubed = attenuation.powi(3);
+    let fourth = cubed * attenuation;
+    (
+        fourth * along,
+        array::from_fn(|axis| {
+            fourth * vector[axis]
+                - 8. * cubed * along * offset[axis]
+        }),
+    )
+}
+
+// sums vertices' contributions to OpenSimplex noise
+struct Surflets<const D: usize> {
+    value: f64,
+    gradient: [f64; D],
+}
+
+impl<const D: usize> Surflets<D> {
+    fn new() -> Self {
+        Surflets {
+            value: 0.,
+            gradient: [0.; D],
+        }
+    }
+
+    fn add(&mut self, vector: [f64; D], offset: [f64; D]) {
+        let (value, gradient) = surflet(vector, offset);
+        self.value += value;
+        for (sum, slope) in
+            self.gradient.iter_mut().zip(gradient)
+        {
+            *sum += slope;
+        }
+    }
+
+    fn scaled(self, scale: f64) -> (f64, [f64; D]) {
+        (
+            self.value * scale,
+            self.gradient.map(|slope| slope * scale),
+        )
+    }
+}
+
+fn open_simplex_2d(
+    table: &PermutationTable,
+    point: [f64; 2],
+) -> (f64, [f64; 2]) {
+    let stretch = (point[0] + point[1]) * STRETCH_2D;
+    let stretched = point.map(|v| v + stretch);
+    let floored = stretched.map(f64::floor);
+    let squish = (floored[0] + floored[1]) * SQUISH_2D;
+    let region = stretched[0] - floored[0] + stretched[1]
+        - floored[1];
+    let origin = [
+        point[0] - (floored[0] + squish),
+        point[1] - (floored[1] + squish),
+    ];
+
+    let t0 = SQUISH_2D;
+    let t1 = SQUISH_2D + 1.;
+    let t2 = SQUISH_2D + t1;
+    // the rhombus is split into two triangles along the
+    // diagonal from (1, 0) to (0, 1)
+    let last = if region > 1. {
+        ([1, 1], [t2, t2])
+    } else {
+        ([0, 0], [0., 0.])
+    };
+    let mut surflets = Surflets::new();
+    for (vertex, shift) in
+        [([1, 0], [t1, t0]), ([0, 1], [t0, t1]), last]
+    {
+        let hash = table.hash2(
+            floored[0] as i64 + vertex[0],
+            floored[1] as i64 + vertex[1],
+        );
+        surflets.add(
+            SIMPLEX_GRADIENTS_2D[hash % 8],
+            [origin[0] - shift[0], origin[1] - shift[1]],
+        );
+    }
+    surflets.scaled(NORM_2D)
+}
+
+fn open_simplex_3d(
+    table: &PermutationTable,
+    point: [f64; 3],
+) -> (f64, [f64; 3]) {
+    let stretch = point.iter().sum::<f64>() * STRETCH_3D;
+    let stretched = point.map(|v| v + stretch);
+    let floored = stretched.map(f64::floor);
+    let squish = floored.iter().sum::<f64>() * SQUISH_3D;
+    let region: f64 = (0..3)
+        .map(|axis| stretched[axis] - floored[axis])
+        .sum();
+    let origin: [f64; 3] = array::from_fn(|axis| {
+        point[axis] - (floored[axis] + squish)
+    });
+
+    let mut surflets = Surflets::new();
+    let mut add = |vertex: [i64; 3], shift: [f64; 3]| {
+        let hash = table.hash3(
+            floored[0] as i64 + vertex[0],
+            floored[1] as i64 + vertex[1],
+            floored[2] as i64 + vertex[2],
+        );
+        surflets.add(
+            SIMPLEX_GRADIENTS_3D[hash % 32],
+            array::from_fn(|axis| {
+                origin[axis] - shift[axis]
+            }),
+        );
+    };
+    for &(vertex, shift) in simplex_vertices_3d(region) {
+        add(vertex, shift);
+    }
+    surflets.scaled(NORM_3D)
+}
+
+// a point in 2D or 3D, so the fractals are written once
+trait Point: Sized {
+    fn perlin(
+        self,
+        table: &PermutationTable,
+    ) -> (f64, Self);
+    fn open_simplex(
+        self,
+        table: &PermutationTable,
+    ) -> (f64, Self);
+}
+
+impl Point for [f64; 2] {
+    fn perlin(
+        self,
+        table: &PermutationTable,
+    ) -> (f64, Self) {
+        // each corner's gradient is (±1, ±1), picked by the
+        // low two bits of its hash
+        let corner = |[x, y]: [i64; 2]| {
+            let hash = table.hash2(x, y);
+            [hash & 1, hash & 2].map(|bit| {
+                if bit == 0 {
+                    1.
+                } else {
+                    -1.
+                }
+            })
+        };
+        perlin(self, corner, 2f64.sqrt())
+    }
+
+    fn open_simplex(
+        self,
+        table: &PermutationTable,
+    ) -> (f64, Self) {
+        open_simplex_2d(table, self)
+    }
+}
+
+impl Point for [f64; 3] {
+    fn perlin(
+        self,
+        table: &PermutationTable,
+    ) -> (f64, Self) {
+        let corner = |[x, y, z]: [i64; 3]| {
+            GRADIENTS_3D[table.hash3(x, y, z) & 0b1111]
+        };
+        perlin(self, corner, 2. / 3f64.sqrt())
+    }
+
+    fn open_simplex(
+        self,
+        table: &PermutationTable,
+    ) -> (f64, Self) {
+        open_simplex_3d(table, self)
+    }
+}
+
+/// Noise that samples its gradient along with its value,
+/// for the noise types it can be worked out for.
+#[derive(Clone, Debug)]
+pub struct GradientNoise {
+    noise_type: NoiseType,
+    // a table per octave, just one for the others
+    tables: Vec<PermutationTable>,
+    frequency: f64,
+    lacunarity: f64,
+    persistence: f64,
+}
+
+impl GradientNoise {
+    /// The noise `config` describes with its gradient, or
+    /// `None` when there's no analytic gradient of it: for
+    /// SuperSimplex, value and Worley noise, the fractals
+    /// other than fBm, and any domain warp.
+    pub fn new(config: &NoiseConfig) -> Option<Self> {
+        if config.warp.iterations > 0 {
+            return None;
+        }
+        let octaves = match config.noise_type {
+            NoiseType::Perlin | NoiseType::OpenSimplex => 1,
+            NoiseType::Fbm => config
+                .octaves
+                .clamp(1, BasicMulti::MAX_OCTAVES),
+            _ => return None,
+        };
+        // fBm seeds its octaves with consecutive seeds
+        let tables = (0..octaves)
+            .map(|octave| {
+                PermutationTable::new(
+                    config.seed.wrapping_add(octave as u32),
+                )
+            })
+            .collect();
+        Some(GradientNoise {
+            noise_type: config.noise_type,
+            tables,
+            frequency: config.frequency,
+            lacunarity: config.lacunarity,
+            persistence: config.persistence,
+        })
+    }
+
+    /// The noise at `point` and its gradient: the change
+    /// in it per unit of noise space along x and y.
+    pub fn get2(&self, point: [f64; 2]) -> (f64, [f64; 2]) {
+        self.get(point)
+    }
+
+    /// The noise at `point` and its gradient along x, y
+    /// and z.
+    pub fn get3(&self, point: [f64; 3]) -> (f64, [f64; 3]) {
+        self.get(point)
+    }
+
+    fn get<const D: usize>(
+        &self,
+        point: [f64; D],
+    ) -> (f64, [f64; D])
+    where
+        [f64; D]: Point,
+    {
+        match self.noise_type {
+            NoiseType::Fbm => self.fbm(point),
+            NoiseType::OpenSimplex => {
+                point.open_simplex(&self.tables[0])
+            }
+            _ => point.perlin(&self.tables[0]),
+        }
+    }
+
+    fn amplitude(&self, octave: usize) -> f64 {
+        self.persistence.powi(octave as i32)
+    }
+
+    // each octave's gradient is scaled by its frequency as
+    // well as its amplitude
+    fn fbm<const D: usize>(
+        &self,
+        point: [f64; D],
+    ) -> (f64, [f64; D])
+    where
+        [f64; D]: Point,
+    {
+        let mut point = point.map(|v| v * self.frequency);
+        let mut frequency = self.frequency;
+        let mut value = 0.;
+        let mut gradient = [0.; D];
+        for (octave, table) in
+            self.tables.iter().enumerate()
+        {
+            let amplitude = self.amplitude(octave);
+            let (signal, slopes) = point.perlin(table);
+            value += signal * amplitude;
+            for (sum, slope) in
+                gradient.iter_mut().zip(slopes)
+            {
+                *sum += slope * amplitude * frequency;
+            }
+            point = point.map(|v| v * self.lacunarity);
+            frequency *= self.lacunarity;
+        }
+        let scale =
+            2. - self.amplitude(self.tables.len() - 1);
+        (value / scale, gradient.map(|slope| slope / scale))
+    }
+}
@@ -0,0 +1,79 @@
+//! Export of raw noise samples so they can be analysed
+//! outside of the sketches, for example in a notebook.
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// Write rows of samples as CSV, with `header` as the first
+/// line.
+pub fn write_csv<const N: usize>(
+    path: impl AsRef<Path>,
+    header: [&str; N],
+    rows: &[[f64; N]],
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "{}", header.join(","))?;
+    for row in rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|value| value.to_string())
+            .collect();
+        writeln!(file, "{}", fields.join(","))?;
+    }
+    file.flush()
+}
+
+/// Write row-major `f64` samples as a version 1.0 `.npy`
+/// array with the given shape.
+pub fn write_npy(
+    path: impl AsRef<Path>,
+    shape: &[usize],
+    data: &[f64],
+) -> io::Result<()> {
+    assert_eq!(
+        shape.iter().product::<usize>(),
+        data.len(),
+        "shape does not match the number of samples"
+    );
+
+    let dims: Vec<String> =
+        shape.iter().map(|dim| dim.to_string()).collect();
+    // python needs the trailing comma for 1-tuples
+    let shape = match dims.len() {
+        1 => format!("({},)", dims[0]),
+        _ => format!("({})", dims.join(", ")),
+    };
+    let mut header = format!(
+        "{{'descr': '<f8', 'fortran_order': False, 'shape': {}, }}",
+        shape
+    );
+    // magic string, version and header length take 10
+    // bytes. The header is padded with spaces and ends in a
+    // newline so that the data starts 64-byte aligned.
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(b"\x93NUMPY\x01\x00")?;
+    file.write_all(&(header.len() as u16).to_le_bytes())?;
+    file.write_all(header.as_bytes())?;
+    for value in data {
+        file.write_all(&value.to_le_bytes())?;
+    }
+    file.flush()
+}
+
+/// Write the parameters that produced an export as a
+/// pretty-printed JSON file next to it.
+pub fn write_sidecar<T: Serialize>(
+    path: impl AsRef<Path>,
+    params: &T,
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut file, params)?;
+    file.flush()
+}
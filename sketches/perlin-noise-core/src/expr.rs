@@ -0,0 +1,995 @@
+//! A small expression language for noise, compiled into the
+//! same graphs the node editor builds. For example:
+//!
+//! ```text
+//! ridged(seed=3, oct=6) * 0.5 + billow(warp(perlin, 0.3))
+//! ```
+//!
+//! Generators are `perlin`, `simplex`, `supersimplex`,
+//! `value`, `worley`, `fbm`, `billow`, `multi`, `hybrid` and
+//! `ridged`, with the named arguments `seed`, `oct`, `freq`,
+//! `lac` and `pers`. Given a noise argument, `billow` and
+//! `ridged` instead fold it the way those fractals fold each
+//! octave.
+//!
+//! The other functions are `warp(x, power, seed=, freq=,
+//! rough=)`, `select(a, b, control, lower=, upper=,
+//! falloff=)`, `blend(a, b, control)`, `clamp(x, lower,
+//! upper)`, `scalebias(x, scale, bias)`, `abs(x)`,
+//! `terrace(x, points.., invert=)` and `curve(x, input,
+//! output, ..)`. Expressions combine with `+`, `-`, `*`, `/`
+//! by a number, unary `-` and parentheses.
+use crate::{
+    graph::{distinct_points, NodeKind, NoiseGraph},
+    noise::{NoiseConfig, NoiseType},
+};
+use std::{error::Error, fmt, ops::Range};
+
+// how deeply expressions may nest, counting parentheses,
+// calls, unary minuses and chained operators, so that
+// parsing and compiling them can't overflow the stack
+const MAX_DEPTH: usize = 128;
+
+/// A parse error, with the byte range of the source it
+/// refers to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl ParseError {
+    fn new(
+        message: impl Into<String>,
+        span: Range<usize>,
+    ) -> Self {
+        ParseError {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// The error with the source underneath and the span
+    /// marked, for monospace display.
+    pub fn report(&self, source: &str) -> String {
+        let start =
+            source[..self.span.start].chars().count();
+        let length = source[self.span.clone()]
+            .chars()
+            .count()
+            .max(1);
+        format!(
+            "{}\n{}\n{}{}",
+            self.message,
+            source,
+            " ".repeat(start),
+            "^".repeat(length)
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parse an expression into a graph whose output is the
+/// expression's value.
+pub fn parse(
+    source: &str,
+) -> Result<NoiseGraph, ParseError> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        end: source.len(),
+        depth: 0,
+    };
+    let expr = parser.expr()?;
+    if let Some(token) = parser.peek() {
+        return Err(ParseError::new(
+            "expected an operator",
+            token.span.clone(),
+        ));
+    }
+
+    let mut compiler = Compiler {
+        graph: NoiseGraph {
+            nodes: vec![],
+            output: None,
+        },
+        depths: vec![],
+    };
+    let output = compiler.compile(&expr, 0)?;
+    compiler.graph.output = Some(output);
+    Ok(compiler.layout())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Number(f64),
+    Ident(String),
+    Symbol(char),
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    span: Range<usize>,
+}
+
+fn tokenize(
+    source: &str,
+) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = vec![];
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let number = source[start..end]
+                .parse()
+                .map_err(|_| {
+                    ParseError::new(
+                        "invalid number",
+                        start..end,
+                    )
+                })?;
+            tokens.push(Token {
+                kind: TokenKind::Number(number),
+                span: start..end,
+            });
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_')
+                {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token {
+                kind: TokenKind::Ident(
+                    source[start..end].to_string(),
+                ),
+                span: start..end,
+            });
+        } else if "+-*/(),=".contains(c) {
+            chars.next();
+            tokens.push(Token {
+                kind: TokenKind::Symbol(c),
+                span: start..start + 1,
+            });
+        } else {
+            let end = start + c.len_utf8();
+            return Err(ParseError::new(
+                format!("unexpected character '{}'", c),
+                start..end,
+            ));
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Number(f64),
+    Call {
+        name: String,
+        args: Vec<Arg>,
+    },
+    Binary {
+        op: char,
+        lhs: Box<Spanned>,
+        rhs: Box<Spanned>,
+    },
+    Neg(Box<Spanned>),
+}
+
+#[derive(Clone, Debug)]
+struct Spanned {
+    expr: Expr,
+    span: Range<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct Arg {
+    name: Option<(String, Range<usize>)>,
+    value: Spanned,
+}
+
+// recursive descent, lowest precedence first:
+//   expr  = term (("+" | "-") term)*
+//   term  = unary (("*" | "/") unary)*
+//   unary = "-" unary | atom
+//   atom  = number | ident ["(" args ")"] | "(" expr ")"
+//   args  = [arg ("," arg)*]
+//   arg   = [ident "="] expr
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    // where errors at the end of the input point
+    end: usize,
+    // nesting of the expression being parsed
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_symbol(&self, symbol: char) -> bool {
+        matches!(
+            self.peek(),
+            Some(Token { kind: TokenKind::Symbol(c), .. })
+                if *c == symbol
+        )
+    }
+
+    fn next(
+        &mut self,
+        expected: &str,
+    ) -> Result<Token, ParseError> {
+        match self.tokens.get(self.position) {
+            Some(token) => {
+                self.position += 1;
+                Ok(token.clone())
+            }
+            None => Err(ParseError::new(
+                format!("expected {}", expected),
+                self.end..self.end,
+            )),
+        }
+    }
+
+    fn expect_symbol(
+        &mut self,
+        symbol: char,
+    ) -> Result<Token, ParseError> {
+        let expected = format!("'{}'", symbol);
+        let token = self.next(&expected)?;
+        if token.kind == TokenKind::Symbol(symbol) {
+            Ok(token)
+        } else {
+            Err(ParseError::new(
+                format!("expected {}", expected),
+                token.span,
+            ))
+        }
+    }
+
+    // go a level deeper, `at` being what opens the level
+    fn nest(
+        &mut self,
+        at: Range<usize>,
+    ) -> Result<(), ParseError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(ParseError::new(
+                "expression nested too deeply",
+                at,
+            ));
+        }
+        Ok(())
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[char],
+        operand: fn(
+            &mut Self,
+        ) -> Result<Spanned, ParseError>,
+    ) -> Result<Spanned, ParseError> {
+        // each operator puts the ones before it a level
+        // further down the tree
+        let depth = self.depth;
+        let mut lhs = operand(self)?;
+        while let Some(&op) =
+            ops.iter().find(|&&op| self.peek_symbol(op))
+        {
+            let at = self.next("an operator")?.span;
+            self.nest(at)?;
+            let rhs = operand(self)?;
+            let span = lhs.span.start..rhs.span.end;
+            lhs = Spanned {
+                expr: Expr::Binary {
+                    op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+                span,
+            };
+        }
+        self.depth = depth;
+        Ok(lhs)
+    }
+
+    fn expr(&mut self) -> Result<Spanned, ParseError> {
+        self.binary(&['+', '-'], Self::term)
+    }
+
+    fn term(&mut self) -> Result<Spanned, ParseError> {
+        self.binary(&['*', '/'], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Spanned, ParseError> {
+        if self.peek_symbol('-') {
+            let minus = self.next("'-'")?;
+            self.nest(minus.span.clone())?;
+            let operand = self.unary()?;
+            self.depth -= 1;
+            let span = minus.span.start..operand.span.end;
+            return Ok(Spanned {
+                expr: Expr::Neg(Box::new(operand)),
+                span,
+            });
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Spanned, ParseError> {
+        let token = self.next("a number, name or '('")?;
+        match token.kind {
+            TokenKind::Number(value) => Ok(Spanned {
+                expr: Expr::Number(value),
+                span: token.span,
+            }),
+            TokenKind::Ident(name) => {
+                let mut args = vec![];
+                let mut end = token.span.end;
+                if self.peek_symbol('(') {
+                    self.position += 1;
+                    self.nest(token.span.clone())?;
+                    if !self.peek_symbol(')') {
+                        args.push(self.arg()?);
+                        while self.peek_symbol(',') {
+                            self.position += 1;
+                            args.push(self.arg()?);
+                        }
+                    }
+                    self.depth -= 1;
+                    end = self.expect_symbol(')')?.span.end;
+                }
+                Ok(Spanned {
+                    expr: Expr::Call { name, args },
+                    span: token.span.start..end,
+                })
+            }
+            TokenKind::Symbol('(') => {
+                self.nest(token.span.clone())?;
+                let inner = self.expr()?;
+                self.depth -= 1;
+                let close = self.expect_symbol(')')?;
+                Ok(Spanned {
+                    expr: inner.expr,
+                    span: token.span.start..close.span.end,
+                })
+            }
+            TokenKind::Symbol(_) => Err(ParseError::new(
+                "expected a number, name or '('",
+                token.span,
+            )),
+        }
+    }
+
+    fn arg(&mut self) -> Result<Arg, ParseError> {
+        let named = matches!(
+            (
+                self.peek(),
+                self.tokens.get(self.position + 1)
+            ),
+            (
+                Some(Token {
+                    kind: TokenKind::Ident(_),
+                    ..
+                }),
+                Some(Token {
+                    kind: TokenKind::Symbol('='),
+                    ..
+                })
+            )
+        );
+        let name = if named {
+            let token = self.next("a name")?;
+            self.position += 1;
+            match token.kind {
+                TokenKind::Ident(name) => {
+                    Some((name, token.span))
+                }
+                _ => unreachable!("checked above"),
+            }
+        } else {
+            None
+        };
+        Ok(Arg {
+            name,
+            value: self.expr()?,
+        })
+    }
+}
+
+// a call's arguments, taken one by one so that whatever is
+// left over can be reported
+struct Args<'a> {
+    function: &'a str,
+    span: Range<usize>,
+    positional: Vec<&'a Spanned>,
+    named: Vec<(&'a str, &'a Range<usize>, &'a Spanned)>,
+}
+
+impl<'a> Args<'a> {
+    fn new(
+        function: &'a str,
+        span: Range<usize>,
+        args: &'a [Arg],
+    ) -> Self {
+        let mut positional = vec![];
+        let mut named = vec![];
+        for arg in args {
+            match &arg.name {
+                Some((name, span)) => named.push((
+                    name.as_str(),
+                    span,
+                    &arg.value,
+                )),
+                None => positional.push(&arg.value),
+            }
+        }
+        positional.reverse();
+        Args {
+            function,
+            span,
+            positional,
+            named,
+        }
+    }
+
+    fn has_positional(&self) -> bool {
+        !self.positional.is_empty()
+    }
+
+    fn positional(&mut self) -> Option<&'a Spanned> {
+        self.positional.pop()
+    }
+
+    fn source(
+        &mut self,
+        what: &str,
+    ) -> Result<&'a Spanned, ParseError> {
+        self.positional().ok_or_else(|| {
+            ParseError::new(
+                format!("{} needs {}", self.function, what),
+                self.span.clone(),
+            )
+        })
+    }
+
+    /// The argument called any of `names`, or else the next
+    /// positional one if `positional` is set.
+    fn number(
+        &mut self,
+        names: &[&str],
+        positional: bool,
+    ) -> Result<Option<f64>, ParseError> {
+        let value = match self
+            .named
+            .iter()
+            .position(|(name, _, _)| names.contains(name))
+        {
+            Some(index) => Some(self.named.remove(index).2),
+            None if positional => self.positional(),
+            None => None,
+        };
+        value.map(number).transpose()
+    }
+
+    fn finish(self) -> Result<(), ParseError> {
+        if let Some((name, span, _)) = self.named.first() {
+            return Err(ParseError::new(
+                format!(
+                    "{} has no argument '{}'",
+                    self.function, name
+                ),
+                (*span).clone(),
+            ));
+        }
+        if let Some(extra) = self.positional.last() {
+            return Err(ParseError::new(
+                format!(
+                    "too many arguments for {}",
+                    self.function
+                ),
+                extra.span.clone(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn number(value: &Spanned) -> Result<f64, ParseError> {
+    match value.expr {
+        Expr::Number(n) => Ok(n),
+        Expr::Neg(ref inner) => number(inner).map(|n| -n),
+        _ => Err(ParseError::new(
+            "expected a number",
+            value.span.clone(),
+        )),
+    }
+}
+
+fn generator(name: &str) -> Option<NoiseType> {
+    Some(match name {
+        "perlin" => NoiseType::Perlin,
+        "simplex" | "opensimplex" => NoiseType::OpenSimplex,
+        "supersimplex" => NoiseType::SuperSimplex,
+        "value" => NoiseType::Value,
+        "worley" => NoiseType::Worley,
+        "fbm" => NoiseType::Fbm,
+        "billow" => NoiseType::Billow,
+        "multi" | "basicmulti" => NoiseType::BasicMulti,
+        "hybrid" | "hybridmulti" => NoiseType::HybridMulti,
+        "ridged" | "ridgedmulti" => NoiseType::RidgedMulti,
+        _ => return None,
+    })
+}
+
+struct Compiler {
+    graph: NoiseGraph,
+    // depth of each node below the output, for the layout
+    depths: Vec<usize>,
+}
+
+impl Compiler {
+    fn node(
+        &mut self,
+        kind: NodeKind,
+        inputs: Vec<usize>,
+        depth: usize,
+    ) -> usize {
+        let index = self.graph.add_node(kind, [0., 0.]);
+        self.graph.nodes[index].inputs =
+            inputs.into_iter().map(Some).collect();
+        self.depths.push(depth);
+        index
+    }
+
+    fn scale_bias(
+        &mut self,
+        source: usize,
+        scale: f64,
+        bias: f64,
+        depth: usize,
+    ) -> usize {
+        self.node(
+            NodeKind::ScaleBias { scale, bias },
+            vec![source],
+            depth,
+        )
+    }
+
+    fn compile(
+        &mut self,
+        spanned: &Spanned,
+        depth: usize,
+    ) -> Result<usize, ParseError> {
+        let span = spanned.span.clone();
+        match &spanned.expr {
+            Expr::Number(value) => Ok(self.node(
+                NodeKind::Constant { value: *value },
+                vec![],
+                depth,
+            )),
+            Expr::Neg(operand) => {
+                let source =
+                    self.compile(operand, depth + 1)?;
+                Ok(self.scale_bias(source, -1., 0., depth))
+            }
+            Expr::Binary { op, lhs, rhs } => {
+                // a number on either side of + and * becomes a
+                // ScaleBias rather than a Constant node
+                let (lhs_number, rhs_number) =
+                    (number(lhs).ok(), number(rhs).ok());
+                match (op, lhs_number, rhs_number) {
+                    ('+', _, Some(n)) => {
+                        let source =
+                            self.compile(lhs, depth + 1)?;
+                        Ok(self.scale_bias(
+                            source, 1., n, depth,
+                        ))
+                    }
+                    ('+', Some(n), None) => {
+                        let source =
+                            self.compile(rhs, depth + 1)?;
+                        Ok(self.scale_bias(
+                            source, 1., n, depth,
+                        ))
+                    }
+                    ('-', _, Some(n)) => {
+                        let source =
+                            self.compile(lhs, depth + 1)?;
+                        Ok(self.scale_bias(
+                            source, 1., -n, depth,
+                        ))
+                    }
+                    ('*', _, Some(n)) => {
+                        let source =
+                            self.compile(lhs, depth + 1)?;
+                        Ok(self.scale_bias(
+                            source, n, 0., depth,
+                        ))
+                    }
+                    ('*', Some(n), None) => {
+                        let source =
+                            self.compile(rhs, depth + 1)?;
+                        Ok(self.scale_bias(
+                            source, n, 0., depth,
+                        ))
+                    }
+                    ('/', _, Some(n)) => {
+                        if n == 0. {
+                            return Err(ParseError::new(
+                                "division by zero",
+                                rhs.span.clone(),
+                            ));
+                        }
+                        let source =
+                            self.compile(lhs, depth + 1)?;
+                        Ok(self.scale_bias(
+                            source,
+                            1. / n,
+                            0.,
+                            depth,
+                        ))
+                    }
+                    ('/', _, None) => Err(ParseError::new(
+                        "can only divide by a number",
+                        rhs.span.clone(),
+                    )),
+                    ('-', _, None) => {
+                        let a =
+                            self.compile(lhs, depth + 1)?;
+                        let b =
+                            self.compile(rhs, depth + 2)?;
+                        let negated = self.scale_bias(
+                            b,
+                            -1.,
+                            0.,
+                            depth + 1,
+                        );
+                        Ok(self.node(
+                            NodeKind::Add,
+                            vec![a, negated],
+                            depth,
+                        ))
+                    }
+                    _ => {
+                        let a =
+                            self.compile(lhs, depth + 1)?;
+                        let b =
+                            self.compile(rhs, depth + 1)?;
+                        let kind = if *op == '+' {
+                            NodeKind::Add
+                        } else {
+                            NodeKind::Multiply
+                        };
+                        Ok(self.node(
+                            kind,
+                            vec![a, b],
+                            depth,
+                        ))
+                    }
+                }
+            }
+            Expr::Call { name, args } => self.call(
+                name,
+                Args::new(name, span, args),
+                depth,
+            ),
+        }
+    }
+
+    fn sources(
+        &mut self,
+        args: &mut Args,
+        names: &[&str],
+        depth: usize,
+    ) -> Result<Vec<usize>, ParseError> {
+        names
+            .iter()
+            .map(|name| {
+                let source = args.source(name)?;
+                self.compile(source, depth + 1)
+            })
+            .collect()
+    }
+
+    fn call(
+        &mut self,
+        name: &str,
+        mut args: Args,
+        depth: usize,
+    ) -> Result<usize, ParseError> {
+        if let Some(noise_type) = generator(name) {
+            // the fractal fold applied to another noise
+            if args.has_positional()
+                && matches!(
+                    noise_type,
+                    NoiseType::Billow
+                        | NoiseType::RidgedMulti
+                )
+            {
+                let source = self.sources(
+                    &mut args,
+                    &["a source"],
+                    depth + 1,
+                )?;
+                args.finish()?;
+                let abs = self.node(
+                    NodeKind::Abs,
+                    vec![source[0]],
+                    depth + 1,
+                );
+                return Ok(
+                    if noise_type == NoiseType::Billow {
+                        self.scale_bias(abs, 2., -1., depth)
+                    } else {
+                        self.scale_bias(abs, -1., 1., depth)
+                    },
+                );
+            }
+
+            let mut config = NoiseConfig {
+                noise_type,
+                ..NoiseConfig::default()
+            };
+            if let Some(seed) =
+                args.number(&["seed"], false)?
+            {
+                config.seed = seed as u32;
+            }
+            if noise_type.is_fractal() {
+                if let Some(octaves) =
+                    args.number(&["oct", "octaves"], false)?
+                {
+                    config.octaves = octaves as usize;
+                }
+                if let Some(frequency) = args
+                    .number(&["freq", "frequency"], false)?
+                {
+                    config.frequency = frequency;
+                }
+                if let Some(lacunarity) = args
+                    .number(&["lac", "lacunarity"], false)?
+                {
+                    config.lacunarity = lacunarity;
+                }
+                if let Some(persistence) = args.number(
+                    &["pers", "persistence"],
+                    false,
+                )? {
+                    config.persistence = persistence;
+                }
+            }
+            args.finish()?;
+            return Ok(self.node(
+                NodeKind::Generator(config),
+                vec![],
+                depth,
+            ));
+        }
+
+        let (kind, inputs) = match name {
+            "warp" | "turbulence" => {
+                let inputs = self.sources(
+                    &mut args,
+                    &["a source"],
+                    depth,
+                )?;
+                let power = args
+                    .number(&["power", "strength"], true)?
+                    .unwrap_or(1.);
+                let seed = args
+                    .number(&["seed"], false)?
+                    .unwrap_or(0.);
+                let frequency = args
+                    .number(&["freq", "frequency"], false)?
+                    .unwrap_or(1.);
+                let roughness = args
+                    .number(&["rough", "roughness"], false)?
+                    .unwrap_or(3.);
+                (
+                    NodeKind::Turbulence {
+                        seed: seed as u32,
+                        frequency,
+                        power,
+                        roughness: roughness as usize,
+                    },
+                    inputs,
+                )
+            }
+            "select" => {
+                let inputs = self.sources(
+                    &mut args,
+                    &[
+                        "a first source",
+                        "a second source",
+                        "a control",
+                    ],
+                    depth,
+                )?;
+                let lower = args
+                    .number(&["lower"], false)?
+                    .unwrap_or(0.);
+                let upper = args
+                    .number(&["upper"], false)?
+                    .unwrap_or(1.);
+                let falloff = args
+                    .number(&["falloff"], false)?
+                    .unwrap_or(0.);
+                (
+                    NodeKind::Select {
+                        lower,
+                        upper,
+                        falloff,
+                    },
+                    inputs,
+                )
+            }
+            "blend" => (
+                NodeKind::Blend,
+                self.sources(
+                    &mut args,
+                    &[
+                        "a first source",
+                        "a second source",
+                        "a control",
+                    ],
+                    depth,
+                )?,
+            ),
+            "clamp" => {
+                let inputs = self.sources(
+                    &mut args,
+                    &["a source"],
+                    depth,
+                )?;
+                let lower = args
+                    .number(&["lower"], true)?
+                    .unwrap_or(-1.);
+                let upper = args
+                    .number(&["upper"], true)?
+                    .unwrap_or(1.);
+                (NodeKind::Clamp { lower, upper }, inputs)
+            }
+            "scalebias" => {
+                let inputs = self.sources(
+                    &mut args,
+                    &["a source"],
+                    depth,
+                )?;
+                let scale = args
+                    .number(&["scale"], true)?
+                    .unwrap_or(1.);
+                let bias = args
+                    .number(&["bias"], true)?
+                    .unwrap_or(0.);
+                (
+                    NodeKind::ScaleBias { scale, bias },
+                    inputs,
+                )
+            }
+            "abs" => (
+                NodeKind::Abs,
+                self.sources(
+                    &mut args,
+                    &["a source"],
+                    depth,
+                )?,
+            ),
+            "terrace" => {
+                let inputs = self.sources(
+                    &mut args,
+                    &["a source"],
+                    depth,
+                )?;
+                let invert = args
+                    .number(&["invert"], false)?
+                    .is_some_and(|invert| invert != 0.);
+                let mut control_points = vec![];
+                while let Some(point) = args.positional() {
+                    control_points.push(number(point)?);
+                }
+                if distinct_points(
+                    control_points.iter().copied(),
+                ) < 2
+                {
+                    return Err(ParseError::new(
+                        "terrace needs at least two different \
+                         control points",
+                        args.span.clone(),
+                    ));
+                }
+                (
+                    NodeKind::Terrace {
+                        control_points,
+                        invert,
+                    },
+                    inputs,
+                )
+            }
+            "curve" => {
+                let inputs = self.sources(
+                    &mut args,
+                    &["a source"],
+                    depth,
+                )?;
+                let mut control_points = vec![];
+                while let Some(input) = args.positional() {
+                    let output = args.source(
+                        "an output for every input",
+                    )?;
+                    control_points.push([
+                        number(input)?,
+                        number(output)?,
+                    ]);
+                }
+                let control_inputs = control_points
+                    .iter()
+                    .map(|&[input, _]| input);
+                if distinct_points(control_inputs) < 4 {
+                    return Err(ParseError::new(
+                        "curve needs at least four control \
+                         points with different inputs",
+                        args.span.clone(),
+                    ));
+                }
+                (NodeKind::Curve { control_points }, inputs)
+            }
+            _ => {
+                return Err(ParseError::new(
+                    format!("unknown function '{}'", name),
+                    args.span.start
+                        ..args.span.start + name.len(),
+                ))
+            }
+        };
+        args.finish()?;
+        Ok(self.node(kind, inputs, depth))
+    }
+
+    // output on the right, each level of inputs a column
+    // further left
+    fn layout(mut self) -> NoiseGraph {
+        let columns =
+            self.depths.iter().max().map_or(0, |d| d + 1);
+        let mut rows = vec![0; columns];
+        for (node, &depth) in
+            self.graph.nodes.iter_mut().zip(&self.depths)
+        {
+            let column = columns - 1 - depth;
+            node.position = [
+                20. + 220. * column as f32,
+                20. + 160. * rows[depth] as f32,
+            ];
+            rows[depth] += 1;
+        }
+        self.graph
+    }
+}
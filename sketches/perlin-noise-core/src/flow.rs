@@ -0,0 +1,315 @@
+//! A flow field made from noise, and particles carried
+//! along it leaving trails.
+//!
+//! The field is sampled from 3D noise, with the third
+//! coordinate moving with time so the flow changes as the
+//! particles move.
+use crate::{color::ColorMap, curl::curl_2d};
+use nannou::{image::RgbImage, noise::NoiseFn};
+use rand_07::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+
+/// How noise becomes a direction of flow.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+pub enum FlowMode {
+    /// The noise value is the angle of the flow, which
+    /// always moves at the same speed.
+    Angle,
+    /// The flow runs along the noise's contours, faster
+    /// where they're closer together. It has no
+    /// divergence, so particles neither bunch up nor
+    /// spread out.
+    Curl,
+}
+
+impl FlowMode {
+    pub const ALL: [FlowMode; 2] =
+        [FlowMode::Angle, FlowMode::Curl];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FlowMode::Angle => "angle",
+            FlowMode::Curl => "curl",
+        }
+    }
+}
+
+/// Settings of the flow and the particles in it.
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+#[serde(default)]
+pub struct FlowConfig {
+    pub mode: FlowMode,
+    pub particles: usize,
+    /// Pixels a particle moves each frame, for a unit of
+    /// flow.
+    pub step: f64,
+    /// Turns of angle the noise's -1 to 1 cover in `Angle`
+    /// mode.
+    pub angle_range: f64,
+    /// Frames a particle lives for before it's put back
+    /// somewhere at random.
+    pub lifetime: usize,
+    /// Share of the trails faded out each frame.
+    pub fade: f32,
+    /// Ink a particle leaves on each pixel it crosses.
+    pub ink: f32,
+}
+
+impl Default for FlowConfig {
+    fn default() -> Self {
+        FlowConfig {
+            mode: FlowMode::Angle,
+            particles: 4000,
+            step: 2.,
+            angle_range: 1.,
+            lifetime: 200,
+            fade: 0.01,
+            ink: 0.05,
+        }
+    }
+}
+
+/// The flow at a noise-space point.
+pub fn velocity<N>(
+    noise: &N,
+    config: &FlowConfig,
+    point: [f64; 3],
+) -> [f64; 2]
+where
+    N: NoiseFn<[f64; 3]> + ?Sized,
+{
+    match config.mode {
+        FlowMode::Angle => {
+            let angle =
+                noise.get(point) * config.angle_range * TAU;
+            [angle.cos(), angle.sin()]
+        }
+        FlowMode::Curl => {
+            let [x, y, z] = point;
+            curl_2d(&Slice { noise, z }, [x, y])
+        }
+    }
+}
+
+// a slice of 3D noise at some z, as 2D noise
+struct Slice<'a, N: ?Sized> {
+    noise: &'a N,
+    z: f64,
+}
+
+impl<N> NoiseFn<[f64; 2]> for Slice<'_, N>
+where
+    N: NoiseFn<[f64; 3]> + ?Sized,
+{
+    fn get(&self, [x, y]: [f64; 2]) -> f64 {
+        self.noise.get([x, y, self.z])
+    }
+}
+
+struct Particle {
+    position: [f64; 2],
+    age: usize,
+}
+
+/// Particles in a `width` by `height` pixel area, placed
+/// from a seed, so that the same seed and settings always
+/// flow the same way.
+pub struct Particles {
+    rng: XorShiftRng,
+    particles: Vec<Particle>,
+    width: f64,
+    height: f64,
+}
+
+impl Particles {
+    /// `config.particles` particles, from `seed`.
+    pub fn new(
+        seed: u32,
+        config: &FlowConfig,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let mut particles = Particles {
+            rng: XorShiftRng::seed_from_u64(seed as u64),
+            particles: vec![],
+            width: width as f64,
+            height: height as f64,
+        };
+        particles.particles = (0..config.particles)
+            .map(|_| {
+                // spread out in age, so they don't all
+                // start again in the same frame
+                let age = particles
+                    .rng
+                    .gen_range(0, config.lifetime.max(1));
+                Particle {
+                    position: particles.spawn(),
+                    age,
+                }
+            })
+            .collect();
+        particles
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    /// Where the particles are, in pixels.
+    pub fn positions(
+        &self,
+    ) -> impl Iterator<Item = [f64; 2]> + '_ {
+        self.particles
+            .iter()
+            .map(|particle| particle.position)
+    }
+
+    fn spawn(&mut self) -> [f64; 2] {
+        [
+            self.rng.gen::<f64>() * self.width,
+            self.rng.gen::<f64>() * self.height,
+        ]
+    }
+
+    /// Move every particle one frame along the flow at
+    /// `z`, with pixels `noise_step` to a unit of noise,
+    /// inking its path onto `trails`. Particles leaving the
+    /// area or reaching the end of their lifetime start
+    /// again somewhere at random.
+    pub fn step<N>(
+        &mut self,
+        noise: &N,
+        config: &FlowConfig,
+        noise_step: f64,
+        z: f64,
+        trails: &mut Trails,
+    ) where
+        N: NoiseFn<[f64; 3]> + Sync + ?Sized,
+    {
+        // the flow is sampled in parallel, and the trails
+        // inked in order
+        let moves: Vec<[f64; 2]> = self
+            .particles
+            .par_iter()
+            .map(|particle| {
+                let [x, y] = particle.position;
+                let point =
+                    [x / noise_step, y / noise_step, z];
+                let [dx, dy] =
+                    velocity(noise, config, point);
+                [x + dx * config.step, y + dy * config.step]
+            })
+            .collect();
+        for (index, next) in moves.into_iter().enumerate() {
+            let particle = &self.particles[index];
+            trails.line(
+                particle.position,
+                next,
+                config.ink,
+            );
+            let inside = next[0] >= 0.
+                && next[1] >= 0.
+                && next[0] < self.width
+                && next[1] < self.height;
+            let (position, age) = if inside
+                && particle.age < config.lifetime
+            {
+                (next, particle.age + 1)
+            } else {
+                (self.spawn(), 0)
+            };
+            self.particles[index] =
+                Particle { position, age };
+        }
+    }
+}
+
+/// Ink left by particles, from 0 to 1 per pixel, fading
+/// over time.
+pub struct Trails {
+    width: usize,
+    height: usize,
+    ink: Vec<f32>,
+}
+
+impl Trails {
+    pub fn new(width: u32, height: u32) -> Self {
+        Trails {
+            width: width as usize,
+            height: height as usize,
+            ink: vec![0.; (width * height) as usize],
+        }
+    }
+
+    pub fn ink(&self) -> &[f32] {
+        &self.ink
+    }
+
+    /// Take `amount` of every pixel's ink away.
+    pub fn fade(&mut self, amount: f32) {
+        let keep = 1. - amount.clamp(0., 1.);
+        self.ink
+            .par_iter_mut()
+            .for_each(|ink| *ink *= keep);
+    }
+
+    /// Add `amount` of ink to each pixel between two
+    /// points.
+    pub fn line(
+        &mut self,
+        from: [f64; 2],
+        to: [f64; 2],
+        amount: f32,
+    ) {
+        let length =
+            (to[0] - from[0]).hypot(to[1] - from[1]);
+        // a dab of ink about every pixel along the line
+        let dabs = length.ceil().clamp(1., 64.) as usize;
+        for dab in 0..dabs {
+            let t = (dab as f64 + 0.5) / dabs as f64;
+            let x = from[0] + (to[0] - from[0]) * t;
+            let y = from[1] + (to[1] - from[1]) * t;
+            if x < 0. || y < 0. {
+                continue;
+            }
+            let (x, y) = (x as usize, y as usize);
+            if x < self.width && y < self.height {
+                let ink = &mut self.ink[y * self.width + x];
+                *ink = (*ink + amount).min(1.);
+            }
+        }
+    }
+
+    /// Colour the trails, with no ink as `colors`' colour
+    /// of -1 and full ink as that of 1.
+    pub fn draw(
+        &self,
+        colors: &ColorMap,
+        image: &mut RgbImage,
+    ) {
+        image
+            .par_chunks_mut(3)
+            .zip(self.ink.par_iter())
+            .for_each(|(pixel, &ink)| {
+                let value = ink as f64 * 2. - 1.;
+                pixel.copy_from_slice(&colors.color(value))
+            });
+    }
+}
@@ -0,0 +1,516 @@
+//! Noise graphs: generators wired through the combinators,
+//! modifiers and selectors of `nannou::noise`, saved as JSON.
+//!
+//! The combinators borrow their sources, so a graph isn't
+//! built into one owned noise function. `with_noise` builds
+//! it on the stack instead and hands the output node to a
+//! callback.
+use crate::noise::{Noise, NoiseConfig};
+use nannou::noise::{
+    Abs, Add, Blend, Clamp, Constant, Curve, Multiply,
+    NoiseFn, ScaleBias, Seedable, Select, Terrace,
+    Turbulence,
+};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, path::Path};
+
+/// What a node does, with its parameters.
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+pub enum NodeKind {
+    Generator(NoiseConfig),
+    Constant {
+        value: f64,
+    },
+    Add,
+    Multiply,
+    /// `source2` where the control is between the bounds,
+    /// `source1` elsewhere.
+    Select {
+        lower: f64,
+        upper: f64,
+        falloff: f64,
+    },
+    /// Blend between two sources, weighted by the control.
+    Blend,
+    Turbulence {
+        seed: u32,
+        frequency: f64,
+        power: f64,
+        roughness: usize,
+    },
+    ScaleBias {
+        scale: f64,
+        bias: f64,
+    },
+    /// Needs at least two distinct control points to have
+    /// an effect.
+    Terrace {
+        control_points: Vec<f64>,
+        invert: bool,
+    },
+    /// Maps input to output values through a spline. Needs
+    /// at least two control points with distinct inputs to
+    /// have an effect.
+    Curve {
+        control_points: Vec<[f64; 2]>,
+    },
+    Clamp {
+        lower: f64,
+        upper: f64,
+    },
+    Abs,
+}
+
+impl NodeKind {
+    /// A node of each kind with default parameters.
+    pub fn all() -> Vec<NodeKind> {
+        vec![
+            NodeKind::Generator(NoiseConfig::default()),
+            NodeKind::Constant { value: 0. },
+            NodeKind::Add,
+            NodeKind::Multiply,
+            NodeKind::Select {
+                lower: 0.,
+                upper: 1.,
+                falloff: 0.,
+            },
+            NodeKind::Blend,
+            NodeKind::Turbulence {
+                seed: 0,
+                frequency: 1.,
+                power: 1.,
+                roughness: 3,
+            },
+            NodeKind::ScaleBias {
+                scale: 1.,
+                bias: 0.,
+            },
+            NodeKind::Terrace {
+                control_points: vec![
+                    -1., -0.5, 0., 0.5, 1.,
+                ],
+                invert: false,
+            },
+            NodeKind::Curve {
+                control_points: vec![
+                    [-1., -1.],
+                    [-0.5, 0.],
+                    [0., 0.2],
+                    [0.5, 0.4],
+                    [1., 1.],
+                ],
+            },
+            NodeKind::Clamp {
+                lower: -1.,
+                upper: 1.,
+            },
+            NodeKind::Abs,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeKind::Generator(_) => "Generator",
+            NodeKind::Constant { .. } => "Constant",
+            NodeKind::Add => "Add",
+            NodeKind::Multiply => "Multiply",
+            NodeKind::Select { .. } => "Select",
+            NodeKind::Blend => "Blend",
+            NodeKind::Turbulence { .. } => "Turbulence",
+            NodeKind::ScaleBias { .. } => "ScaleBias",
+            NodeKind::Terrace { .. } => "Terrace",
+            NodeKind::Curve { .. } => "Curve",
+            NodeKind::Clamp { .. } => "Clamp",
+            NodeKind::Abs => "Abs",
+        }
+    }
+
+    /// Names of the node's inputs, in order.
+    pub fn inputs(&self) -> &'static [&'static str] {
+        match self {
+            NodeKind::Generator(_)
+            | NodeKind::Constant { .. } => &[],
+            NodeKind::Add | NodeKind::Multiply => {
+                &["source 1", "source 2"]
+            }
+            NodeKind::Select { .. } | NodeKind::Blend => {
+                &["source 1", "source 2", "control"]
+            }
+            NodeKind::Turbulence { .. }
+            | NodeKind::ScaleBias { .. }
+            | NodeKind::Terrace { .. }
+            | NodeKind::Curve { .. }
+            | NodeKind::Clamp { .. }
+            | NodeKind::Abs => &["source"],
+        }
+    }
+}
+
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+pub struct Node {
+    /// Stays the same when other nodes are removed, unlike
+    /// the node's index.
+    pub id: u32,
+    pub kind: NodeKind,
+    /// Index of the node connected to each input, if any.
+    /// Unconnected inputs read as zero.
+    pub inputs: Vec<Option<usize>>,
+    /// Where the editor shows the node.
+    pub position: [f32; 2],
+}
+
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+pub struct NoiseGraph {
+    pub nodes: Vec<Node>,
+    /// Index of the node whose value the graph outputs.
+    pub output: Option<usize>,
+}
+
+impl Default for NoiseGraph {
+    /// A single generator with the default noise, which
+    /// looks the same as the sketches without a graph.
+    fn default() -> Self {
+        let mut graph = NoiseGraph {
+            nodes: vec![],
+            output: None,
+        };
+        let generator = graph.add_node(
+            NodeKind::Generator(NoiseConfig::default()),
+            [20., 20.],
+        );
+        graph.output = Some(generator);
+        graph
+    }
+}
+
+impl NoiseGraph {
+    /// Add an unconnected node, returning its index.
+    pub fn add_node(
+        &mut self,
+        kind: NodeKind,
+        position: [f32; 2],
+    ) -> usize {
+        let id = self
+            .nodes
+            .iter()
+            .map(|node| node.id + 1)
+            .max()
+            .unwrap_or(0);
+        let inputs = vec![None; kind.inputs().len()];
+        self.nodes.push(Node {
+            id,
+            kind,
+            inputs,
+            position,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Remove a node, disconnecting everything it fed.
+    pub fn remove_node(&mut self, index: usize) {
+        self.nodes.remove(index);
+        let shift = |input: Option<usize>| match input {
+            Some(i) if i == index => None,
+            Some(i) if i > index => Some(i - 1),
+            input => input,
+        };
+        for node in &mut self.nodes {
+            for input in &mut node.inputs {
+                *input = shift(*input);
+            }
+        }
+        self.output = shift(self.output);
+    }
+
+    /// Change a node's kind, keeping what inputs it can.
+    pub fn set_kind(
+        &mut self,
+        index: usize,
+        kind: NodeKind,
+    ) {
+        let node = &mut self.nodes[index];
+        node.inputs.resize(kind.inputs().len(), None);
+        node.kind = kind;
+    }
+
+    /// Whether `node` depends on `on`, directly or through
+    /// other nodes. Connecting `on`'s input to `node` would
+    /// then make a cycle.
+    pub fn depends_on(
+        &self,
+        node: usize,
+        on: usize,
+    ) -> bool {
+        let mut stack = vec![node];
+        let mut seen = vec![false; self.nodes.len()];
+        while let Some(index) = stack.pop() {
+            if index == on {
+                return true;
+            }
+            if seen[index] {
+                continue;
+            }
+            seen[index] = true;
+            stack.extend(
+                self.nodes[index].inputs.iter().flatten(),
+            );
+        }
+        false
+    }
+
+    pub fn save(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<(), Box<dyn Error>> {
+        fs::write(
+            path,
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    /// Load a graph saved with `save`, rejecting ones with
+    /// dangling connections or cycles.
+    pub fn load(
+        path: impl AsRef<Path>,
+    ) -> Result<NoiseGraph, Box<dyn Error>> {
+        let graph: NoiseGraph = serde_json::from_str(
+            &fs::read_to_string(path)?,
+        )?;
+        let count = graph.nodes.len();
+        let connections = graph
+            .nodes
+            .iter()
+            .flat_map(|node| node.inputs.iter().flatten())
+            .chain(graph.output.iter());
+        for &index in connections {
+            if index >= count {
+                return Err(format!(
+                    "connection to missing node {}",
+                    index
+                )
+                .into());
+            }
+        }
+        for (index, node) in graph.nodes.iter().enumerate()
+        {
+            for &input in node.inputs.iter().flatten() {
+                if graph.depends_on(input, index) {
+                    return Err(format!(
+                        "node {} is part of a cycle",
+                        node.id
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Build the graph and call `f` with its output.
+    ///
+    /// Building is cheap next to sampling, but the result
+    /// can't be shared between threads, so threaded code
+    /// builds one per thread.
+    pub fn with_noise<P, R>(
+        &self,
+        f: &mut dyn FnMut(&dyn NoiseFn<P>) -> R,
+    ) -> R
+    where
+        P: Copy,
+        Noise: NoiseFn<P>,
+        for<'a> Turbulence<Source<'a, P>>: NoiseFn<P>,
+    {
+        match self.output {
+            Some(output) => self.build(output, 0, f),
+            None => f(&Constant::new(0.)),
+        }
+    }
+
+    fn build<P, R>(
+        &self,
+        index: usize,
+        depth: usize,
+        f: &mut dyn FnMut(&dyn NoiseFn<P>) -> R,
+    ) -> R
+    where
+        P: Copy,
+        Noise: NoiseFn<P>,
+        for<'a> Turbulence<Source<'a, P>>: NoiseFn<P>,
+    {
+        // cycles are rejected when loading and connecting,
+        // but don't recurse forever if one slips through
+        if depth > self.nodes.len() {
+            return f(&Constant::new(0.));
+        }
+        let node = &self.nodes[index];
+        let input = |i: usize,
+                     f: &mut dyn FnMut(
+            &dyn NoiseFn<P>,
+        ) -> R| {
+            match node.inputs[i] {
+                Some(input) => {
+                    self.build(input, depth + 1, f)
+                }
+                None => f(&Constant::new(0.)),
+            }
+        };
+
+        match &node.kind {
+            NodeKind::Generator(config) => {
+                f(&config.build())
+            }
+            NodeKind::Constant { value } => {
+                f(&Constant::new(*value))
+            }
+            NodeKind::Add => input(0, &mut |a| {
+                input(1, &mut |b| f(&Add::new(a, b)))
+            }),
+            NodeKind::Multiply => input(0, &mut |a| {
+                input(1, &mut |b| f(&Multiply::new(a, b)))
+            }),
+            NodeKind::Select {
+                lower,
+                upper,
+                falloff,
+            } => input(0, &mut |a| {
+                input(1, &mut |b| {
+                    input(2, &mut |control| {
+                        f(&Select::new(a, b, control)
+                            .set_bounds(*lower, *upper)
+                            .set_falloff(*falloff))
+                    })
+                })
+            }),
+            NodeKind::Blend => input(0, &mut |a| {
+                input(1, &mut |b| {
+                    input(2, &mut |control| {
+                        f(&Blend::new(a, b, control))
+                    })
+                })
+            }),
+            NodeKind::Turbulence {
+                seed,
+                frequency,
+                power,
+                roughness,
+            } => input(0, &mut |source| {
+                f(&Turbulence::new(Source(source))
+                    .set_seed(*seed)
+                    .set_frequency(*frequency)
+                    .set_power(*power)
+                    .set_roughness(*roughness))
+            }),
+            NodeKind::ScaleBias { scale, bias } => {
+                input(0, &mut |source| {
+                    f(&ScaleBias::new(source)
+                        .set_scale(*scale)
+                        .set_bias(*bias))
+                })
+            }
+            NodeKind::Terrace {
+                control_points,
+                invert,
+            } => input(0, &mut |source| {
+                if distinct_points(
+                    control_points.iter().copied(),
+                ) < 2
+                {
+                    return f(source);
+                }
+                let terrace = control_points.iter().fold(
+                    Terrace::new(source),
+                    |terrace, &point| {
+                        terrace.add_control_point(point)
+                    },
+                );
+                f(&terrace.invert_terraces(*invert))
+            }),
+            NodeKind::Curve { control_points } => {
+                input(0, &mut |source| {
+                    // noise's Curve prints a warning for every
+                    // value below its second control point, so
+                    // hold the low end flat with two more
+                    let [low, output] = control_points
+                        .iter()
+                        .copied()
+                        .fold(
+                            [f64::MAX, 0.],
+                            |lowest, point| {
+                                if point[0] < lowest[0] {
+                                    point
+                                } else {
+                                    lowest
+                                }
+                            },
+                        );
+                    let guards = [
+                        [low - 2e6, output],
+                        [low - 1e6, output],
+                    ];
+                    let inputs = control_points
+                        .iter()
+                        .chain(&guards)
+                        .map(|&[input, _]| input);
+                    if distinct_points(inputs) < 4 {
+                        return f(source);
+                    }
+                    f(&control_points
+                        .iter()
+                        .chain(&guards)
+                        .fold(
+                            Curve::new(source),
+                            |curve, &[input, output]| {
+                                curve.add_control_point(
+                                    input, output,
+                                )
+                            },
+                        ))
+                })
+            }
+            NodeKind::Clamp { lower, upper } => {
+                input(0, &mut |source| {
+                    f(&Clamp::new(source)
+                        .set_bounds(*lower, *upper))
+                })
+            }
+            NodeKind::Abs => {
+                input(0, &mut |source| f(&Abs::new(source)))
+            }
+        }
+    }
+}
+
+/// A borrowed noise function, for the combinators that own
+/// their source.
+pub struct Source<'a, P>(&'a dyn NoiseFn<P>);
+
+impl<'a, P> NoiseFn<P> for Source<'a, P> {
+    fn get(&self, point: P) -> f64 {
+        self.0.get(point)
+    }
+}
+
+/// How many of `points` noise keeps as control points: it
+/// drops any within `f64::EPSILON` of one it already has,
+/// then panics if too few are left.
+pub(crate) fn distinct_points(
+    points: impl IntoIterator<Item = f64>,
+) -> usize {
+    let mut kept: Vec<f64> = vec![];
+    for point in points {
+        if !kept
+            .iter()
+            .any(|kept| (kept - point).abs() < f64::EPSILON)
+        {
+            kept.push(point);
+        }
+    }
+    kept.len()
+}
@@ -0,0 +1,370 @@
+//! egui node editor for noise graphs. Each node is a small
+//! window with its parameters and a picker per input, and
+//! connections are drawn as lines behind the windows.
+use crate::{
+    graph::{NodeKind, NoiseGraph},
+    ui::noise_settings,
+};
+use nannou_egui::egui::{self, Color32, LayerId, Stroke};
+
+pub enum GraphAction {
+    Save,
+    Load,
+    /// Replace the graph with the parsed expression.
+    Parse,
+}
+
+/// Controls for whether the graph drives the sketch, adding
+/// nodes, the file the graph is saved to, and an expression
+/// to build it from.
+///
+/// `expression_error` is the report of the last expression
+/// that failed to parse.
+pub fn graph_settings(
+    ui: &mut egui::Ui,
+    graph: &mut NoiseGraph,
+    enabled: &mut bool,
+    path: &mut String,
+    expression: &mut String,
+    expression_error: Option<&str>,
+) -> Option<GraphAction> {
+    let mut action = None;
+
+    ui.checkbox(enabled, "draw the graph");
+    ui.horizontal(|ui| {
+        ui.label("Add node:");
+        for kind in NodeKind::all() {
+            if ui.small_button(kind.name()).clicked() {
+                let position = [20., 20.];
+                graph.add_node(kind, position);
+            }
+        }
+    });
+
+    ui.label("Graph file:");
+    ui.text_edit_singleline(path);
+    ui.horizontal(|ui| {
+        if ui.button("save").clicked() {
+            action = Some(GraphAction::Save);
+        }
+        if ui.button("load").clicked() {
+            action = Some(GraphAction::Load);
+        }
+    });
+
+    ui.label("Expression:");
+    ui.text_edit_multiline(expression);
+    if ui.button("parse").clicked() {
+        action = Some(GraphAction::Parse);
+    }
+    if let Some(error) = expression_error {
+        ui.add(
+            egui::Label::new(error)
+                .monospace()
+                .text_color(Color32::RED),
+        );
+    }
+
+    action
+}
+
+/// A window per node of `graph`.
+///
+/// Returns whether the graph's output may have changed.
+/// Moving nodes around only updates their positions.
+pub fn graph_editor(
+    ctx: &egui::CtxRef,
+    graph: &mut NoiseGraph,
+) -> bool {
+    let mut changed = false;
+    let mut new_kind = None;
+    let mut removed = None;
+    let mut rects = vec![None; graph.nodes.len()];
+
+    for index in 0..graph.nodes.len() {
+        // nodes this one can read from without making a
+        // cycle
+        let sources: Vec<(usize, String)> =
+            (0..graph.nodes.len())
+                .filter(|&source| {
+                    !graph.depends_on(source, index)
+                })
+                .map(|source| {
+                    (source, node_label(graph, source))
+                })
+                .collect();
+
+        let NoiseGraph { nodes, output } = graph;
+        let node = &mut nodes[index];
+        let id = node.id;
+        let response = egui::Window::new(node_label_of(
+            id, &node.kind,
+        ))
+        .id(egui::Id::new(("graph_node", id)))
+        .default_pos(node.position)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let kind = node.kind.name();
+            egui::ComboBox::from_id_source("node_kind")
+                .selected_text(kind)
+                .show_ui(ui, |ui| {
+                    for option in NodeKind::all() {
+                        let name = option.name();
+                        if ui
+                            .selectable_label(
+                                name == kind,
+                                name,
+                            )
+                            .clicked()
+                            && name != kind
+                        {
+                            new_kind =
+                                Some((index, option));
+                        }
+                    }
+                });
+
+            changed |= node_parameters(ui, &mut node.kind);
+
+            for (input, name) in
+                node.kind.inputs().iter().enumerate()
+            {
+                let selected = match node.inputs[input] {
+                    Some(source) => sources
+                        .iter()
+                        .find(|(i, _)| *i == source)
+                        .map(|(_, label)| label.as_str())
+                        .unwrap_or("?"),
+                    None => "none",
+                };
+                let before = node.inputs[input];
+                egui::ComboBox::from_label(*name)
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut node.inputs[input],
+                            None,
+                            "none",
+                        );
+                        for (source, label) in &sources {
+                            ui.selectable_value(
+                                &mut node.inputs[input],
+                                Some(*source),
+                                label,
+                            );
+                        }
+                    });
+                changed |= before != node.inputs[input];
+            }
+
+            ui.horizontal(|ui| {
+                if ui
+                    .radio(*output == Some(index), "output")
+                    .clicked()
+                {
+                    changed |= *output != Some(index);
+                    *output = Some(index);
+                }
+                if ui.button("remove").clicked() {
+                    removed = Some(index);
+                }
+            });
+        });
+
+        if let Some(response) = response {
+            let rect = response.response.rect;
+            node.position = [rect.min.x, rect.min.y];
+            rects[index] = Some(rect);
+        }
+    }
+
+    // connections, from the right of a source to the left of
+    // the node reading it
+    let painter = ctx.layer_painter(LayerId::background());
+    let stroke = Stroke::new(2., Color32::LIGHT_BLUE);
+    for (index, node) in graph.nodes.iter().enumerate() {
+        for source in node.inputs.iter().flatten() {
+            if let (Some(from), Some(to)) =
+                (rects[*source], rects[index])
+            {
+                painter.line_segment(
+                    [from.right_center(), to.left_center()],
+                    stroke,
+                );
+            }
+        }
+    }
+
+    if let Some((index, kind)) = new_kind {
+        graph.set_kind(index, kind);
+        changed = true;
+    }
+    if let Some(index) = removed {
+        graph.remove_node(index);
+        changed = true;
+    }
+
+    changed
+}
+
+fn node_label(graph: &NoiseGraph, index: usize) -> String {
+    let node = &graph.nodes[index];
+    node_label_of(node.id, &node.kind)
+}
+
+fn node_label_of(id: u32, kind: &NodeKind) -> String {
+    format!("{}: {}", id, kind.name())
+}
+
+// Returns whether anything changed.
+fn node_parameters(
+    ui: &mut egui::Ui,
+    kind: &mut NodeKind,
+) -> bool {
+    let mut changed = false;
+
+    match kind {
+        NodeKind::Generator(config) => {
+            ui.horizontal(|ui| {
+                ui.label("seed:");
+                changed |= ui
+                    .add(egui::DragValue::new(
+                        &mut config.seed,
+                    ))
+                    .changed();
+            });
+            changed |= noise_settings(ui, config);
+        }
+        NodeKind::Constant { value } => {
+            changed |= drag(ui, "value:", value);
+        }
+        NodeKind::Add
+        | NodeKind::Multiply
+        | NodeKind::Blend
+        | NodeKind::Abs => {}
+        NodeKind::Select {
+            lower,
+            upper,
+            falloff,
+        } => {
+            changed |= drag(ui, "lower bound:", lower);
+            changed |= drag(ui, "upper bound:", upper);
+            changed |= drag(ui, "falloff:", falloff);
+        }
+        NodeKind::Turbulence {
+            seed,
+            frequency,
+            power,
+            roughness,
+        } => {
+            changed |= drag(ui, "frequency:", frequency);
+            changed |= drag(ui, "power:", power);
+            ui.horizontal(|ui| {
+                ui.label("seed:");
+                changed |= ui
+                    .add(egui::DragValue::new(seed))
+                    .changed();
+            });
+            ui.label("roughness:");
+            changed |= ui
+                .add(egui::Slider::new(roughness, 1..=8))
+                .changed();
+        }
+        NodeKind::ScaleBias { scale, bias } => {
+            changed |= drag(ui, "scale:", scale);
+            changed |= drag(ui, "bias:", bias);
+        }
+        NodeKind::Terrace {
+            control_points,
+            invert,
+        } => {
+            changed |=
+                ui.checkbox(invert, "invert").changed();
+            ui.label("control points:");
+            let mut remove = None;
+            for (i, point) in
+                control_points.iter_mut().enumerate()
+            {
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(point)
+                                .speed(0.01),
+                        )
+                        .changed();
+                    if ui.small_button("x").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = remove {
+                control_points.remove(i);
+                changed = true;
+            }
+            if ui.small_button("add point").clicked() {
+                control_points.push(
+                    control_points
+                        .last()
+                        .map_or(0., |p| p + 0.5),
+                );
+                changed = true;
+            }
+        }
+        NodeKind::Curve { control_points } => {
+            ui.label("control points (input, output):");
+            let mut remove = None;
+            for (i, [input, output]) in
+                control_points.iter_mut().enumerate()
+            {
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(input)
+                                .speed(0.01),
+                        )
+                        .changed();
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(output)
+                                .speed(0.01),
+                        )
+                        .changed();
+                    if ui.small_button("x").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = remove {
+                control_points.remove(i);
+                changed = true;
+            }
+            if ui.small_button("add point").clicked() {
+                let [input, output] = control_points
+                    .last()
+                    .copied()
+                    .unwrap_or_default();
+                control_points.push([input + 0.5, output]);
+                changed = true;
+            }
+        }
+        NodeKind::Clamp { lower, upper } => {
+            changed |= drag(ui, "lower bound:", lower);
+            changed |= drag(ui, "upper bound:", upper);
+        }
+    }
+
+    changed
+}
+
+fn drag(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut f64,
+) -> bool {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.add(egui::DragValue::new(value).speed(0.01))
+            .changed()
+    })
+    .inner
+}
@@ -0,0 +1,31 @@
+//! Code shared between the perlin noise sketches.
+
+pub mod batch;
+pub mod capture;
+pub mod color;
+pub mod config;
+pub mod contact_sheet;
+pub mod contour;
+pub mod curl;
+pub mod derivative;
+pub mod export;
+pub mod expr;
+pub mod flow;
+pub mod graph;
+pub mod graph_editor;
+pub mod metadata;
+pub mod noise;
+pub mod osc;
+pub mod perlin;
+pub mod plot;
+pub mod preset;
+pub mod render;
+pub mod seed;
+pub mod server;
+pub mod state;
+pub mod terrain;
+pub mod tiles;
+pub mod timeline;
+pub mod ui;
+pub mod viewport;
+pub mod warp;
@@ -0,0 +1,136 @@
+//! Sketch state stored in the text chunks of captured PNGs,
+//! so that any image a sketch produced can be loaded back
+//! into it.
+use nannou::image::RgbImage;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    error::Error, fs::File, io::BufWriter, path::Path,
+};
+
+/// Save `image` as a PNG with every field of `state` in its
+/// own text chunk.
+///
+/// Nested fields are keyed by their dotted path, for
+/// example `noise.seed`, and values are stored as JSON so
+/// they read back with the same type. Values that Latin-1
+/// can't hold, such as a preset named in another script, go
+/// in a UTF-8 `iTXt` chunk rather than a `tEXt` one.
+pub fn save_png_with_state<T: Serialize>(
+    path: impl AsRef<Path>,
+    image: &RgbImage,
+    state: &T,
+) -> Result<(), Box<dyn Error>> {
+    let mut chunks = vec![];
+    flatten(
+        String::new(),
+        serde_json::to_value(state)?,
+        &mut chunks,
+    );
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(
+        file,
+        image.width(),
+        image.height(),
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    for (keyword, text) in chunks {
+        if text.chars().all(|c| (c as u32) <= 0xff) {
+            encoder.add_text_chunk(keyword, text)?;
+        } else {
+            encoder.add_itxt_chunk(keyword, text)?;
+        }
+    }
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(image.as_raw())?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Read back the state written by `save_png_with_state`.
+///
+/// The state's `sketch` field must name `sketch`, so that
+/// an image from one sketch isn't loaded into another with
+/// similar fields. Text chunks that aren't JSON, such as
+/// ones added by other software, are ignored.
+pub fn load_state_from_png<T: DeserializeOwned>(
+    path: impl AsRef<Path>,
+    sketch: &str,
+) -> Result<T, Box<dyn Error>> {
+    let decoder = png::Decoder::new(File::open(path)?);
+    let reader = decoder.read_info()?;
+    let info = reader.info();
+
+    let latin1 = info
+        .uncompressed_latin1_text
+        .iter()
+        .map(|chunk| (&chunk.keyword, chunk.text.clone()));
+    let utf8 = info.utf8_text.iter().filter_map(|chunk| {
+        Some((&chunk.keyword, chunk.get_text().ok()?))
+    });
+    let mut state = Value::Object(Map::new());
+    for (keyword, text) in latin1.chain(utf8) {
+        if let Ok(value) = serde_json::from_str(&text) {
+            insert(&mut state, keyword, value);
+        }
+    }
+
+    match state.get("sketch").and_then(Value::as_str) {
+        Some(saved) if saved == sketch => {}
+        Some(saved) => {
+            return Err(format!(
+                "it was saved by {}, not {}",
+                saved, sketch
+            )
+            .into())
+        }
+        None => {
+            return Err(
+                "it has no sketch state to load".into()
+            )
+        }
+    }
+    Ok(serde_json::from_value(state)?)
+}
+
+fn flatten(
+    prefix: String,
+    value: Value,
+    chunks: &mut Vec<(String, String)>,
+) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(key, value, chunks);
+            }
+        }
+        value => chunks.push((prefix, value.to_string())),
+    }
+}
+
+fn insert(state: &mut Value, keyword: &str, value: Value) {
+    let mut target = state;
+    let mut parts = keyword.split('.').peekable();
+    while let Some(part) = parts.next() {
+        let map = match target {
+            Value::Object(map) => map,
+            // a value already sits where this path
+            // expects an object
+            _ => return,
+        };
+        if parts.peek().is_none() {
+            map.insert(part.to_string(), value);
+            return;
+        }
+        target = map
+            .entry(part)
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+}
@@ -0,0 +1,412 @@
+//! Noise generators that the sketches can switch between at
+//! runtime, described by a serializable `NoiseConfig`.
+
+use crate::{
+    batch::BatchNoise,
+    curl::{gradient_2d, gradient_3d},
+    derivative::GradientNoise,
+    perlin::Perlin,
+    warp::{DomainWarp, WarpConfig},
+};
+use nannou::noise::{
+    BasicMulti, Billow, Fbm, HybridMulti, MultiFractal,
+    NoiseFn, OpenSimplex, RidgedMulti, Seedable,
+    SuperSimplex, Value, Worley,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+pub enum NoiseType {
+    Perlin,
+    OpenSimplex,
+    SuperSimplex,
+    Value,
+    Worley,
+    Fbm,
+    Billow,
+    BasicMulti,
+    HybridMulti,
+    RidgedMulti,
+}
+
+impl NoiseType {
+    pub const ALL: [NoiseType; 10] = [
+        NoiseType::Perlin,
+        NoiseType::OpenSimplex,
+        NoiseType::SuperSimplex,
+        NoiseType::Value,
+        NoiseType::Worley,
+        NoiseType::Fbm,
+        NoiseType::Billow,
+        NoiseType::BasicMulti,
+        NoiseType::HybridMulti,
+        NoiseType::RidgedMulti,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NoiseType::Perlin => "Perlin",
+            NoiseType::OpenSimplex => "OpenSimplex",
+            NoiseType::SuperSimplex => "SuperSimplex",
+            NoiseType::Value => "Value",
+            NoiseType::Worley => "Worley",
+            NoiseType::Fbm => "Fbm",
+            NoiseType::Billow => "Billow",
+            NoiseType::BasicMulti => "BasicMulti",
+            NoiseType::HybridMulti => "HybridMulti",
+            NoiseType::RidgedMulti => "RidgedMulti",
+        }
+    }
+
+    /// Whether the octave, frequency, lacunarity and
+    /// persistence settings apply to this noise type.
+    pub fn is_fractal(self) -> bool {
+        matches!(
+            self,
+            NoiseType::Fbm
+                | NoiseType::Billow
+                | NoiseType::BasicMulti
+                | NoiseType::HybridMulti
+                | NoiseType::RidgedMulti
+        )
+    }
+}
+
+/// Everything needed to rebuild a noise function.
+///
+/// The defaults are those of `BasicMulti::new()`, which is
+/// what the sketches used before the noise was
+/// configurable.
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+pub struct NoiseConfig {
+    pub noise_type: NoiseType,
+    pub seed: u32,
+    pub octaves: usize,
+    pub frequency: f64,
+    pub lacunarity: f64,
+    pub persistence: f64,
+    // configs saved before warping existed are unwarped
+    #[serde(default)]
+    pub warp: WarpConfig,
+}
+
+impl Default for NoiseConfig {
+    fn default() -> Self {
+        NoiseConfig {
+            noise_type: NoiseType::BasicMulti,
+            seed: BasicMulti::DEFAULT_SEED,
+            octaves: BasicMulti::DEFAULT_OCTAVES,
+            frequency: BasicMulti::DEFAULT_FREQUENCY,
+            lacunarity: BasicMulti::DEFAULT_LACUNARITY,
+            persistence: BasicMulti::DEFAULT_PERSISTENCE,
+            warp: WarpConfig::default(),
+        }
+    }
+}
+
+impl NoiseConfig {
+    pub fn build(&self) -> Noise {
+        Noise {
+            source: self.source(),
+            warp: self.warp.build(),
+            batch: BatchNoise::new(self),
+            gradient: GradientNoise::new(self),
+        }
+    }
+
+    /// The interval every value of the noise falls in.
+    ///
+    /// The generators keep to -1..1. The fractals add up
+    /// octaves of Perlin noise, and their bounds are the
+    /// most the octaves can add up to, which leaves -1..1
+    /// for some settings: hybrid multifractal can reach
+    /// ±4.4 at the defaults, and the colours clip there.
+    /// Domain warping moves the points sampled, not the
+    /// values.
+    pub fn bounds(&self) -> [f64; 2] {
+        let octaves =
+            self.octaves.clamp(1, BasicMulti::MAX_OCTAVES);
+        // the most an octave of each can add
+        let amplitudes = (0..octaves)
+            .map(|octave| {
+                self.persistence.abs().powi(octave as i32)
+            })
+            .collect::<Vec<_>>();
+        let sum = amplitudes.iter().sum::<f64>();
+        match self.noise_type {
+            NoiseType::Perlin
+            | NoiseType::OpenSimplex
+            | NoiseType::SuperSimplex
+            | NoiseType::Value
+            | NoiseType::Worley => [-1., 1.],
+            NoiseType::Fbm => {
+                let scale = 2.
+                    - self
+                        .persistence
+                        .powi(octaves as i32 - 1);
+                let bound = sum / scale.abs();
+                [-bound, bound]
+            }
+            NoiseType::Billow => {
+                let bound = sum * 0.5;
+                [-bound, bound]
+            }
+            // each octave scales the result by up to
+            // 1 + its amplitude
+            NoiseType::BasicMulti => {
+                let bound = amplitudes[1..]
+                    .iter()
+                    .map(|amplitude| 1. + amplitude)
+                    .product::<f64>()
+                    * 0.5;
+                [-bound, bound]
+            }
+            // octaves after the first are weighted by the
+            // previous octave's signal, or by at least 1
+            NoiseType::HybridMulti => {
+                let first = self.persistence.abs();
+                let mut weight = first.max(1.);
+                let mut bound = first;
+                for amplitude in &amplitudes[1..] {
+                    bound += weight * amplitude;
+                    weight = (weight * amplitude).max(1.);
+                }
+                [-bound * 3., bound * 3.]
+            }
+            // ridges are 0..1 before being scaled and
+            // shifted down by 1
+            NoiseType::RidgedMulti => {
+                let scale =
+                    2. - 0.5f64.powi(octaves as i32 - 1);
+                [-1., sum * 2. / scale - 1.]
+            }
+        }
+    }
+
+    fn source(&self) -> Source {
+        match self.noise_type {
+            NoiseType::Perlin => Source::Perlin(
+                Perlin::new().set_seed(self.seed),
+            ),
+            NoiseType::OpenSimplex => Source::OpenSimplex(
+                OpenSimplex::new().set_seed(self.seed),
+            ),
+            NoiseType::SuperSimplex => {
+                Source::SuperSimplex(
+                    SuperSimplex::new().set_seed(self.seed),
+                )
+            }
+            NoiseType::Value => Source::Value(
+                Value::new().set_seed(self.seed),
+            ),
+            NoiseType::Worley => Source::Worley(
+                Worley::new().set_seed(self.seed),
+            ),
+            NoiseType::Fbm => {
+                Source::Fbm(self.fractal(Fbm::new()))
+            }
+            NoiseType::Billow => {
+                Source::Billow(self.fractal(Billow::new()))
+            }
+            NoiseType::BasicMulti => Source::BasicMulti(
+                self.fractal(BasicMulti::new()),
+            ),
+            NoiseType::HybridMulti => Source::HybridMulti(
+                self.fractal(HybridMulti::new()),
+            ),
+            NoiseType::RidgedMulti => Source::RidgedMulti(
+                self.fractal(RidgedMulti::new()),
+            ),
+        }
+    }
+
+    fn fractal<T: MultiFractal + Seedable>(
+        &self,
+        noise: T,
+    ) -> T {
+        noise
+            .set_seed(self.seed)
+            .set_octaves(self.octaves)
+            .set_frequency(self.frequency)
+            .set_lacunarity(self.lacunarity)
+            .set_persistence(self.persistence)
+    }
+}
+
+/// A noise function built from a `NoiseConfig`, sampled at
+/// domain warped points.
+#[derive(Clone, Debug)]
+pub struct Noise {
+    source: Source,
+    warp: DomainWarp,
+    // the same noise sampled with SIMD, where there is a
+    // version of it to sample
+    batch: Option<BatchNoise>,
+    // the same noise with its gradient, where there is an
+    // analytic one
+    gradient: Option<GradientNoise>,
+}
+
+impl Noise {
+    /// Sample `row.len()` points, starting at `start` and
+    /// `step` apart along x, with SIMD if the noise type
+    /// allows.
+    pub fn row_2d(
+        &self,
+        start: [f64; 2],
+        step: f64,
+        row: &mut [f64],
+    ) {
+        match &self.batch {
+            Some(batch) => batch.row_2d(start, step, row),
+            None => {
+                for (x, value) in row.iter_mut().enumerate()
+                {
+                    *value = self.get([
+                        start[0] + x as f64 * step,
+                        start[1],
+                    ]);
+                }
+            }
+        }
+    }
+
+    /// The 3D version of `row_2d`.
+    pub fn row_3d(
+        &self,
+        start: [f64; 3],
+        step: f64,
+        row: &mut [f64],
+    ) {
+        match &self.batch {
+            Some(batch) => batch.row_3d(start, step, row),
+            None => {
+                for (x, value) in row.iter_mut().enumerate()
+                {
+                    *value = self.get([
+                        start[0] + x as f64 * step,
+                        start[1],
+                        start[2],
+                    ]);
+                }
+            }
+        }
+    }
+}
+
+impl Noise {
+    /// Whether the gradients from `get_with_gradient_2d`
+    /// and `get_with_gradient_3d` are worked out
+    /// analytically, which they are for unwarped Perlin,
+    /// OpenSimplex and fBm noise, rather than by central
+    /// differences.
+    pub fn has_analytic_gradient(&self) -> bool {
+        self.gradient.is_some()
+    }
+
+    /// The noise at `point` and its gradient, the change in
+    /// it per unit of noise space along x and y.
+    pub fn get_with_gradient_2d(
+        &self,
+        point: [f64; 2],
+    ) -> (f64, [f64; 2]) {
+        match &self.gradient {
+            Some(gradient) => gradient.get2(point),
+            None => {
+                (self.get(point), gradient_2d(self, point))
+            }
+        }
+    }
+
+    /// The 3D version of `get_with_gradient_2d`.
+    pub fn get_with_gradient_3d(
+        &self,
+        point: [f64; 3],
+    ) -> (f64, [f64; 3]) {
+        match &self.gradient {
+            Some(gradient) => gradient.get3(point),
+            None => {
+                (self.get(point), gradient_3d(self, point))
+            }
+        }
+    }
+}
+
+impl NoiseFn<[f64; 2]> for Noise {
+    fn get(&self, point: [f64; 2]) -> f64 {
+        self.source.get(self.warp.warp2(point))
+    }
+}
+
+impl NoiseFn<[f64; 3]> for Noise {
+    fn get(&self, point: [f64; 3]) -> f64 {
+        self.source.get(self.warp.warp3(point))
+    }
+}
+
+// the unwarped noise function
+#[derive(Clone, Debug)]
+enum Source {
+    Perlin(Perlin),
+    OpenSimplex(OpenSimplex),
+    SuperSimplex(SuperSimplex),
+    Value(Value),
+    Worley(Worley),
+    Fbm(Fbm),
+    Billow(Billow),
+    BasicMulti(BasicMulti),
+    HybridMulti(HybridMulti),
+    RidgedMulti(RidgedMulti),
+}
+
+macro_rules! impl_noise_fn {
+    ($point:ty) => {
+        impl NoiseFn<$point> for Source {
+            fn get(&self, point: $point) -> f64 {
+                match self {
+                    Source::Perlin(noise) => {
+                        noise.get(point)
+                    }
+                    Source::OpenSimplex(noise) => {
+                        noise.get(point)
+                    }
+                    Source::SuperSimplex(noise) => {
+                        noise.get(point)
+                    }
+                    Source::Value(noise) => {
+                        noise.get(point)
+                    }
+                    Source::Worley(noise) => {
+                        noise.get(point)
+                    }
+                    Source::Fbm(noise) => noise.get(point),
+                    Source::Billow(noise) => {
+                        noise.get(point)
+                    }
+                    Source::BasicMulti(noise) => {
+                        noise.get(point)
+                    }
+                    Source::HybridMulti(noise) => {
+                        noise.get(point)
+                    }
+                    Source::RidgedMulti(noise) => {
+                        noise.get(point)
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_noise_fn!([f64; 2]);
+impl_noise_fn!([f64; 3]);
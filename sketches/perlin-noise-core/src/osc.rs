@@ -0,0 +1,469 @@
+//! Remote control of the sketches over OSC: a UDP listener,
+//! a parser for OSC packets and the addresses the sketches
+//! respond to.
+//!
+//! | address             | argument | sets                  |
+//! |---------------------|----------|-----------------------|
+//! | `/noise/seed`       | int      | noise seed            |
+//! | `/noise/octaves`    | int      | fractal octaves       |
+//! | `/noise/frequency`  | float    | fractal frequency     |
+//! | `/noise/lacunarity` | float    | fractal lacunarity    |
+//! | `/noise/persistence`| float    | fractal persistence   |
+//! | `/color/hue_center` | float    | hue center, degrees   |
+//! | `/color/hue_range`  | float    | hue range, degrees    |
+//! | `/color/lightness`  | float    | Lch lightness         |
+//! | `/color/chroma`     | float    | Lch chroma            |
+//! | `/color/grayscale`  | bool/int | grayscale on or off   |
+//! | `/time/speed`       | float    | animation speed (3D)  |
+//!
+//! Ints and floats are accepted for any numeric argument.
+use crate::{color::ColorSettings, noise::NoiseConfig};
+use std::{
+    env,
+    error::Error,
+    fmt, io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+};
+
+// larger than any packet the sketches expect
+const MAX_PACKET: usize = 8192;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Blob(Vec<u8>),
+    Bool(bool),
+    Nil,
+    Impulse,
+}
+
+impl OscArg {
+    /// The argument as a number, if it is one. Booleans
+    /// count as 0 and 1.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            OscArg::Int(value) => Some(value as f64),
+            OscArg::Long(value) => Some(value as f64),
+            OscArg::Float(value) => Some(value as f64),
+            OscArg::Double(value) => Some(value),
+            OscArg::Bool(value) => {
+                Some(if value { 1. } else { 0. })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct OscError {
+    pub message: String,
+}
+
+impl OscError {
+    fn new(message: impl Into<String>) -> Self {
+        OscError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for OscError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "malformed OSC packet: {}", self.message)
+    }
+}
+
+impl Error for OscError {}
+
+/// The messages in an OSC packet, which is either a single
+/// message or a bundle of messages and nested bundles.
+/// Bundle time tags are ignored, everything applies as soon
+/// as it arrives.
+pub fn parse_packet(
+    packet: &[u8],
+) -> Result<Vec<OscMessage>, OscError> {
+    let mut messages = vec![];
+    parse_element(packet, &mut messages)?;
+    Ok(messages)
+}
+
+fn parse_element(
+    packet: &[u8],
+    messages: &mut Vec<OscMessage>,
+) -> Result<(), OscError> {
+    let mut reader = Reader { packet, at: 0 };
+    if packet.starts_with(b"#bundle\0") {
+        reader.at = 8;
+        // time tag
+        reader.take(8)?;
+        while !reader.is_empty() {
+            let size = reader.int()?;
+            if size < 0 {
+                return Err(OscError::new(
+                    "negative bundle element size",
+                ));
+            }
+            let element = reader.take(size as usize)?;
+            parse_element(element, messages)?;
+        }
+        return Ok(());
+    }
+
+    let address = reader.string()?;
+    if !address.starts_with('/') {
+        return Err(OscError::new(format!(
+            "address {:?} doesn't start with /",
+            address
+        )));
+    }
+    // a message without a type tag string has no arguments
+    let tags = if reader.is_empty() {
+        String::new()
+    } else {
+        reader.string()?
+    };
+    let tags = match tags.strip_prefix(',') {
+        Some(tags) => tags,
+        None if tags.is_empty() => "",
+        None => {
+            return Err(OscError::new(format!(
+                "type tags {:?} don't start with ,",
+                tags
+            )))
+        }
+    };
+
+    let mut args = vec![];
+    for tag in tags.chars() {
+        let arg = match tag {
+            'i' => OscArg::Int(reader.int()?),
+            'h' => OscArg::Long(i64::from_be_bytes(
+                reader.array()?,
+            )),
+            'f' => OscArg::Float(f32::from_be_bytes(
+                reader.array()?,
+            )),
+            'd' => OscArg::Double(f64::from_be_bytes(
+                reader.array()?,
+            )),
+            's' | 'S' => OscArg::String(reader.string()?),
+            'b' => {
+                let size = reader.int()?;
+                if size < 0 {
+                    return Err(OscError::new(
+                        "negative blob size",
+                    ));
+                }
+                let blob =
+                    reader.take(size as usize)?.to_vec();
+                reader.align()?;
+                OscArg::Blob(blob)
+            }
+            'T' => OscArg::Bool(true),
+            'F' => OscArg::Bool(false),
+            'N' => OscArg::Nil,
+            'I' => OscArg::Impulse,
+            _ => {
+                return Err(OscError::new(format!(
+                    "unsupported type tag {:?}",
+                    tag
+                )))
+            }
+        };
+        args.push(arg);
+    }
+
+    messages.push(OscMessage { address, args });
+    Ok(())
+}
+
+// reads the big-endian, 4-byte aligned parts of a packet
+struct Reader<'a> {
+    packet: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.at >= self.packet.len()
+    }
+
+    fn take(
+        &mut self,
+        size: usize,
+    ) -> Result<&'a [u8], OscError> {
+        let end = self
+            .at
+            .checked_add(size)
+            .filter(|&end| end <= self.packet.len())
+            .ok_or_else(|| {
+                OscError::new("packet too short")
+            })?;
+        let bytes = &self.packet[self.at..end];
+        self.at = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(
+        &mut self,
+    ) -> Result<[u8; N], OscError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn int(&mut self) -> Result<i32, OscError> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    // skip the padding up to the next multiple of 4
+    fn align(&mut self) -> Result<(), OscError> {
+        let padding = (4 - self.at % 4) % 4;
+        self.take(padding).map(|_| ())
+    }
+
+    // a null-terminated string, padded to a multiple of 4
+    fn string(&mut self) -> Result<String, OscError> {
+        let rest =
+            &self.packet[self.at.min(self.packet.len())..];
+        let length = rest
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or_else(|| {
+                OscError::new("unterminated string")
+            })?;
+        let string =
+            String::from_utf8(rest[..length].to_vec())
+                .map_err(|_| {
+                    OscError::new("string isn't UTF-8")
+                })?;
+        self.take(length + 1)?;
+        self.align()?;
+        Ok(string)
+    }
+}
+
+/// A sketch parameter set over OSC.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Control {
+    Seed(u32),
+    Octaves(usize),
+    Frequency(f64),
+    Lacunarity(f64),
+    Persistence(f64),
+    HueCenter(f64),
+    HueRange(f64),
+    Lightness(f64),
+    Chroma(f64),
+    Grayscale(bool),
+    /// Multiplier on how fast an animated sketch moves.
+    TimeSpeed(f64),
+}
+
+impl Control {
+    /// The control for a message, or `None` for an unknown
+    /// address or a missing or non-numeric first argument.
+    pub fn from_message(
+        message: &OscMessage,
+    ) -> Option<Self> {
+        let value = message.args.first()?.as_f64()?;
+        let control = match message.address.as_str() {
+            "/noise/seed" => Control::Seed(value as u32),
+            "/noise/octaves" => Control::Octaves(
+                value.clamp(1., 12.) as usize,
+            ),
+            "/noise/frequency" => Control::Frequency(value),
+            "/noise/lacunarity" => {
+                Control::Lacunarity(value)
+            }
+            "/noise/persistence" => {
+                Control::Persistence(value)
+            }
+            "/color/hue_center" => {
+                Control::HueCenter(value)
+            }
+            "/color/hue_range" => Control::HueRange(value),
+            "/color/lightness" => Control::Lightness(value),
+            "/color/chroma" => Control::Chroma(value),
+            "/color/grayscale" => {
+                Control::Grayscale(value != 0.)
+            }
+            "/time/speed" => Control::TimeSpeed(value),
+            _ => return None,
+        };
+        Some(control)
+    }
+
+    /// Set the parameter on the noise or colour settings.
+    ///
+    /// Returns whether it's one of theirs, `TimeSpeed` is
+    /// left to the sketch.
+    pub fn apply(
+        &self,
+        noise: &mut NoiseConfig,
+        colors: &mut ColorSettings,
+    ) -> bool {
+        match *self {
+            Control::Seed(seed) => noise.seed = seed,
+            Control::Octaves(octaves) => {
+                noise.octaves = octaves
+            }
+            Control::Frequency(frequency) => {
+                noise.frequency = frequency
+            }
+            Control::Lacunarity(lacunarity) => {
+                noise.lacunarity = lacunarity
+            }
+            Control::Persistence(persistence) => {
+                noise.persistence = persistence
+            }
+            Control::HueCenter(hue) => {
+                colors.hue_center = hue
+            }
+            Control::HueRange(range) => {
+                colors.hue_range = range
+            }
+            Control::Lightness(lightness) => {
+                colors.lightness = lightness
+            }
+            Control::Chroma(chroma) => {
+                colors.chroma = chroma
+            }
+            Control::Grayscale(grayscale) => {
+                colors.grayscale = grayscale
+            }
+            Control::TimeSpeed(_) => return false,
+        }
+        true
+    }
+}
+
+/// A non-blocking UDP socket receiving OSC packets.
+pub struct OscListener {
+    socket: UdpSocket,
+    buffer: Vec<u8>,
+}
+
+impl OscListener {
+    pub fn bind(
+        address: impl ToSocketAddrs,
+    ) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(OscListener {
+            socket,
+            buffer: vec![0; MAX_PACKET],
+        })
+    }
+
+    /// A listener at the port passed as `--osc <port>`, or
+    /// `None` without the flag. It listens on localhost
+    /// unless `--osc-host <address>` names other interfaces,
+    /// such as `0.0.0.0` for all of them. Failing to bind is
+    /// reported and the sketch runs without OSC.
+    pub fn from_args() -> Option<Self> {
+        let mut port = None;
+        let mut host = "127.0.0.1".to_string();
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--osc" => port = Some(args.next()),
+                "--osc-host" => {
+                    if let Some(address) = args.next() {
+                        host = address;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let port = match port?.map(|port| port.parse()) {
+            Some(Ok(port)) => port,
+            _ => {
+                eprintln!("--osc takes a port number");
+                return None;
+            }
+        };
+        match OscListener::bind((host.as_str(), port)) {
+            Ok(listener) => Some(listener),
+            Err(error) => {
+                eprintln!(
+                    "failed to listen for OSC on {}:{}: {}",
+                    host, port, error
+                );
+                None
+            }
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Every message received since the last call, without
+    /// waiting for more. Malformed packets are reported and
+    /// skipped.
+    pub fn receive(&mut self) -> Vec<OscMessage> {
+        let mut messages = vec![];
+        loop {
+            match self.socket.recv_from(&mut self.buffer) {
+                Ok((size, from)) => {
+                    match parse_packet(&self.buffer[..size])
+                    {
+                        Ok(packet) => {
+                            messages.extend(packet)
+                        }
+                        Err(error) => {
+                            eprintln!(
+                                "{} from {}",
+                                error, from
+                            )
+                        }
+                    }
+                }
+                Err(error)
+                    if error.kind()
+                        == io::ErrorKind::WouldBlock =>
+                {
+                    break
+                }
+                Err(error) => {
+                    eprintln!(
+                        "failed to receive OSC: {}",
+                        error
+                    );
+                    break;
+                }
+            }
+        }
+        messages
+    }
+
+    /// The controls among the received messages. Messages
+    /// for addresses the sketches don't know are reported.
+    pub fn controls(&mut self) -> Vec<Control> {
+        self.receive()
+            .iter()
+            .filter_map(|message| {
+                let control =
+                    Control::from_message(message);
+                if control.is_none() {
+                    eprintln!(
+                        "ignoring OSC message {} {:?}",
+                        message.address, message.args
+                    );
+                }
+                control
+            })
+            .collect()
+    }
+}
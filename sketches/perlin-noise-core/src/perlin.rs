@@ -0,0 +1,297 @@
+//! Plain Perlin noise.
+//!
+//! `noise` 0.7 glob-exports two structs named `Perlin`, so
+//! naming either is ambiguous and will stop compiling. This
+//! is the one its fractals are built on, written again
+//! operation for operation so that it gives the same values
+//! to the bit.
+use crate::batch::PermutationTable;
+use nannou::noise::{NoiseFn, Seedable};
+
+#[derive(Clone, Debug)]
+pub struct Perlin {
+    seed: u32,
+    table: PermutationTable,
+}
+
+impl Perlin {
+    pub fn new() -> Self {
+        Perlin {
+            seed: 0,
+            table: PermutationTable::new(0),
+        }
+    }
+}
+
+impl Default for Perlin {
+    fn default() -> Self {
+        Perlin::new()
+    }
+}
+
+impl Seedable for Perlin {
+    fn set_seed(self, seed: u32) -> Self {
+        if self.seed == seed {
+            return self;
+        }
+        Perlin {
+            seed,
+            table: PermutationTable::new(seed),
+        }
+    }
+
+    fn seed(&self) -> u32 {
+        self.seed
+    }
+}
+
+impl NoiseFn<[f64; 2]> for Perlin {
+    fn get(&self, [x, y]: [f64; 2]) -> f64 {
+        // the gradient (±1, ±1) picked by the low two bits
+        // of a corner's hash, dotted with the offset
+        #[rustfmt::skip]
+        fn dot(hash: usize, x: f64, y: f64) -> f64 {
+            match hash & 0b11 {
+                0 =>  x + y,
+                1 => -x + y,
+                2 =>  x - y,
+                _ => -x - y,
+            }
+        }
+
+        let floored = [x.floor(), y.floor()];
+        let [cx, cy] =
+            floored.map(|floored| floored as i64);
+        let [nx, ny] = [x - floored[0], y - floored[1]];
+        let [fx, fy] = [nx - 1., ny - 1.];
+
+        let table = &self.table;
+        let g00 = dot(table.hash2(cx, cy), nx, ny);
+        let g10 = dot(table.hash2(cx + 1, cy), fx, ny);
+        let g01 = dot(table.hash2(cx, cy + 1), nx, fy);
+        let g11 = dot(table.hash2(cx + 1, cy + 1), fx, fy);
+
+        let u = s_curve5(nx);
+        let v = s_curve5(ny);
+
+        let k0 = g00;
+        let k1 = g10 - g00;
+        let k2 = g01 - g00;
+        let k3 = g00 + g11 - g10 - g01;
+        let unscaled = k0 + k1 * u + k2 * v + k3 * u * v;
+
+        (unscaled * 2f64.sqrt()).clamp(-1., 1.)
+    }
+}
+
+impl NoiseFn<[f64; 3]> for Perlin {
+    #[allow(clippy::many_single_char_names)]
+    fn get(&self, [x, y, z]: [f64; 3]) -> f64 {
+        // one of twelve edge gradients, four of them
+        // repeated, dotted with the offset
+        #[rustfmt::skip]
+        fn dot(hash: usize, [x, y, z]: [f64; 3]) -> f64 {
+            match hash & 0b1111 {
+                0 | 12 =>  x + y,
+                1 | 13 => -x + y,
+                2 =>  x - y,
+                3 => -x - y,
+                4 =>  x + z,
+                5 => -x + z,
+                6 =>  x - z,
+                7 => -x - z,
+                8 =>  y + z,
+                9 | 14 => -y + z,
+                10 =>  y - z,
+                _ => -y - z,
+            }
+        }
+
+        let floored = [x.floor(), y.floor(), z.floor()];
+        let [cx, cy, cz] =
+            floored.map(|floored| floored as i64);
+        let near = [
+            x - floored[0],
+            y - floored[1],
+            z - floored[2],
+        ];
+        let far = near.map(|near| near - 1.);
+        let [nx, ny, nz] = near;
+        let [fx, fy, fz] = far;
+
+        let table = &self.table;
+        let g000 = dot(table.hash3(cx, cy, cz), near);
+        let g100 =
+            dot(table.hash3(cx + 1, cy, cz), [fx, ny, nz]);
+        let g010 =
+            dot(table.hash3(cx, cy + 1, cz), [nx, fy, nz]);
+        let g110 = dot(
+            table.hash3(cx + 1, cy + 1, cz),
+            [fx, fy, nz],
+        );
+        let g001 =
+            dot(table.hash3(cx, cy, cz + 1), [nx, ny, fz]);
+        let g101 = dot(
+            table.hash3(cx + 1, cy, cz + 1),
+            [fx, ny, fz],
+        );
+        let g011 = dot(
+            table.hash3(cx, cy + 1, cz + 1),
+            [nx, fy, fz],
+        );
+        let g111 =
+            dot(table.hash3(cx + 1, cy + 1, cz + 1), far);
+
+        let a = s_curve5(nx);
+        let b = s_curve5(ny);
+        let c = s_curve5(nz);
+
+        let k0 = g000;
+        let k1 = g100 - g000;
+        let k2 = g010 - g000;
+        let k3 = g001 - g000;
+        let k4 = g000 + g110 - g100 - g010;
+        let k5 = g000 + g101 - g100 - g001;
+        let k6 = g000 + g011 - g010 - g001;
+        let k7 = g100 + g010 + g001 + g111
+            - g000
+            - g110
+            - g101
+            - g011;
+        let unscaled = k0
+            + k1 * a
+            + k2 * b
+            + k3 * c
+            + k4 * a * b
+            + k5 * a * c
+            + k6 * b * c
+            + k7 * a * b * c;
+
+        (unscaled * (2. / 3f64.sqrt())).clamp(-1., 1.)
+    }
+}
+
+impl NoiseFn<[f64; 4]> for Perlin {
+    #[allow(clippy::many_single_char_names)]
+    fn get(&self, point: [f64; 4]) -> f64 {
+        // noise's 4D gradients, including the ones that
+        // repeat or don't match their intended direction
+        #[rustfmt::skip]
+        fn dot(hash: usize, [x, y, z, w]: [f64; 4]) -> f64 {
+            match hash & 0b11111 {
+                0 | 28 =>  x + y + z,
+                1 => -x + y + z,
+                2 =>  x - y + z,
+                3 =>  x + y - z,
+                4 => -x + y - z,
+                5 | 6 =>  x - y - z,
+                7 | 29 =>  x + y + w,
+                8 => -x + y + w,
+                9 =>  x - y + w,
+                10..=12 =>  x + y - w,
+                13 => -x - y - w,
+                14 | 30 =>  x + z + w,
+                15 => -x + z + w,
+                16 =>  x - z + w,
+                17..=19 =>  x + z - w,
+                20 => -x - z - w,
+                21 | 31 =>  y + z + w,
+                22 => -y + z + w,
+                23 =>  y - z + w,
+                24 =>  y - z - w,
+                25 => -y - z - w,
+                26 =>  x + y + z - w,
+                _ => -x + y + z - w,
+            }
+        }
+
+        let floored = point.map(f64::floor);
+        let cell = floored.map(|floored| floored as i64);
+        let near: [f64; 4] =
+            std::array::from_fn(|i| point[i] - floored[i]);
+        let far = near.map(|near| near - 1.);
+
+        // corners are numbered by their offsets from the
+        // cell's origin, x in the lowest bit
+        let g: [f64; 16] = std::array::from_fn(|corner| {
+            let far_in =
+                |axis: usize| corner >> axis & 1 == 1;
+            let hash =
+                self.table.hash4(std::array::from_fn(
+                    |axis| cell[axis] + far_in(axis) as i64,
+                ));
+            let offset = std::array::from_fn(|axis| {
+                if far_in(axis) {
+                    far[axis]
+                } else {
+                    near[axis]
+                }
+            });
+            dot(hash, offset)
+        });
+        let [g0000, g1000, g0100, g1100, g0010, g1010, g0110, g1110, g0001, g1001, g0101, g1101, g0011, g1011, g0111, g1111] =
+            g;
+
+        let [a, b, c, d] = near.map(s_curve5);
+
+        let k0 = g0000;
+        let k1 = g1000 - g0000;
+        let k2 = g0100 - g0000;
+        let k3 = g0010 - g0000;
+        let k4 = g0001 - g0000;
+        let k5 = g0000 + g1100 - g1000 - g0100;
+        let k6 = g0000 + g1010 - g1000 - g0010;
+        let k7 = g0000 + g1001 - g1000 - g0001;
+        let k8 = g0000 + g0110 - g0100 - g0010;
+        let k9 = g0000 + g0101 - g0100 - g0001;
+        let k10 = g0000 + g0011 - g0010 - g0001;
+        let k11 = g1110 + g1000 + g0100 + g0010
+            - g0000
+            - g0111
+            - g1011
+            - g1101;
+        let k12 = g1101 + g1000 + g0100 + g0001
+            - g0000
+            - g0111
+            - g1011
+            - g1110;
+        let k13 = g1011 + g1000 + g0010 + g0001
+            - g0000
+            - g0111
+            - g1101
+            - g1110;
+        let k14 = g0111 + g0100 + g0010 + g0001
+            - g0000
+            - g1011
+            - g1101
+            - g1110;
+        let k15 = g1111 + g1000 + g0100 + g0010 + g0001
+            - g0000
+            - g0111
+            - g1011
+            - g1101
+            - g1110;
+        let unscaled = k0
+            + k1 * a
+            + k2 * b
+            + k3 * c
+            + k4 * d
+            + k5 * a * b
+            + k6 * a * c
+            + k7 * a * d
+            + k8 * b * c
+            + k9 * b * d
+            + k10 * c * d
+            + k11 * a * b * c
+            + k12 * a * b * d
+            + k13 * a * c * d
+            + k14 * b * c * d
+            + k15 * a * b * c * d;
+
+        (unscaled * 2.).clamp(-1., 1.)
+    }
+}
+
+fn s_curve5(t: f64) -> f64 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
@@ -0,0 +1,92 @@
+//! The 1D sketches' graphs drawn straight into an image, so
+//! their captures can carry the sketch state the way the
+//! 2D and 3D ones do.
+use nannou::image::{Rgb, RgbImage};
+
+const BACKGROUND: [u8; 3] = [112, 10, 119];
+const FOREGROUND: [u8; 3] = [218, 79, 171];
+const AXIS: [u8; 3] = [65, 5, 70];
+const DOT_RADIUS: i64 = 5;
+
+/// Graph each of `lines`, values from -1 to 1, in its own
+/// horizontal band, the first one at the bottom.
+///
+/// As in the sketches' windows, -1 is at the top of a band,
+/// the newest value sits in the middle of the image with a
+/// dot on it and every older one is a pixel further left.
+pub fn plot_lines(
+    lines: &[Vec<f64>],
+    width: u32,
+    height: u32,
+) -> RgbImage {
+    let mut image = RgbImage::from_pixel(
+        width,
+        height,
+        Rgb(BACKGROUND),
+    );
+    let band = height as f64 / lines.len().max(1) as f64;
+    let center = (width / 2) as i64;
+    for (i, values) in lines.iter().enumerate() {
+        let bottom = height as f64 - band * i as f64;
+        let row = |value: f64| {
+            (bottom - band * (1. - value) / 2.) as i64
+        };
+        let axis = row(0.);
+        for x in 0..width {
+            put(&mut image, x as i64, axis, AXIS);
+        }
+
+        let mut previous = None;
+        for (age, &value) in values.iter().rev().enumerate()
+        {
+            let x = center - age as i64;
+            if x < 0 {
+                break;
+            }
+            let y = row(value);
+            // join the columns so steep runs stay
+            // connected
+            let (low, high) = match previous {
+                Some(last) => (y.min(last), y.max(last)),
+                None => (y, y),
+            };
+            for y in low..=high {
+                put(&mut image, x, y, FOREGROUND);
+            }
+            previous = Some(y);
+        }
+
+        if let Some(&value) = values.last() {
+            let y = row(value);
+            for dy in -DOT_RADIUS..=DOT_RADIUS {
+                for dx in -DOT_RADIUS..=DOT_RADIUS {
+                    if dx * dx + dy * dy
+                        <= DOT_RADIUS * DOT_RADIUS
+                    {
+                        put(
+                            &mut image,
+                            center + dx,
+                            y + dy,
+                            FOREGROUND,
+                        );
+                    }
+                }
+            }
+        }
+    }
+    image
+}
+
+// set a pixel, ignoring ones outside the image
+fn put(
+    image: &mut RgbImage,
+    x: i64,
+    y: i64,
+    color: [u8; 3],
+) {
+    if (0..image.width() as i64).contains(&x)
+        && (0..image.height() as i64).contains(&y)
+    {
+        image.put_pixel(x as u32, y as u32, Rgb(color));
+    }
+}
@@ -0,0 +1,84 @@
+//! Named sketch states saved as JSON files, one directory
+//! per sketch.
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// The directory the presets of `sketch` live in.
+pub fn preset_dir(sketch: &str) -> PathBuf {
+    Path::new("presets").join(sketch)
+}
+
+/// The file the preset `name` is kept in.
+///
+/// Names are used as file names, so ones that are empty,
+/// hidden or that would reach outside `directory` are
+/// rejected.
+pub fn preset_path(
+    directory: &Path,
+    name: &str,
+) -> Result<PathBuf, Box<dyn Error>> {
+    if name.trim().is_empty() {
+        return Err("preset names can't be empty".into());
+    }
+    if name.starts_with('.')
+        || name.contains(['/', '\\'])
+        || name.chars().any(char::is_control)
+    {
+        return Err(format!(
+            "{:?} can't be a preset name, names can't \
+             start with . or contain slashes",
+            name
+        )
+        .into());
+    }
+    Ok(directory.join(format!("{}.json", name)))
+}
+
+pub fn save_preset<T: Serialize>(
+    directory: &Path,
+    name: &str,
+    state: &T,
+) -> Result<(), Box<dyn Error>> {
+    let path = preset_path(directory, name)?;
+    fs::create_dir_all(directory)?;
+    let mut file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut file, state)?;
+    file.flush()?;
+    Ok(())
+}
+
+pub fn load_preset<T: DeserializeOwned>(
+    directory: &Path,
+    name: &str,
+) -> Result<T, Box<dyn Error>> {
+    let path = preset_path(directory, name)?;
+    let file = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(file)?)
+}
+
+/// The names of the presets in `directory`, sorted. A
+/// missing directory has no presets.
+pub fn list_presets(directory: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(directory)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "json")
+        })
+        .filter_map(|path| {
+            path.file_stem().map(|stem| {
+                stem.to_string_lossy().into_owned()
+            })
+        })
+        .collect();
+    names.sort();
+    names
+}
@@ -0,0 +1,260 @@
+//! Rendering noise into images, shared by the sketches and
+//! the headless renderer.
+use crate::{
+    color::ColorMap, graph::NoiseGraph, noise::Noise,
+    terrain::percentile_magnitude, viewport::Viewport,
+};
+use nannou::{
+    image::{Rgb, RgbImage},
+    noise::NoiseFn,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// points sampled per graph build when drawing a graph
+const GRAPH_CHUNK: usize = 4096;
+
+/// What the pixels of a 2D view are coloured by.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+pub enum ColorMode {
+    /// The noise itself.
+    #[default]
+    Value,
+    /// How steep the noise is: the length of its gradient.
+    GradientMagnitude,
+}
+
+impl ColorMode {
+    pub const ALL: [ColorMode; 2] =
+        [ColorMode::Value, ColorMode::GradientMagnitude];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorMode::Value => "value",
+            ColorMode::GradientMagnitude => {
+                "gradient magnitude"
+            }
+        }
+    }
+}
+
+/// Noise that can be sampled a row of evenly spaced points
+/// at a time, which `Noise` does with SIMD.
+pub trait SampleRow<P> {
+    /// Sample `row.len()` points, starting at `start` and
+    /// `step` apart along x.
+    fn sample_row(
+        &self,
+        start: P,
+        step: f64,
+        row: &mut [f64],
+    );
+}
+
+impl SampleRow<[f64; 2]> for dyn NoiseFn<[f64; 2]> + '_ {
+    fn sample_row(
+        &self,
+        start: [f64; 2],
+        step: f64,
+        row: &mut [f64],
+    ) {
+        for (x, value) in row.iter_mut().enumerate() {
+            *value = self.get([
+                start[0] + x as f64 * step,
+                start[1],
+            ]);
+        }
+    }
+}
+
+impl SampleRow<[f64; 2]> for Noise {
+    fn sample_row(
+        &self,
+        start: [f64; 2],
+        step: f64,
+        row: &mut [f64],
+    ) {
+        self.row_2d(start, step, row)
+    }
+}
+
+impl SampleRow<[f64; 3]> for Noise {
+    fn sample_row(
+        &self,
+        start: [f64; 3],
+        step: f64,
+        row: &mut [f64],
+    ) {
+        self.row_3d(start, step, row)
+    }
+}
+
+/// Sample `noise` at every pixel of `image` and colour it.
+/// The raw values go into `samples`, row-major, which must
+/// hold one value per pixel.
+pub fn render_2d<N>(
+    noise: &N,
+    viewport: &Viewport,
+    colors: &ColorMap,
+    image: &mut RgbImage,
+    samples: &mut [f64],
+) where
+    N: SampleRow<[f64; 2]> + ?Sized,
+{
+    let width = image.width() as usize;
+    for (y, row) in samples.chunks_mut(width).enumerate() {
+        noise.sample_row(
+            viewport.point(0., y as f64),
+            viewport.scale,
+            row,
+        );
+    }
+    for (pixel, &value) in image.pixels_mut().zip(&*samples)
+    {
+        *pixel = Rgb(colors.color(value));
+    }
+}
+
+/// Sample the slice of 3D noise at `z` at every pixel of
+/// `image`, `pixels_per_unit` pixels to a unit of noise, and
+/// colour it. Draws `graph` if there is one, `noise`
+/// otherwise.
+///
+/// Rows of pixels are sampled in parallel. The raw values go
+/// into `samples` as for `render_2d`.
+pub fn render_3d(
+    noise: &Noise,
+    graph: Option<&NoiseGraph>,
+    pixels_per_unit: f64,
+    z: f64,
+    colors: &ColorMap,
+    image: &mut RgbImage,
+    samples: &mut [f64],
+) {
+    sample_3d(
+        noise,
+        graph,
+        pixels_per_unit,
+        z,
+        image.width() as usize,
+        samples,
+    );
+    color_image(colors, samples, image);
+}
+
+/// Sample the slice of 3D noise at `z` into `samples`, a
+/// row-major image `width` pixels wide, as `render_3d`
+/// does without colouring it.
+pub fn sample_3d(
+    noise: &Noise,
+    graph: Option<&NoiseGraph>,
+    pixels_per_unit: f64,
+    z: f64,
+    width: usize,
+    samples: &mut [f64],
+) {
+    let point = |index: usize| {
+        [
+            (index % width) as f64 / pixels_per_unit,
+            (index / width) as f64 / pixels_per_unit,
+            z,
+        ]
+    };
+    match graph {
+        // a built graph can't be shared between threads, so
+        // each chunk of points builds its own
+        Some(graph) => samples
+            .par_chunks_mut(GRAPH_CHUNK)
+            .enumerate()
+            .for_each(|(chunk, samples)| {
+                graph.with_noise(&mut |noise| {
+                    for (i, sample) in
+                        samples.iter_mut().enumerate()
+                    {
+                        let index = chunk * GRAPH_CHUNK + i;
+                        *sample = noise.get(point(index));
+                    }
+                })
+            }),
+        None => samples
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                noise.sample_row(
+                    [0., y as f64 / pixels_per_unit, z],
+                    1. / pixels_per_unit,
+                    row,
+                )
+            }),
+    }
+}
+
+/// Colour each pixel of `image` by its value in `samples`,
+/// in parallel.
+pub fn color_image(
+    colors: &ColorMap,
+    samples: &[f64],
+    image: &mut RgbImage,
+) {
+    image
+        .par_chunks_mut(3)
+        .zip(samples.par_iter())
+        .for_each(|(pixel, &value)| {
+            pixel.copy_from_slice(&colors.color(value))
+        });
+}
+
+/// The length of `noise`'s gradient at every pixel of a
+/// view `width` pixels wide, into `magnitudes`, row-major.
+/// Rows are sampled in parallel.
+pub fn gradient_magnitudes_2d(
+    noise: &Noise,
+    viewport: &Viewport,
+    width: usize,
+    magnitudes: &mut [f64],
+) {
+    magnitudes.par_chunks_mut(width).enumerate().for_each(
+        |(y, row)| {
+            let [x0, y0] = viewport.point(0., y as f64);
+            for (x, magnitude) in row.iter_mut().enumerate()
+            {
+                let (_, [dx, dy]) = noise
+                    .get_with_gradient_2d([
+                        x0 + x as f64 * viewport.scale,
+                        y0,
+                    ]);
+                *magnitude = dx.hypot(dy);
+            }
+        },
+    );
+}
+
+/// Colour each pixel of `image` by its magnitude in
+/// `magnitudes`, from `colors`' colour of -1 for none to
+/// that of 1 for the steepest hundredth of the image, so a
+/// few extreme slopes don't wash out the rest.
+pub fn color_magnitudes(
+    colors: &ColorMap,
+    magnitudes: &[f64],
+    image: &mut RgbImage,
+) {
+    let scale =
+        percentile_magnitude(magnitudes, 0.99).max(1e-12);
+    image
+        .par_chunks_mut(3)
+        .zip(magnitudes.par_iter())
+        .for_each(|(pixel, &magnitude)| {
+            let value =
+                (magnitude / scale).min(1.) * 2. - 1.;
+            pixel.copy_from_slice(&colors.color(value))
+        });
+}
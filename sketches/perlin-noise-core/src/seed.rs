@@ -0,0 +1,30 @@
+use names::Generator;
+
+/// Generate a new seed using ascii characters, which tend
+/// to be more human-readable than numbers.
+pub fn random_seed() -> u32 {
+    let mut generator = Generator::default();
+    seed_from_name(&generator.next().unwrap())
+}
+
+/// Pack the first four bytes of `name` into a seed,
+/// zero-padding names shorter than that.
+pub fn seed_from_name(name: &str) -> u32 {
+    let mut bytes: [u8; 4] = [0; 4];
+    for (i, byte) in
+        name.as_bytes().iter().take(4).enumerate()
+    {
+        bytes[i] = *byte;
+    }
+    u32::from_be_bytes(bytes)
+}
+
+/// The human-readable form of a seed.
+///
+/// Seeds are generated from the first four ascii characters
+/// of a random name, so the big-endian bytes read back as
+/// those characters.
+pub fn seed_name(seed: u32) -> String {
+    String::from_utf8_lossy(&seed.to_be_bytes())
+        .into_owned()
+}
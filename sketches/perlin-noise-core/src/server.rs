@@ -0,0 +1,419 @@
+//! A local HTTP and WebSocket server for scripting a running
+//! sketch.
+//!
+//! | request              | does                                |
+//! |----------------------|-------------------------------------|
+//! | `GET /state`         | the sketch state as JSON            |
+//! | `POST /state`        | merge a partial state into it       |
+//! | `POST /capture`      | capture, replying with the path     |
+//! | `GET /events`        | WebSocket stream of redraw events   |
+//!
+//! The server answers on its own thread. Requests that need
+//! the sketch are queued until the sketch's next update, which
+//! takes them with [`Server::requests`] and answers each one.
+//!
+//! Web pages can send requests to localhost too, so requests
+//! whose `Host` isn't the server's or that come from another
+//! `Origin` are refused, and POSTs must be
+//! `application/json`, which a page can't send elsewhere
+//! without the browser asking first.
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::{
+    env,
+    error::Error,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use tiny_http::{Header, Method, Request, Response};
+use tungstenite::{
+    handshake::derive_accept_key, protocol::Role, Message,
+    WebSocket,
+};
+
+// how long a request waits for the sketch to answer
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a request asks of the sketch.
+pub enum Command {
+    GetState,
+    /// A JSON object to merge into the state, see
+    /// [`merge_patch`].
+    SetState(Value),
+    Capture,
+}
+
+/// A request waiting for the sketch to answer it.
+pub struct ApiRequest {
+    pub command: Command,
+    reply: Sender<Result<Value, String>>,
+}
+
+impl ApiRequest {
+    /// Answer with `result` as the JSON body, or an error
+    /// message that is sent back as a 400.
+    pub fn respond<T: Serialize>(
+        self,
+        result: Result<T, String>,
+    ) {
+        let result = result.and_then(|body| {
+            serde_json::to_value(body)
+                .map_err(|error| error.to_string())
+        });
+        // the client may have given up waiting
+        let _ = self.reply.send(result);
+    }
+}
+
+/// The answer to `POST /capture`.
+#[derive(Serialize)]
+pub struct Captured {
+    pub path: PathBuf,
+}
+
+type Clients = Arc<Mutex<Vec<Sender<String>>>>;
+
+pub struct Server {
+    address: SocketAddr,
+    requests: Receiver<ApiRequest>,
+    clients: Clients,
+}
+
+impl Server {
+    /// Serve on `port` of localhost. Port 0 picks a free one.
+    pub fn bind(port: u16) -> Result<Self, Box<dyn Error>> {
+        let http =
+            tiny_http::Server::http(("127.0.0.1", port))
+                .map_err(|error| error.to_string())?;
+        let address = http
+            .server_addr()
+            .to_ip()
+            .ok_or("not listening on an IP address")?;
+        let (sender, requests) = mpsc::channel();
+        let clients = Clients::default();
+        let server_clients = clients.clone();
+        let port = address.port();
+        thread::spawn(move || {
+            for request in http.incoming_requests() {
+                handle(
+                    request,
+                    port,
+                    &sender,
+                    &server_clients,
+                );
+            }
+        });
+        Ok(Server {
+            address,
+            requests,
+            clients,
+        })
+    }
+
+    /// A server on the port passed as `--server <port>`, or
+    /// `None` without the flag. Failing to start is reported
+    /// and the sketch runs without it.
+    pub fn from_args() -> Option<Self> {
+        let mut args = env::args().skip(1);
+        args.find(|arg| arg == "--server")?;
+        let port = match args
+            .next()
+            .map(|port| port.parse())
+        {
+            Some(Ok(port)) => port,
+            _ => {
+                eprintln!("--server takes a port number");
+                return None;
+            }
+        };
+        match Server::bind(port) {
+            Ok(server) => {
+                println!(
+                    "serving the sketch on http://{}",
+                    server.address
+                );
+                Some(server)
+            }
+            Err(error) => {
+                eprintln!(
+                    "failed to serve on port {}: {}",
+                    port, error
+                );
+                None
+            }
+        }
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The requests received since the last call.
+    pub fn requests(&self) -> Vec<ApiRequest> {
+        self.requests.try_iter().collect()
+    }
+
+    /// Send `event` as JSON to every connected WebSocket
+    /// client.
+    pub fn notify(&self, event: &impl Serialize) {
+        let message = match serde_json::to_string(event) {
+            Ok(message) => message,
+            Err(error) => {
+                eprintln!(
+                    "failed to send event: {}",
+                    error
+                );
+                return;
+            }
+        };
+        // clients that went away have dropped their receiver
+        self.clients.lock().unwrap().retain(|client| {
+            client.send(message.clone()).is_ok()
+        });
+    }
+}
+
+/// `state` with `patch` merged into it, or why the result
+/// isn't a valid state.
+pub fn patch_state<T: Serialize + DeserializeOwned>(
+    state: &T,
+    patch: Value,
+) -> Result<T, String> {
+    let mut value = serde_json::to_value(state)
+        .map_err(|error| error.to_string())?;
+    merge_patch(&mut value, patch);
+    serde_json::from_value(value).map_err(|error| {
+        format!("invalid state: {}", error)
+    })
+}
+
+/// Merge `patch` into `target` the way JSON merge patch
+/// (RFC 7386) does: objects merge key by key, `null` removes
+/// a key and anything else replaces what was there.
+pub fn merge_patch(target: &mut Value, patch: Value) {
+    match patch {
+        Value::Object(patch) => {
+            if !target.is_object() {
+                *target = json!({});
+            }
+            let target = target.as_object_mut().unwrap();
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.remove(&key);
+                } else {
+                    merge_patch(
+                        target
+                            .entry(key)
+                            .or_insert(Value::Null),
+                        value,
+                    );
+                }
+            }
+        }
+        patch => *target = patch,
+    }
+}
+
+fn handle(
+    mut request: Request,
+    port: u16,
+    sender: &Sender<ApiRequest>,
+    clients: &Clients,
+) {
+    if let Err(error) = check_sender(&request, port) {
+        return respond_error(request, 403, error);
+    }
+    if *request.method() == Method::Post
+        && !is_json(&request)
+    {
+        return respond_error(
+            request,
+            415,
+            "expected Content-Type: application/json",
+        );
+    }
+
+    let command = match (request.method(), request.url()) {
+        (Method::Get, "/state") => Command::GetState,
+        (Method::Post, "/state") => {
+            let mut body = String::new();
+            if let Err(error) = request
+                .as_reader()
+                .read_to_string(&mut body)
+            {
+                return respond_error(request, 400, error);
+            }
+            match serde_json::from_str(&body) {
+                Ok(patch) => Command::SetState(patch),
+                Err(error) => {
+                    return respond_error(
+                        request, 400, error,
+                    )
+                }
+            }
+        }
+        (Method::Post, "/capture") => Command::Capture,
+        (Method::Get, "/events") => {
+            return accept_websocket(request, clients)
+        }
+        _ => {
+            return respond_error(
+                request,
+                404,
+                "no such endpoint",
+            )
+        }
+    };
+
+    let (reply, answer) = mpsc::channel();
+    if sender.send(ApiRequest { command, reply }).is_err() {
+        return respond_error(
+            request,
+            503,
+            "sketch closed",
+        );
+    }
+    match answer.recv_timeout(REPLY_TIMEOUT) {
+        Ok(Ok(body)) => respond_json(request, 200, &body),
+        Ok(Err(error)) => {
+            respond_error(request, 400, error)
+        }
+        Err(_) => respond_error(
+            request,
+            503,
+            "the sketch didn't answer",
+        ),
+    }
+}
+
+fn header<'a>(
+    request: &'a Request,
+    field: &'static str,
+) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(field))
+        .map(|header| header.value.as_str())
+}
+
+// refuse requests that name another host, so a site resolving
+// its own name to 127.0.0.1 can't reach the server, and
+// requests from pages on other origins
+fn check_sender(
+    request: &Request,
+    port: u16,
+) -> Result<(), &'static str> {
+    let hosts = [
+        format!("127.0.0.1:{}", port),
+        format!("localhost:{}", port),
+    ];
+    let host = header(request, "Host").unwrap_or_default();
+    if !hosts
+        .iter()
+        .any(|expected| expected.eq_ignore_ascii_case(host))
+    {
+        return Err("unexpected Host");
+    }
+    if let Some(origin) = header(request, "Origin") {
+        let local = hosts.iter().any(|expected| {
+            origin.eq_ignore_ascii_case(&format!(
+                "http://{}",
+                expected
+            ))
+        });
+        if !local {
+            return Err(
+                "requests from other origins are refused",
+            );
+        }
+    }
+    Ok(())
+}
+
+fn is_json(request: &Request) -> bool {
+    header(request, "Content-Type").is_some_and(|value| {
+        value
+            .split(';')
+            .next()
+            .unwrap()
+            .trim()
+            .eq_ignore_ascii_case("application/json")
+    })
+}
+
+fn respond_json(
+    request: Request,
+    status: u16,
+    body: &Value,
+) {
+    let header = Header::from_bytes(
+        "Content-Type",
+        "application/json",
+    )
+    .unwrap();
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header);
+    if let Err(error) = request.respond(response) {
+        eprintln!("failed to respond: {}", error);
+    }
+}
+
+fn respond_error(
+    request: Request,
+    status: u16,
+    error: impl ToString,
+) {
+    respond_json(
+        request,
+        status,
+        &json!({ "error": error.to_string() }),
+    );
+}
+
+// finish the WebSocket handshake and forward events to the
+// client on a thread of its own until it goes away. Anything
+// the client sends is ignored.
+fn accept_websocket(request: Request, clients: &Clients) {
+    let key = header(&request, "Sec-WebSocket-Key")
+        .map(|key| derive_accept_key(key.as_bytes()));
+    let key = match key {
+        Some(key) => key,
+        None => {
+            return respond_error(
+                request,
+                400,
+                "expected a WebSocket upgrade",
+            )
+        }
+    };
+
+    let accept =
+        Header::from_bytes("Sec-WebSocket-Accept", key)
+            .unwrap();
+    let response = Response::empty(101).with_header(accept);
+    let stream = request.upgrade("websocket", response);
+    let mut socket = WebSocket::from_raw_socket(
+        stream,
+        Role::Server,
+        None,
+    );
+
+    let (client, events) = mpsc::channel::<String>();
+    clients.lock().unwrap().push(client);
+    thread::spawn(move || {
+        for event in events {
+            if socket.send(Message::Text(event)).is_err() {
+                break;
+            }
+        }
+        let _ = socket.close(None);
+    });
+}
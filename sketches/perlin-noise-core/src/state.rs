@@ -0,0 +1,65 @@
+//! What the 2D and 3D sketches store in their captures and
+//! presets and serve over HTTP. The command line tools write
+//! and read the same states, so what they make can be
+//! dropped onto a sketch and the other way around.
+use crate::{
+    color::ColorSettings,
+    contour::ContourSettings,
+    curl::CurlSettings,
+    graph::NoiseGraph,
+    noise::NoiseConfig,
+    render::ColorMode,
+    terrain::{ErosionConfig, MapSettings},
+    timeline::Timeline,
+    viewport::Viewport,
+};
+use serde::{Deserialize, Serialize};
+
+/// Everything needed to reproduce an image of the 2D
+/// sketch.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct State2d {
+    /// The sketch that saved the state.
+    pub sketch: String,
+    pub noise: NoiseConfig,
+    /// Drawn instead of `noise` when there is one.
+    #[serde(default)]
+    pub graph: Option<NoiseGraph>,
+    pub settings: ColorSettings,
+    // images saved before panning and zooming existed show
+    // the view the sketch had then
+    #[serde(default = "default_viewport")]
+    pub viewport: Viewport,
+    #[serde(default)]
+    pub erosion: ErosionConfig,
+    #[serde(default)]
+    pub maps: MapSettings,
+    #[serde(default)]
+    pub contour: ContourSettings,
+    #[serde(default)]
+    pub curl: CurlSettings,
+    #[serde(default)]
+    pub color_mode: ColorMode,
+}
+
+fn default_viewport() -> Viewport {
+    Viewport::new(1. / 500.)
+}
+
+/// Everything needed to reproduce a frame of the 3D sketch.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct State3d {
+    /// The sketch that saved the state.
+    pub sketch: String,
+    pub noise: NoiseConfig,
+    /// Drawn instead of `noise` when there is one.
+    #[serde(default)]
+    pub graph: Option<NoiseGraph>,
+    pub settings: ColorSettings,
+    /// The slice of the noise that's drawn.
+    pub z: f64,
+    // states saved before animation existed have no
+    // keyframes
+    #[serde(default)]
+    pub timeline: Timeline,
+}
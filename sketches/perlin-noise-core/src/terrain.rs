@@ -0,0 +1,757 @@
+//! Post-processing a 2D noise buffer as a terrain
+//! heightmap: eroding it, and deriving normal, hillshade,
+//! slope and curvature maps from it.
+//!
+//! Heights are noise values and the samples are `spacing`
+//! apart in noise space, so slopes are the noise's own:
+//! the same field eroded at another zoom is the same terrain
+//! at another resolution. Erosion runs on what a view
+//! shows, so panning changes which parts are eroded.
+use nannou::image::RgbImage;
+use rand_07::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// how much of a droplet's direction is kept each step
+// rather than following the slope
+const INERTIA: f64 = 0.05;
+// sediment a droplet can carry per unit of speed, water
+// and drop
+const CAPACITY: f64 = 4.;
+// capacity on flat ground, so droplets still erode a little
+const MIN_CAPACITY: f64 = 0.01;
+const ERODE_RATE: f64 = 0.3;
+const DEPOSIT_RATE: f64 = 0.3;
+const EVAPORATION: f64 = 0.01;
+const GRAVITY: f64 = 4.;
+// steps a droplet lives for, if it doesn't run off the map
+const LIFETIME: usize = 30;
+// share of the material above the talus angle moved per
+// thermal pass
+const THERMAL_RATE: f64 = 0.5;
+
+/// Settings of the erosion applied to a heightmap. Both
+/// kinds are off with zero iterations.
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+#[serde(default)]
+pub struct ErosionConfig {
+    /// Seed of where droplets land.
+    pub seed: u32,
+    /// Droplets of rain run downhill by hydraulic erosion,
+    /// each picking up and dropping sediment on the way.
+    pub droplets: usize,
+    /// Water each droplet starts with. More water carries
+    /// more sediment.
+    pub rain: f64,
+    /// Passes of thermal erosion, each moving material
+    /// down slopes steeper than the talus angle.
+    pub thermal_iterations: usize,
+    /// The steepest slope loose material rests on, in
+    /// degrees.
+    pub talus_angle: f64,
+}
+
+impl Default for ErosionConfig {
+    fn default() -> Self {
+        ErosionConfig {
+            seed: 1,
+            droplets: 0,
+            rain: 1.,
+            thermal_iterations: 0,
+            talus_angle: 30.,
+        }
+    }
+}
+
+impl ErosionConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.droplets > 0 || self.thermal_iterations > 0
+    }
+}
+
+/// Erode `heights`, `width` samples to a row and `spacing`
+/// apart in noise space: hydraulically, then thermally.
+/// The same settings and heights always erode the same
+/// way.
+pub fn erode(
+    config: &ErosionConfig,
+    heights: &mut [f64],
+    width: usize,
+    spacing: f64,
+) {
+    if !config.is_enabled() || width < 2 || spacing <= 0. {
+        return;
+    }
+    // in units of the distance between samples, where a
+    // slope of 1 is 45 degrees
+    for height in heights.iter_mut() {
+        *height /= spacing;
+    }
+    let height = heights.len() / width;
+    let mut map = HeightMap {
+        heights,
+        width,
+        height,
+    };
+    if map.height >= 2 {
+        hydraulic(config, &mut map);
+        thermal(config, &mut map);
+    }
+    for height in map.heights.iter_mut() {
+        *height *= spacing;
+    }
+}
+
+/// The heights `erode` made last, so that redrawing a view
+/// with the same erosion settings doesn't erode it again.
+///
+/// Heights are cached with a key for where they came from,
+/// which for a view of the noise is what it shows and at
+/// what size.
+pub struct ErosionCache<K> {
+    key: Option<(K, ErosionConfig)>,
+    eroded: Vec<f64>,
+}
+
+impl<K> Default for ErosionCache<K> {
+    fn default() -> Self {
+        ErosionCache {
+            key: None,
+            eroded: vec![],
+        }
+    }
+}
+
+impl<K: PartialEq> ErosionCache<K> {
+    /// Erode `heights` as `erode` does, or replace them
+    /// with the ones eroded last if those came from `key`
+    /// with the same settings.
+    pub fn erode(
+        &mut self,
+        key: K,
+        config: &ErosionConfig,
+        heights: &mut [f64],
+        width: usize,
+        spacing: f64,
+    ) {
+        if !config.is_enabled() {
+            return;
+        }
+        let cached = self.key.as_ref().is_some_and(
+            |(cached_key, cached_config)| {
+                *cached_key == key
+                    && cached_config == config
+            },
+        ) && self.eroded.len()
+            == heights.len();
+        if cached {
+            heights.copy_from_slice(&self.eroded);
+            return;
+        }
+        erode(config, heights, width, spacing);
+        self.key = Some((key, config.clone()));
+        self.eroded.clear();
+        self.eroded.extend_from_slice(heights);
+    }
+}
+
+struct HeightMap<'a> {
+    heights: &'a mut [f64],
+    width: usize,
+    height: usize,
+}
+
+impl HeightMap<'_> {
+    // the cell containing `point` and how far into it the
+    // point is, if it's on the map
+    fn cell(
+        &self,
+        [x, y]: [f64; 2],
+    ) -> Option<(usize, [f64; 2])> {
+        let in_bounds = x >= 0.
+            && y >= 0.
+            && x < (self.width - 1) as f64
+            && y < (self.height - 1) as f64;
+        in_bounds.then(|| {
+            let (cx, cy) = (x.floor(), y.floor());
+            let index =
+                cy as usize * self.width + cx as usize;
+            (index, [x - cx, y - cy])
+        })
+    }
+
+    // the height at a point in a cell, interpolated from
+    // its corners, and the slope there
+    fn height_and_gradient(
+        &self,
+        index: usize,
+        [u, v]: [f64; 2],
+    ) -> (f64, [f64; 2]) {
+        let h = &self.heights;
+        let [nw, ne, sw, se] = [
+            h[index],
+            h[index + 1],
+            h[index + self.width],
+            h[index + self.width + 1],
+        ];
+        let gradient = [
+            (ne - nw) * (1. - v) + (se - sw) * v,
+            (sw - nw) * (1. - u) + (se - ne) * u,
+        ];
+        let height = nw * (1. - u) * (1. - v)
+            + ne * u * (1. - v)
+            + sw * (1. - u) * v
+            + se * u * v;
+        (height, gradient)
+    }
+
+    // add `amount` at a point in a cell, spread over its
+    // corners
+    fn add(
+        &mut self,
+        index: usize,
+        [u, v]: [f64; 2],
+        amount: f64,
+    ) {
+        let width = self.width;
+        self.heights[index] += amount * (1. - u) * (1. - v);
+        self.heights[index + 1] += amount * u * (1. - v);
+        self.heights[index + width] +=
+            amount * (1. - u) * v;
+        self.heights[index + width + 1] += amount * u * v;
+    }
+}
+
+// run droplets downhill one after another, each eroding
+// where it speeds up and depositing where it slows down
+fn hydraulic(config: &ErosionConfig, map: &mut HeightMap) {
+    let mut rng =
+        XorShiftRng::seed_from_u64(config.seed as u64);
+    let size =
+        [(map.width - 1) as f64, (map.height - 1) as f64];
+    for _ in 0..config.droplets {
+        let mut position =
+            size.map(|size| rng.gen::<f64>() * size);
+        let mut direction = [0., 0.];
+        let mut speed = 1.;
+        let mut water = config.rain;
+        let mut sediment = 0.;
+        for _ in 0..LIFETIME {
+            let (index, offset) = match map.cell(position) {
+                Some(cell) => cell,
+                None => break,
+            };
+            let (height, gradient) =
+                map.height_and_gradient(index, offset);
+            direction = [0, 1].map(|axis| {
+                direction[axis] * INERTIA
+                    - gradient[axis] * (1. - INERTIA)
+            });
+            let length = direction[0].hypot(direction[1]);
+            if length == 0. {
+                break;
+            }
+            direction = direction.map(|d| d / length);
+            position = [0, 1].map(|axis| {
+                position[axis] + direction[axis]
+            });
+
+            // a droplet running off the map takes its
+            // sediment with it
+            let (next_index, next_offset) =
+                match map.cell(position) {
+                    Some(cell) => cell,
+                    None => break,
+                };
+            let (next_height, _) = map.height_and_gradient(
+                next_index,
+                next_offset,
+            );
+            let drop = height - next_height;
+
+            let capacity =
+                (drop * speed * water * CAPACITY)
+                    .max(MIN_CAPACITY);
+            if drop < 0. || sediment > capacity {
+                // fill the pit it climbs out of, or drop what
+                // it can't carry
+                let deposit = if drop < 0. {
+                    sediment.min(-drop)
+                } else {
+                    (sediment - capacity) * DEPOSIT_RATE
+                };
+                sediment -= deposit;
+                map.add(index, offset, deposit);
+            } else {
+                // never dig below where it's going
+                let eroded = ((capacity - sediment)
+                    * ERODE_RATE)
+                    .min(drop);
+                sediment += eroded;
+                map.add(index, offset, -eroded);
+            }
+
+            speed = (speed * speed + drop * GRAVITY)
+                .max(0.)
+                .sqrt();
+            water *= 1. - EVAPORATION;
+        }
+    }
+}
+
+// the eight neighbours of a cell and how far away they are
+const NEIGHBOURS: [(isize, isize, f64); 8] = [
+    (-1, -1, std::f64::consts::SQRT_2),
+    (0, -1, 1.),
+    (1, -1, std::f64::consts::SQRT_2),
+    (-1, 0, 1.),
+    (1, 0, 1.),
+    (-1, 1, std::f64::consts::SQRT_2),
+    (0, 1, 1.),
+    (1, 1, std::f64::consts::SQRT_2),
+];
+
+// move material from each cell to its neighbours lower
+// than the talus angle allows, in proportion to how much
+// lower they are. Each pass works out every cell's outflow
+// first and then gathers each cell's inflow, so cells can
+// be done in parallel.
+fn thermal(config: &ErosionConfig, map: &mut HeightMap) {
+    let talus = config.talus_angle.to_radians().tan();
+    let (width, height) = (map.width, map.height);
+    let neighbour =
+        |index: usize, (dx, dy, _): (isize, isize, f64)| {
+            let x = (index % width) as isize + dx;
+            let y = (index / width) as isize + dy;
+            let in_bounds = x >= 0
+                && y >= 0
+                && x < width as isize
+                && y < height as isize;
+            in_bounds
+                .then(|| y as usize * width + x as usize)
+        };
+    // how far `to` is below the slope from `from`
+    let excess = |heights: &[f64],
+                  from: usize,
+                  to: usize,
+                  distance: f64| {
+        (heights[from] - heights[to] - talus * distance)
+            .max(0.)
+    };
+
+    // each cell's outflow and the total excess it's shared
+    // out by
+    let mut outflow = vec![(0., 0.); map.heights.len()];
+    for _ in 0..config.thermal_iterations {
+        let heights = &*map.heights;
+        outflow.par_iter_mut().enumerate().for_each(
+            |(index, outflow)| {
+                let mut total = 0.;
+                let mut steepest = 0f64;
+                for offset in NEIGHBOURS {
+                    if let Some(other) =
+                        neighbour(index, offset)
+                    {
+                        let excess = excess(
+                            heights, index, other, offset.2,
+                        );
+                        total += excess;
+                        steepest = steepest.max(excess);
+                    }
+                }
+                // half the steepest excess levels that slope,
+                // so moving more would pile material up at the
+                // bottom instead
+                *outflow =
+                    (steepest * 0.5 * THERMAL_RATE, total);
+            },
+        );
+        let outflow = &outflow;
+        let mut next = heights.to_vec();
+        next.par_iter_mut().enumerate().for_each(
+            |(index, next)| {
+                *next -= outflow[index].0;
+                for offset in NEIGHBOURS {
+                    if let Some(other) =
+                        neighbour(index, offset)
+                    {
+                        let (amount, total) =
+                            outflow[other];
+                        let excess = excess(
+                            heights, other, index, offset.2,
+                        );
+                        if excess > 0. {
+                            *next +=
+                                amount * excess / total;
+                        }
+                    }
+                }
+            },
+        );
+        map.heights.copy_from_slice(&next);
+    }
+}
+
+/// A map derived from a heightmap, or the heights
+/// themselves.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+pub enum TerrainMap {
+    Height,
+    Normal,
+    Hillshade,
+    Slope,
+    Curvature,
+}
+
+impl TerrainMap {
+    pub const ALL: [TerrainMap; 5] = [
+        TerrainMap::Height,
+        TerrainMap::Normal,
+        TerrainMap::Hillshade,
+        TerrainMap::Slope,
+        TerrainMap::Curvature,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TerrainMap::Height => "height",
+            TerrainMap::Normal => "normal",
+            TerrainMap::Hillshade => "hillshade",
+            TerrainMap::Slope => "slope",
+            TerrainMap::Curvature => "curvature",
+        }
+    }
+
+    /// Values per sample: the three components of the
+    /// normal, one for the others.
+    pub fn channels(self) -> usize {
+        match self {
+            TerrainMap::Normal => 3,
+            _ => 1,
+        }
+    }
+}
+
+/// Which way the green channel of a normal map points,
+/// up for OpenGL and down for DirectX.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+pub enum GreenChannel {
+    OpenGl,
+    DirectX,
+}
+
+impl GreenChannel {
+    pub fn name(self) -> &'static str {
+        match self {
+            GreenChannel::OpenGl => "OpenGL",
+            GreenChannel::DirectX => "DirectX",
+        }
+    }
+}
+
+/// Which map of a heightmap to show and how.
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+#[serde(default)]
+pub struct MapSettings {
+    pub map: TerrainMap,
+    /// Scales the slopes a normal map is made from.
+    pub normal_strength: f64,
+    pub green: GreenChannel,
+    /// Compass direction the sun shines from, in degrees
+    /// clockwise from the top of the image.
+    pub sun_azimuth: f64,
+    /// Height of the sun above the horizon, in degrees.
+    pub sun_elevation: f64,
+}
+
+impl Default for MapSettings {
+    fn default() -> Self {
+        MapSettings {
+            map: TerrainMap::Height,
+            normal_strength: 1.,
+            green: GreenChannel::OpenGl,
+            sun_azimuth: 315.,
+            sun_elevation: 45.,
+        }
+    }
+}
+
+/// The values of `settings`' map of `heights`, `width`
+/// samples to a row and `spacing` apart in noise space,
+/// with `channels()` values per sample:
+///
+/// - `Height`: the heights.
+/// - `Normal`: the unit surface normal in tangent space,
+///   x to the right, y up or down as `green` says and z out
+///   of the surface, with the slopes scaled by
+///   `normal_strength`.
+/// - `Hillshade`: the Lambertian shading from the sun, 0
+///   to 1.
+/// - `Slope`: the steepness in degrees.
+/// - `Curvature`: the Laplacian of the heights, positive
+///   in hollows and negative on ridges.
+pub fn terrain_map(
+    settings: &MapSettings,
+    heights: &[f64],
+    width: usize,
+    spacing: f64,
+) -> Vec<f64> {
+    let channels = settings.map.channels();
+    let mut values = vec![0.; heights.len() * channels];
+    if width == 0 || spacing <= 0. {
+        return values;
+    }
+    let field = Field {
+        heights,
+        width,
+        height: heights.len() / width,
+        spacing,
+    };
+    let azimuth = settings.sun_azimuth.to_radians();
+    let elevation = settings.sun_elevation.to_radians();
+    // towards the sun, with y down the image like the rows
+    let sun = [
+        elevation.cos() * azimuth.sin(),
+        -elevation.cos() * azimuth.cos(),
+        elevation.sin(),
+    ];
+    let green = match settings.green {
+        GreenChannel::OpenGl => 1.,
+        GreenChannel::DirectX => -1.,
+    };
+    values
+        .par_chunks_mut(width * channels)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, value) in
+                row.chunks_mut(channels).enumerate()
+            {
+                let [dx, dy] = field.gradient(x, y);
+                match settings.map {
+                    TerrainMap::Height => {
+                        value[0] = heights[y * width + x]
+                    }
+                    TerrainMap::Normal => {
+                        let strength =
+                            settings.normal_strength;
+                        // rows go down, so the slope up the
+                        // image is -dy
+                        let normal = normalize([
+                            -dx * strength,
+                            dy * strength * green,
+                            1.,
+                        ]);
+                        value.copy_from_slice(&normal);
+                    }
+                    TerrainMap::Hillshade => {
+                        let normal =
+                            normalize([-dx, -dy, 1.]);
+                        let light = (0..3)
+                            .map(|i| normal[i] * sun[i])
+                            .sum::<f64>();
+                        value[0] = light.max(0.);
+                    }
+                    TerrainMap::Slope => {
+                        value[0] =
+                            dx.hypot(dy).atan().to_degrees()
+                    }
+                    TerrainMap::Curvature => {
+                        value[0] = field.laplacian(x, y)
+                    }
+                }
+            }
+        });
+    values
+}
+
+/// The gradient of `heights`, `width` samples to a row and
+/// `spacing` apart in noise space, at each sample: the
+/// change in height per unit of noise space along the rows
+/// and down the columns.
+pub fn gradients(
+    heights: &[f64],
+    width: usize,
+    spacing: f64,
+) -> Vec<[f64; 2]> {
+    let mut gradients = vec![[0.; 2]; heights.len()];
+    if width == 0 || spacing <= 0. {
+        return gradients;
+    }
+    let field = Field {
+        heights,
+        width,
+        height: heights.len() / width,
+        spacing,
+    };
+    gradients.par_chunks_mut(width).enumerate().for_each(
+        |(y, row)| {
+            for (x, gradient) in row.iter_mut().enumerate()
+            {
+                *gradient = field.gradient(x, y);
+            }
+        },
+    );
+    gradients
+}
+
+/// Draw a map returned by `terrain_map`: normals as RGB,
+/// hillshade and slope in gray, and curvature from blue
+/// ridges through white to red hollows, scaled to this
+/// map's range of curvatures.
+pub fn draw_terrain_map(
+    map: TerrainMap,
+    values: &[f64],
+    image: &mut RgbImage,
+) {
+    // a few extreme curvatures shouldn't wash out the rest
+    let curvature_scale = match map {
+        TerrainMap::Curvature => {
+            percentile_magnitude(values, 0.99).max(1e-12)
+        }
+        _ => 1.,
+    };
+    let channels = map.channels();
+    image
+        .par_chunks_mut(3)
+        .zip(values.par_chunks(channels))
+        .for_each(|(pixel, value)| {
+            let byte = |v: f64| {
+                (v.clamp(0., 1.) * 255.).round() as u8
+            };
+            let color = match map {
+                TerrainMap::Normal => [0, 1, 2]
+                    .map(|i| byte(value[i] * 0.5 + 0.5)),
+                TerrainMap::Height
+                | TerrainMap::Hillshade => {
+                    [byte(value[0]); 3]
+                }
+                TerrainMap::Slope => {
+                    [byte(value[0] / 90.); 3]
+                }
+                TerrainMap::Curvature => {
+                    let v = (value[0] / curvature_scale)
+                        .clamp(-1., 1.);
+                    let fade = byte(1. - v.abs());
+                    if v > 0. {
+                        [255, fade, fade]
+                    } else {
+                        [fade, fade, 255]
+                    }
+                }
+            };
+            pixel.copy_from_slice(&color);
+        });
+}
+
+// the magnitude `fraction` of the way through the values
+// sorted by magnitude
+pub(crate) fn percentile_magnitude(
+    values: &[f64],
+    fraction: f64,
+) -> f64 {
+    if values.is_empty() {
+        return 0.;
+    }
+    let mut magnitudes: Vec<f64> =
+        values.iter().map(|value| value.abs()).collect();
+    let index =
+        ((magnitudes.len() - 1) as f64 * fraction) as usize;
+    let (_, nth, _) = magnitudes
+        .select_nth_unstable_by(index, f64::total_cmp);
+    *nth
+}
+
+fn normalize(vector: [f64; 3]) -> [f64; 3] {
+    let length =
+        vector.iter().map(|v| v * v).sum::<f64>().sqrt();
+    vector.map(|v| v / length)
+}
+
+// heights sampled `spacing` apart, with differences taken
+// one-sided at the edges
+struct Field<'a> {
+    heights: &'a [f64],
+    width: usize,
+    height: usize,
+    spacing: f64,
+}
+
+impl Field<'_> {
+    fn at(&self, x: usize, y: usize) -> f64 {
+        self.heights[y * self.width + x]
+    }
+
+    // the neighbours either side along an axis, as far as
+    // the edges allow
+    fn around(index: usize, size: usize) -> (usize, usize) {
+        (index.saturating_sub(1), (index + 1).min(size - 1))
+    }
+
+    // change in height per unit of noise space along x
+    // and y
+    fn gradient(&self, x: usize, y: usize) -> [f64; 2] {
+        let (left, right) = Field::around(x, self.width);
+        let (up, down) = Field::around(y, self.height);
+        let difference = |a: f64, b: f64, steps: usize| {
+            if steps == 0 {
+                0.
+            } else {
+                (b - a) / (steps as f64 * self.spacing)
+            }
+        };
+        [
+            difference(
+                self.at(left, y),
+                self.at(right, y),
+                right - left,
+            ),
+            difference(
+                self.at(x, up),
+                self.at(x, down),
+                down - up,
+            ),
+        ]
+    }
+
+    // the sum of the second derivatives along x and y,
+    // which at the edges are those a sample in
+    fn laplacian(&self, x: usize, y: usize) -> f64 {
+        // the second difference of three heights around
+        // `index` along an axis
+        let second =
+            |index: usize,
+             size: usize,
+             at: &dyn Fn(usize) -> f64| {
+                if size < 3 {
+                    return 0.;
+                }
+                let index = index.clamp(1, size - 2);
+                at(index - 1) - 2. * at(index)
+                    + at(index + 1)
+            };
+        (second(x, self.width, &|x| self.at(x, y))
+            + second(y, self.height, &|y| self.at(x, y)))
+            / (self.spacing * self.spacing)
+    }
+}
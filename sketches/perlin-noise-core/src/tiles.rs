@@ -0,0 +1,389 @@
+//! A cache of the noise field sampled in tiles at several
+//! zoom levels, like a slippy map's, so that panning reuses
+//! what has already been sampled and zooming shows a
+//! blurrier tile while the sharper one is sampled.
+//!
+//! A tile at level `l` is `TILE_SIZE` samples square, `2^l`
+//! apart in noise space, and tile `x`, `y` starts at the
+//! noise point `(x, y) * TILE_SIZE * 2^l`. A view is drawn
+//! from the level whose samples are as close together as its
+//! pixels or closer.
+use crate::{
+    graph::NoiseGraph, noise::NoiseConfig,
+    render::SampleRow, viewport::Viewport,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{
+    collections::{
+        hash_map::DefaultHasher, HashMap, HashSet,
+    },
+    hash::{Hash, Hasher},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+};
+
+pub const TILE_SIZE: usize = 256;
+// coarser levels looked at for a tile that isn't ready
+const FALLBACK_LEVELS: i32 = 4;
+// how much coarser the tile sampled straight away is when
+// there is nothing to fall back on
+const PLACEHOLDER_LEVELS: i32 = 2;
+
+/// What a `TileCache` samples.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum TileSource {
+    Noise(NoiseConfig),
+    Graph(NoiseGraph),
+}
+
+impl TileSource {
+    // tiles of sources with the same parameters are shared
+    fn id(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_string(self)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn sample(&self, key: TileKey) -> Vec<f64> {
+        let mut samples = vec![0.; TILE_SIZE * TILE_SIZE];
+        match self {
+            TileSource::Noise(config) => sample_tile(
+                &config.build(),
+                key,
+                &mut samples,
+            ),
+            TileSource::Graph(graph) => {
+                graph.with_noise(&mut |noise| {
+                    sample_tile(noise, key, &mut samples)
+                })
+            }
+        }
+        samples
+    }
+}
+
+fn sample_tile<N>(
+    noise: &N,
+    key: TileKey,
+    samples: &mut [f64],
+) where
+    N: SampleRow<[f64; 2]> + ?Sized,
+{
+    let spacing = key.spacing();
+    let [left, top] = key.origin();
+    for (y, row) in
+        samples.chunks_mut(TILE_SIZE).enumerate()
+    {
+        noise.sample_row(
+            [left, top + y as f64 * spacing],
+            spacing,
+            row,
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    /// Identifies the source's parameters.
+    pub source: u64,
+    pub level: i32,
+    pub x: i64,
+    pub y: i64,
+}
+
+impl TileKey {
+    /// Noise-space distance between the tile's samples.
+    pub fn spacing(self) -> f64 {
+        2f64.powi(self.level)
+    }
+
+    /// Noise-space point of the tile's top left sample.
+    pub fn origin(self) -> [f64; 2] {
+        let span = TILE_SIZE as f64 * self.spacing();
+        [self.x as f64 * span, self.y as f64 * span]
+    }
+
+    /// The tile a level coarser that covers this one.
+    pub fn parent(self) -> TileKey {
+        TileKey {
+            level: self.level + 1,
+            x: self.x.div_euclid(2),
+            y: self.y.div_euclid(2),
+            ..self
+        }
+    }
+}
+
+struct Tile {
+    samples: Vec<f64>,
+    // the frame it was last drawn in
+    last_used: u64,
+}
+
+// a sampled tile, or `None` for one skipped because it
+// was no longer wanted
+type Sampled = (TileKey, Option<Vec<f64>>);
+
+/// Tiles sampled so far, up to a number of them, and the
+/// ones being sampled on rayon's threads.
+pub struct TileCache {
+    capacity: usize,
+    tiles: HashMap<TileKey, Tile>,
+    pending: HashSet<TileKey>,
+    // the tiles the last view needed, so that tiles scrolled
+    // out of view before their turn aren't sampled
+    wanted: Arc<Mutex<HashSet<TileKey>>>,
+    sender: Sender<Sampled>,
+    receiver: Receiver<Sampled>,
+    frame: u64,
+}
+
+impl TileCache {
+    /// A cache keeping up to `capacity` tiles, of
+    /// `TILE_SIZE`² samples each. Tiles drawn longest ago
+    /// go first.
+    pub fn new(capacity: usize) -> Self {
+        let (sender, receiver) = channel();
+        TileCache {
+            capacity,
+            tiles: HashMap::new(),
+            pending: HashSet::new(),
+            wanted: Arc::default(),
+            sender,
+            receiver,
+            frame: 0,
+        }
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    /// Store the tiles sampled since the last call,
+    /// returning whether there were any.
+    pub fn receive(&mut self) -> bool {
+        let mut received = false;
+        while let Ok((key, samples)) =
+            self.receiver.try_recv()
+        {
+            self.pending.remove(&key);
+            if let Some(samples) = samples {
+                self.insert(key, samples);
+                received = true;
+            }
+        }
+        received
+    }
+
+    /// Fill `samples`, `width` values to a row, with what
+    /// `viewport` shows of `source`, from the sharpest tiles
+    /// sampled so far, and start sampling the tiles it
+    /// needs that aren't. Returns whether all of it came
+    /// from tiles at the right level.
+    ///
+    /// Parts with no tile to fall back on are sampled
+    /// straight away at a lower resolution.
+    pub fn render(
+        &mut self,
+        source: &TileSource,
+        viewport: &Viewport,
+        width: usize,
+        samples: &mut [f64],
+    ) -> bool {
+        self.frame += 1;
+        let id = source.id();
+        let level = viewport.scale.log2().floor() as i32;
+        let spacing = 2f64.powi(level);
+        // the tile holding the sample nearest a coordinate
+        let tile = |coordinate: f64| {
+            (nearest_index(coordinate / spacing)
+                / TILE_SIZE as f64)
+                .floor() as i64
+        };
+        let height = samples.len() / width.max(1);
+        let [left, top] = viewport.point(0., 0.);
+        let [right, bottom] =
+            viewport.point(width as f64, height as f64);
+        let columns = tile(left)..=tile(right);
+        let rows = tile(top)..=tile(bottom);
+
+        // the tile each part of the view is drawn from
+        let mut drawn = vec![];
+        let mut wanted = HashSet::new();
+        let mut missing = vec![];
+        for y in rows.clone() {
+            for x in columns.clone() {
+                let key = TileKey {
+                    source: id,
+                    level,
+                    x,
+                    y,
+                };
+                let found = self.find(key);
+                if found != Some(key) {
+                    wanted.insert(key);
+                    if !self.pending.contains(&key) {
+                        missing.push(key);
+                    }
+                }
+                drawn.push(found.unwrap_or_else(|| {
+                    self.placeholder(source, key)
+                }));
+            }
+        }
+        for key in &drawn {
+            if let Some(tile) = self.tiles.get_mut(key) {
+                tile.last_used = self.frame;
+            }
+        }
+        let complete = wanted.is_empty();
+        *self.wanted.lock().unwrap() = wanted;
+        self.spawn(source, missing);
+        self.evict();
+
+        let tiles: Vec<(TileKey, &[f64])> = drawn
+            .iter()
+            .map(|key| (*key, &self.tiles[key].samples[..]))
+            .collect();
+        let counts =
+            [columns.clone().count(), rows.clone().count()];
+        let first = [*columns.start(), *rows.start()];
+        samples.par_chunks_mut(width).enumerate().for_each(
+            |(y, row)| {
+                for (x, sample) in
+                    row.iter_mut().enumerate()
+                {
+                    let point =
+                        viewport.point(x as f64, y as f64);
+                    let [column, row] =
+                        [0, 1].map(|axis| {
+                            let tile = tile(point[axis])
+                                - first[axis];
+                            (tile.max(0) as usize)
+                                .min(counts[axis] - 1)
+                        });
+                    let (key, samples) =
+                        tiles[row * counts[0] + column];
+                    *sample = nearest(key, samples, point);
+                }
+            },
+        );
+        complete
+    }
+
+    // the sharpest tile sampled so far covering `key`'s
+    fn find(&self, key: TileKey) -> Option<TileKey> {
+        let mut key = key;
+        for _ in 0..=FALLBACK_LEVELS {
+            if self.tiles.contains_key(&key) {
+                return Some(key);
+            }
+            key = key.parent();
+        }
+        None
+    }
+
+    // sample a coarser tile covering `key`'s on this thread
+    fn placeholder(
+        &mut self,
+        source: &TileSource,
+        key: TileKey,
+    ) -> TileKey {
+        let mut placeholder = key;
+        for _ in 0..PLACEHOLDER_LEVELS {
+            placeholder = placeholder.parent();
+        }
+        if !self.tiles.contains_key(&placeholder) {
+            self.insert(
+                placeholder,
+                source.sample(placeholder),
+            );
+        }
+        placeholder
+    }
+
+    fn spawn(
+        &mut self,
+        source: &TileSource,
+        keys: Vec<TileKey>,
+    ) {
+        if keys.is_empty() {
+            return;
+        }
+        let source = Arc::new(source.clone());
+        for key in keys {
+            self.pending.insert(key);
+            let source = source.clone();
+            let wanted = self.wanted.clone();
+            let sender = self.sender.clone();
+            rayon::spawn(move || {
+                let samples = wanted
+                    .lock()
+                    .unwrap()
+                    .contains(&key)
+                    .then(|| source.sample(key));
+                // the cache may have been dropped
+                let _ = sender.send((key, samples));
+            });
+        }
+    }
+
+    fn insert(&mut self, key: TileKey, samples: Vec<f64>) {
+        self.tiles.insert(
+            key,
+            Tile {
+                samples,
+                last_used: self.frame,
+            },
+        );
+    }
+
+    // drop the tiles drawn longest ago until there are no
+    // more than `capacity`, keeping the ones in view
+    fn evict(&mut self) {
+        while self.tiles.len() > self.capacity {
+            let oldest = self
+                .tiles
+                .iter()
+                .filter(|(_, tile)| {
+                    tile.last_used < self.frame
+                })
+                .min_by_key(|(_, tile)| tile.last_used)
+                .map(|(key, _)| *key);
+            match oldest {
+                Some(key) => self.tiles.remove(&key),
+                None => break,
+            };
+        }
+    }
+}
+
+// the sample of `key`'s tile nearest `point`. A coarser
+// tile drawn in place of a missing one can have the nearest
+// sample in a neighbour, and gives the one on its edge.
+fn nearest(
+    key: TileKey,
+    samples: &[f64],
+    point: [f64; 2],
+) -> f64 {
+    let spacing = key.spacing();
+    let origin = key.origin();
+    let [x, y] = [0, 1].map(|axis| {
+        let offset = (point[axis] - origin[axis]) / spacing;
+        (nearest_index(offset).max(0.) as usize)
+            .min(TILE_SIZE - 1)
+    });
+    samples[y * TILE_SIZE + x]
+}
+
+// the whole number nearest `offset`, halves rounding up so
+// that they go the same way on both sides of the origin
+fn nearest_index(offset: f64) -> f64 {
+    (offset + 0.5).floor()
+}
@@ -0,0 +1,305 @@
+//! Keyframed parameter animation. A timeline holds a track
+//! of keyframes per animated parameter and is evaluated at
+//! the time of a fixed-step clock, so an animation plays the
+//! same whether it runs live or is rendered headless.
+use crate::{color::ColorSettings, noise::NoiseConfig};
+use serde::{Deserialize, Serialize};
+
+/// Steps of the animation clock per second of timeline.
+/// Every frame advances the clock by one step.
+pub const FRAME_RATE: f64 = 60.;
+
+/// How a keyframe's value moves towards the next one.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+pub enum Easing {
+    /// Hold the value until the next keyframe.
+    Step,
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 5] = [
+        Easing::Step,
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Easing::Step => "step",
+            Easing::Linear => "linear",
+            Easing::EaseIn => "ease in",
+            Easing::EaseOut => "ease out",
+            Easing::EaseInOut => "ease in-out",
+        }
+    }
+
+    /// Progress between two keyframes, for linear progress
+    /// `t` in 0..1.
+    pub fn apply(self, t: f64) -> f64 {
+        match self {
+            Easing::Step => 0.,
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1. - (1. - t).powi(3),
+            Easing::EaseInOut => t * t * (3. - 2. * t),
+        }
+    }
+}
+
+/// An animatable parameter.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+pub enum Parameter {
+    HueCenter,
+    HueRange,
+    Lightness,
+    Chroma,
+    Frequency,
+    Persistence,
+    WarpStrength,
+    WarpScale,
+    /// Size of the noise's features, relative to the
+    /// sketch's scale.
+    Scale,
+    /// Position along the sketch's time axis.
+    Z,
+}
+
+impl Parameter {
+    pub const ALL: [Parameter; 10] = [
+        Parameter::HueCenter,
+        Parameter::HueRange,
+        Parameter::Lightness,
+        Parameter::Chroma,
+        Parameter::Frequency,
+        Parameter::Persistence,
+        Parameter::WarpStrength,
+        Parameter::WarpScale,
+        Parameter::Scale,
+        Parameter::Z,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Parameter::HueCenter => "hue center",
+            Parameter::HueRange => "hue range",
+            Parameter::Lightness => "lightness",
+            Parameter::Chroma => "chroma",
+            Parameter::Frequency => "frequency",
+            Parameter::Persistence => "persistence",
+            Parameter::WarpStrength => "warp strength",
+            Parameter::WarpScale => "warp scale",
+            Parameter::Scale => "scale",
+            Parameter::Z => "z",
+        }
+    }
+
+    /// The parameter's value in the noise or colour
+    /// settings, or `None` for those the sketch keeps.
+    pub fn get(
+        self,
+        noise: &NoiseConfig,
+        colors: &ColorSettings,
+    ) -> Option<f64> {
+        match self {
+            Parameter::HueCenter => Some(colors.hue_center),
+            Parameter::HueRange => Some(colors.hue_range),
+            Parameter::Lightness => Some(colors.lightness),
+            Parameter::Chroma => Some(colors.chroma),
+            Parameter::Frequency => Some(noise.frequency),
+            Parameter::Persistence => {
+                Some(noise.persistence)
+            }
+            Parameter::WarpStrength => {
+                Some(noise.warp.strength)
+            }
+            Parameter::WarpScale => Some(noise.warp.scale),
+            Parameter::Scale | Parameter::Z => None,
+        }
+    }
+
+    /// Set the parameter in the noise or colour settings.
+    ///
+    /// Returns whether it's one of theirs, `Scale` and `Z`
+    /// are left to the sketch.
+    pub fn set(
+        self,
+        value: f64,
+        noise: &mut NoiseConfig,
+        colors: &mut ColorSettings,
+    ) -> bool {
+        match self {
+            Parameter::HueCenter => {
+                colors.hue_center = value
+            }
+            Parameter::HueRange => colors.hue_range = value,
+            Parameter::Lightness => {
+                colors.lightness = value
+            }
+            Parameter::Chroma => colors.chroma = value,
+            Parameter::Frequency => noise.frequency = value,
+            Parameter::Persistence => {
+                noise.persistence = value
+            }
+            Parameter::WarpStrength => {
+                noise.warp.strength = value
+            }
+            Parameter::WarpScale => {
+                noise.warp.scale = value
+            }
+            Parameter::Scale | Parameter::Z => {
+                return false
+            }
+        }
+        true
+    }
+}
+
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+pub struct Keyframe {
+    /// Seconds from the start of the timeline.
+    pub time: f64,
+    pub value: f64,
+    /// How the value moves from this keyframe to the next.
+    pub easing: Easing,
+}
+
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+pub struct Track {
+    pub parameter: Parameter,
+    /// Kept in order of time.
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+    /// The value at `time`. Before the first keyframe and
+    /// after the last the value holds, and an empty track
+    /// has none.
+    pub fn value_at(&self, time: f64) -> Option<f64> {
+        let next = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time > time);
+        let (from, to) = match next {
+            Some(0) => {
+                return Some(self.keyframes[0].value)
+            }
+            Some(next) => (
+                &self.keyframes[next - 1],
+                &self.keyframes[next],
+            ),
+            None => {
+                return Some(self.keyframes.last()?.value)
+            }
+        };
+        let t = (time - from.time) / (to.time - from.time);
+        let t = from.easing.apply(t);
+        Some(from.value + (to.value - from.value) * t)
+    }
+
+    /// Insert a keyframe, replacing one at the same time.
+    pub fn insert(&mut self, keyframe: Keyframe) {
+        self.keyframes
+            .retain(|other| other.time != keyframe.time);
+        self.keyframes.push(keyframe);
+        self.sort();
+    }
+
+    /// Restore the order of time after keyframes were
+    /// edited.
+    pub fn sort(&mut self) {
+        self.keyframes
+            .sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+}
+
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+pub struct Timeline {
+    pub tracks: Vec<Track>,
+    /// Seconds until the timeline ends or loops.
+    pub length: f64,
+    pub looping: bool,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Timeline {
+            tracks: vec![],
+            length: 10.,
+            looping: true,
+        }
+    }
+}
+
+impl Timeline {
+    /// The number of frames in one play through.
+    pub fn frames(&self) -> u64 {
+        (self.length * FRAME_RATE).round() as u64
+    }
+
+    /// The clock's time a step after `time`: wrapped to the
+    /// start when looping, and `None` past the end
+    /// otherwise.
+    pub fn step(&self, time: f64) -> Option<f64> {
+        let next = time + 1. / FRAME_RATE;
+        if next < self.length {
+            Some(next)
+        } else if self.looping {
+            Some(next % self.length.max(1. / FRAME_RATE))
+        } else {
+            None
+        }
+    }
+
+    /// The value of each animated parameter at `time`.
+    pub fn values_at(
+        &self,
+        time: f64,
+    ) -> Vec<(Parameter, f64)> {
+        self.tracks
+            .iter()
+            .filter_map(|track| {
+                Some((
+                    track.parameter,
+                    track.value_at(time)?,
+                ))
+            })
+            .collect()
+    }
+
+    pub fn track(
+        &self,
+        parameter: Parameter,
+    ) -> Option<&Track> {
+        self.tracks
+            .iter()
+            .find(|track| track.parameter == parameter)
+    }
+}
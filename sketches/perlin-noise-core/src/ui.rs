@@ -0,0 +1,692 @@
+//! egui controls shared by the sketches' Settings windows.
+use crate::{
+    capture::{CaptureConfig, CaptureWindow},
+    contour::ContourSettings,
+    curl::{CurlSettings, CurlView},
+    flow::{FlowConfig, FlowMode},
+    noise::{NoiseConfig, NoiseType},
+    seed::random_seed,
+    terrain::{
+        ErosionConfig, GreenChannel, MapSettings,
+        TerrainMap,
+    },
+    timeline::{
+        Easing, Keyframe, Parameter, Timeline, Track,
+    },
+    warp::WarpConfig,
+};
+use nannou_egui::egui;
+
+/// Controls for the noise type, for fractal noise its
+/// octaves, frequency, lacunarity and persistence, and for
+/// the domain warp.
+///
+/// Returns whether anything changed.
+pub fn noise_settings(
+    ui: &mut egui::Ui,
+    config: &mut NoiseConfig,
+) -> bool {
+    let mut changed = false;
+
+    ui.label("Noise:");
+    let noise_type = config.noise_type;
+    egui::ComboBox::from_id_source("noise_type")
+        .selected_text(noise_type.name())
+        .show_ui(ui, |ui| {
+            for option in NoiseType::ALL {
+                ui.selectable_value(
+                    &mut config.noise_type,
+                    option,
+                    option.name(),
+                );
+            }
+        });
+    changed |= noise_type != config.noise_type;
+
+    if config.noise_type.is_fractal() {
+        ui.label("Octaves:");
+        changed |= ui
+            .add(egui::Slider::new(
+                &mut config.octaves,
+                1..=12,
+            ))
+            .changed();
+
+        ui.label("Frequency:");
+        changed |= ui
+            .add(egui::Slider::new(
+                &mut config.frequency,
+                0.1..=8.0,
+            ))
+            .changed();
+
+        ui.label("Lacunarity:");
+        changed |= ui
+            .add(egui::Slider::new(
+                &mut config.lacunarity,
+                1.0..=4.0,
+            ))
+            .changed();
+
+        ui.label("Persistence:");
+        changed |= ui
+            .add(egui::Slider::new(
+                &mut config.persistence,
+                0.0..=1.0,
+            ))
+            .changed();
+    }
+
+    changed |= ui
+        .collapsing("Domain warp", |ui| {
+            warp_settings(ui, &mut config.warp)
+        })
+        .body_returned
+        .unwrap_or(false);
+
+    changed
+}
+
+/// Controls for the domain warp's iterations, strength,
+/// scale and seed.
+///
+/// Returns whether anything changed.
+pub fn warp_settings(
+    ui: &mut egui::Ui,
+    config: &mut WarpConfig,
+) -> bool {
+    let mut changed = false;
+
+    ui.label("Iterations:");
+    changed |= ui
+        .add(egui::Slider::new(
+            &mut config.iterations,
+            0..=4,
+        ))
+        .changed();
+
+    ui.label("Strength:");
+    changed |= ui
+        .add(egui::Slider::new(
+            &mut config.strength,
+            0.0..=4.0,
+        ))
+        .changed();
+
+    ui.label("Scale:");
+    changed |= ui
+        .add(egui::Slider::new(
+            &mut config.scale,
+            0.1..=8.0,
+        ))
+        .changed();
+
+    ui.horizontal(|ui| {
+        ui.label("Seed:");
+        changed |= ui
+            .add(egui::DragValue::new(&mut config.seed))
+            .changed();
+        if ui.button("random").clicked() {
+            config.seed = random_seed();
+            changed = true;
+        }
+    });
+
+    changed
+}
+
+/// Controls for the number of droplets and rain of
+/// hydraulic erosion, the iterations and talus angle of
+/// thermal erosion, and the seed.
+///
+/// Returns whether anything changed.
+pub fn erosion_settings(
+    ui: &mut egui::Ui,
+    config: &mut ErosionConfig,
+) -> bool {
+    let mut changed = false;
+
+    ui.label("Droplets:");
+    changed |= ui
+        .add(
+            egui::Slider::new(
+                &mut config.droplets,
+                0..=200_000,
+            )
+            .logarithmic(true),
+        )
+        .changed();
+
+    ui.label("Rain:");
+    changed |= ui
+        .add(egui::Slider::new(&mut config.rain, 0.1..=4.0))
+        .changed();
+
+    ui.label("Thermal iterations:");
+    changed |= ui
+        .add(egui::Slider::new(
+            &mut config.thermal_iterations,
+            0..=200,
+        ))
+        .changed();
+
+    ui.label("Talus angle:");
+    changed |= ui
+        .add(egui::Slider::new(
+            &mut config.talus_angle,
+            0.0..=89.0,
+        ))
+        .changed();
+
+    ui.horizontal(|ui| {
+        ui.label("Seed:");
+        changed |= ui
+            .add(egui::DragValue::new(&mut config.seed))
+            .changed();
+        if ui.button("random").clicked() {
+            config.seed = random_seed();
+            changed = true;
+        }
+    });
+
+    changed
+}
+
+/// Controls for which map of the heightmap is shown, the
+/// strength and green channel of normal maps and the sun of
+/// hillshades.
+///
+/// Returns whether anything changed.
+pub fn map_settings(
+    ui: &mut egui::Ui,
+    settings: &mut MapSettings,
+) -> bool {
+    let mut changed = false;
+
+    ui.label("Show:");
+    let map = settings.map;
+    egui::ComboBox::from_id_source("terrain_map")
+        .selected_text(map.name())
+        .show_ui(ui, |ui| {
+            for option in TerrainMap::ALL {
+                ui.selectable_value(
+                    &mut settings.map,
+                    option,
+                    option.name(),
+                );
+            }
+        });
+    changed |= map != settings.map;
+
+    match settings.map {
+        TerrainMap::Normal => {
+            ui.label("Strength:");
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut settings.normal_strength,
+                    0.0..=10.0,
+                ))
+                .changed();
+
+            ui.horizontal(|ui| {
+                ui.label("Green:");
+                for green in [
+                    GreenChannel::OpenGl,
+                    GreenChannel::DirectX,
+                ] {
+                    changed |= ui
+                        .radio_value(
+                            &mut settings.green,
+                            green,
+                            green.name(),
+                        )
+                        .changed();
+                }
+            });
+        }
+        TerrainMap::Hillshade => {
+            ui.label("Sun azimuth:");
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut settings.sun_azimuth,
+                    0.0..=360.0,
+                ))
+                .changed();
+
+            ui.label("Sun elevation:");
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut settings.sun_elevation,
+                    0.0..=90.0,
+                ))
+                .changed();
+        }
+        _ => {}
+    }
+
+    changed
+}
+
+/// Controls for isolines: whether they're drawn, their
+/// levels, line weight and colour, whether the noise is
+/// drawn under them, and a button to export them as SVG.
+///
+/// Returns whether anything changed, and whether the export
+/// button was clicked.
+pub fn contour_settings(
+    ui: &mut egui::Ui,
+    settings: &mut ContourSettings,
+) -> (bool, bool) {
+    let mut changed = false;
+
+    changed |= ui
+        .checkbox(&mut settings.enabled, "Draw isolines")
+        .changed();
+
+    ui.label("Levels:");
+    changed |= ui
+        .add(egui::Slider::new(
+            &mut settings.levels,
+            1..=64,
+        ))
+        .changed();
+
+    ui.label("Lowest:");
+    changed |= ui
+        .add(egui::Slider::new(
+            &mut settings.lower,
+            -2.0..=2.0,
+        ))
+        .changed();
+
+    ui.label("Highest:");
+    changed |= ui
+        .add(egui::Slider::new(
+            &mut settings.upper,
+            -2.0..=2.0,
+        ))
+        .changed();
+
+    ui.label("Line weight:");
+    changed |= ui
+        .add(egui::Slider::new(
+            &mut settings.line_weight,
+            0.25..=8.0,
+        ))
+        .changed();
+
+    ui.horizontal(|ui| {
+        ui.label("Colour:");
+        changed |= ui
+            .color_edit_button_srgb(&mut settings.color)
+            .changed();
+    });
+
+    changed |= ui
+        .checkbox(&mut settings.lines_only, "Lines only")
+        .changed();
+
+    let export = ui.button("export SVG").clicked();
+
+    (changed, export)
+}
+
+/// Controls for how noise becomes a flow, the number of
+/// particles and how they move, and their trails.
+///
+/// Returns whether anything changed.
+pub fn flow_settings(
+    ui: &mut egui::Ui,
+    config: &mut FlowConfig,
+) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        ui.label("Flow:");
+        for mode in FlowMode::ALL {
+            changed |= ui
+                .radio_value(
+                    &mut config.mode,
+                    mode,
+                    mode.name(),
+                )
+                .changed();
+        }
+    });
+
+    if config.mode == FlowMode::Angle {
+        ui.label("Angle range (turns):");
+        changed |= ui
+            .add(egui::Slider::new(
+                &mut config.angle_range,
+                0.1..=4.0,
+            ))
+            .changed();
+    }
+
+    ui.label("Particles:");
+    changed |= ui
+        .add(
+            egui::Slider::new(
+                &mut config.particles,
+                0..=50_000,
+            )
+            .logarithmic(true),
+        )
+        .changed();
+
+    ui.label("Step:");
+    changed |= ui
+        .add(egui::Slider::new(
+            &mut config.step,
+            0.1..=10.0,
+        ))
+        .changed();
+
+    ui.label("Lifetime:");
+    changed |= ui
+        .add(egui::Slider::new(
+            &mut config.lifetime,
+            1..=1000,
+        ))
+        .changed();
+
+    ui.label("Fade:");
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut config.fade, 0.0..=0.2)
+                .logarithmic(true),
+        )
+        .changed();
+
+    ui.label("Ink:");
+    changed |= ui
+        .add(egui::Slider::new(&mut config.ink, 0.0..=1.0))
+        .changed();
+
+    changed
+}
+
+/// Controls for how the curl of the noise is drawn: as
+/// arrows, with their spacing, or by line integral
+/// convolution, with how far it follows the flow.
+///
+/// Returns whether anything changed.
+pub fn curl_settings(
+    ui: &mut egui::Ui,
+    settings: &mut CurlSettings,
+) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        ui.label("Show:");
+        for view in CurlView::ALL {
+            changed |= ui
+                .radio_value(
+                    &mut settings.view,
+                    view,
+                    view.name(),
+                )
+                .changed();
+        }
+    });
+
+    match settings.view {
+        CurlView::Arrows => {
+            ui.label("Spacing:");
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut settings.arrow_spacing,
+                    8..=128,
+                ))
+                .changed();
+        }
+        CurlView::Lic => {
+            ui.label("Length:");
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut settings.lic_length,
+                    1..=50,
+                ))
+                .changed();
+        }
+        CurlView::Off => {}
+    }
+
+    changed
+}
+
+/// Controls for where captures go and what they capture,
+/// with a button to take one.
+///
+/// `windows` lists the capture targets the sketch supports.
+/// Returns whether the capture button was clicked.
+pub fn capture_settings(
+    ui: &mut egui::Ui,
+    config: &mut CaptureConfig,
+    windows: &[CaptureWindow],
+) -> bool {
+    ui.label("Capture directory:");
+    let mut directory =
+        config.directory.display().to_string();
+    if ui.text_edit_singleline(&mut directory).changed() {
+        config.directory = directory.into();
+    }
+
+    ui.label("File name template:");
+    ui.text_edit_singleline(&mut config.template);
+
+    ui.label("Capture:");
+    egui::ComboBox::from_id_source("capture_window")
+        .selected_text(config.window.name())
+        .show_ui(ui, |ui| {
+            for option in windows {
+                ui.selectable_value(
+                    &mut config.window,
+                    *option,
+                    option.name(),
+                );
+            }
+        });
+
+    ui.button("capture").clicked()
+}
+
+pub enum PresetAction {
+    Save(String),
+    Load(String),
+}
+
+/// A name field with a save button, and a button to load
+/// each of `presets`.
+pub fn preset_settings(
+    ui: &mut egui::Ui,
+    name: &mut String,
+    presets: &[String],
+) -> Option<PresetAction> {
+    let mut action = None;
+
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(name);
+        if ui.button("save").clicked() && !name.is_empty() {
+            action = Some(PresetAction::Save(name.clone()));
+        }
+    });
+    for preset in presets {
+        if ui.button(preset).clicked() {
+            action =
+                Some(PresetAction::Load(preset.clone()));
+        }
+    }
+
+    action
+}
+
+/// Playback controls for `timeline` and an editor for its
+/// tracks.
+///
+/// `time` is the clock's position in seconds, and `current`
+/// gives a parameter's value for new keyframes. Returns
+/// whether the keyframes or the time changed, so the values
+/// need evaluating again.
+pub fn timeline_settings(
+    ui: &mut egui::Ui,
+    timeline: &mut Timeline,
+    time: &mut f64,
+    playing: &mut bool,
+    current: &dyn Fn(Parameter) -> f64,
+) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        let label = if *playing { "pause" } else { "play" };
+        if ui.button(label).clicked() {
+            *playing = !*playing;
+        }
+        if ui.button("rewind").clicked() {
+            *time = 0.;
+            changed = true;
+        }
+        ui.checkbox(&mut timeline.looping, "loop");
+    });
+    changed |= ui
+        .add(
+            egui::Slider::new(time, 0.0..=timeline.length)
+                .text("s"),
+        )
+        .changed();
+    ui.horizontal(|ui| {
+        ui.label("length:");
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut timeline.length)
+                    .speed(0.1)
+                    .clamp_range(0.1..=3600.0)
+                    .suffix(" s"),
+            )
+            .changed();
+    });
+
+    let mut removed = None;
+    for (index, track) in
+        timeline.tracks.iter_mut().enumerate()
+    {
+        ui.collapsing(track.parameter.name(), |ui| {
+            changed |= track_settings(ui, track, index);
+            ui.horizontal(|ui| {
+                if ui
+                    .small_button("key at current time")
+                    .clicked()
+                {
+                    track.insert(Keyframe {
+                        time: *time,
+                        value: current(track.parameter),
+                        easing: Easing::Linear,
+                    });
+                    changed = true;
+                }
+                if ui.small_button("remove track").clicked()
+                {
+                    removed = Some(index);
+                }
+            });
+        });
+    }
+    if let Some(index) = removed {
+        timeline.tracks.remove(index);
+        changed = true;
+    }
+
+    let mut added = None;
+    egui::ComboBox::from_id_source("add_track")
+        .selected_text("add track")
+        .show_ui(ui, |ui| {
+            for parameter in Parameter::ALL {
+                if timeline.track(parameter).is_none()
+                    && ui
+                        .selectable_label(
+                            false,
+                            parameter.name(),
+                        )
+                        .clicked()
+                {
+                    added = Some(parameter);
+                }
+            }
+        });
+    if let Some(parameter) = added {
+        timeline.tracks.push(Track {
+            parameter,
+            keyframes: vec![Keyframe {
+                time: *time,
+                value: current(parameter),
+                easing: Easing::Linear,
+            }],
+        });
+        changed = true;
+    }
+
+    changed
+}
+
+// a row per keyframe: its time, value and easing
+fn track_settings(
+    ui: &mut egui::Ui,
+    track: &mut Track,
+    index: usize,
+) -> bool {
+    let mut changed = false;
+    let mut removed = None;
+
+    for (key, keyframe) in
+        track.keyframes.iter_mut().enumerate()
+    {
+        ui.horizontal(|ui| {
+            changed |= ui
+                .add(
+                    egui::DragValue::new(
+                        &mut keyframe.time,
+                    )
+                    .speed(0.01)
+                    .clamp_range(0.0..=3600.0)
+                    .suffix(" s"),
+                )
+                .changed();
+            changed |= ui
+                .add(
+                    egui::DragValue::new(
+                        &mut keyframe.value,
+                    )
+                    .speed(0.1),
+                )
+                .changed();
+            egui::ComboBox::from_id_source((
+                "easing", index, key,
+            ))
+            .selected_text(keyframe.easing.name())
+            .show_ui(ui, |ui| {
+                for easing in Easing::ALL {
+                    changed |= ui
+                        .selectable_value(
+                            &mut keyframe.easing,
+                            easing,
+                            easing.name(),
+                        )
+                        .changed();
+                }
+            });
+            if ui.small_button("x").clicked() {
+                removed = Some(key);
+            }
+        });
+    }
+    if let Some(key) = removed {
+        track.keyframes.remove(key);
+        changed = true;
+    }
+    if changed {
+        track.sort();
+    }
+
+    changed
+}
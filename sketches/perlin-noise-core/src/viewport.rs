@@ -0,0 +1,52 @@
+//! The region of the infinite noise field that a 2D sketch
+//! shows, for panning and zooming around it.
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Serialize, Deserialize,
+)]
+pub struct Viewport {
+    /// Noise-space coordinate of the top left pixel.
+    pub offset: [f64; 2],
+    /// Noise-space distance between neighbouring pixels.
+    pub scale: f64,
+}
+
+impl Viewport {
+    pub fn new(scale: f64) -> Self {
+        Viewport {
+            offset: [0., 0.],
+            scale,
+        }
+    }
+
+    /// The noise-space point sampled for the pixel at `x`,
+    /// `y`, counted from the top left with y going down.
+    pub fn point(&self, x: f64, y: f64) -> [f64; 2] {
+        [
+            self.offset[0] + x * self.scale,
+            self.offset[1] + y * self.scale,
+        ]
+    }
+
+    /// Move the field along with a drag of `dx`, `dy`
+    /// pixels.
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.offset[0] -= dx * self.scale;
+        self.offset[1] -= dy * self.scale;
+    }
+
+    /// Zoom by `factor` while keeping the point under the
+    /// pixel at `x`, `y` in place. Factors above 1 zoom in.
+    pub fn zoom_about(
+        &mut self,
+        x: f64,
+        y: f64,
+        factor: f64,
+    ) {
+        let [px, py] = self.point(x, y);
+        self.scale /= factor;
+        self.offset =
+            [px - x * self.scale, py - y * self.scale];
+    }
+}
@@ -0,0 +1,109 @@
+//! Domain warping: displacing the coordinates a noise
+//! function is sampled at by other noise fields before
+//! sampling it.
+use nannou::noise::{Fbm, MultiFractal, NoiseFn, Seedable};
+use serde::{Deserialize, Serialize};
+
+// octaves of the displacement fields. They only need to be
+// smooth, more octaves mostly cost time.
+const WARP_OCTAVES: usize = 4;
+
+/// Settings of the domain warp stage. With zero iterations
+/// the noise is sampled unwarped.
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+pub struct WarpConfig {
+    /// Seed of the first displacement field, the others use
+    /// the following seeds.
+    pub seed: u32,
+    /// How far, in noise space, a point can be displaced.
+    pub strength: f64,
+    /// Frequency of the displacement fields in noise space.
+    /// Points are multiplied by it before sampling the
+    /// fields, whatever the frequency of the noise being
+    /// warped.
+    pub scale: f64,
+    /// How many times the displacement is applied, each
+    /// time to the already displaced point.
+    pub iterations: usize,
+}
+
+impl Default for WarpConfig {
+    fn default() -> Self {
+        WarpConfig {
+            seed: 1,
+            strength: 0.5,
+            scale: 1.,
+            iterations: 0,
+        }
+    }
+}
+
+impl WarpConfig {
+    pub fn build(&self) -> DomainWarp {
+        let field = |axis: u32| {
+            Fbm::new()
+                .set_seed(self.seed.wrapping_add(axis))
+                .set_octaves(WARP_OCTAVES)
+        };
+        DomainWarp {
+            fields: [field(0), field(1), field(2)],
+            strength: self.strength,
+            scale: self.scale,
+            iterations: self.iterations,
+        }
+    }
+}
+
+/// A domain warp built from a `WarpConfig`, with one
+/// displacement field per axis.
+#[derive(Clone, Debug)]
+pub struct DomainWarp {
+    fields: [Fbm; 3],
+    strength: f64,
+    scale: f64,
+    iterations: usize,
+}
+
+impl DomainWarp {
+    pub fn warp2(&self, mut point: [f64; 2]) -> [f64; 2] {
+        for _ in 0..self.iterations {
+            let sample = [
+                point[0] * self.scale,
+                point[1] * self.scale,
+            ];
+            point = [
+                point[0]
+                    + self.strength
+                        * self.fields[0].get(sample),
+                point[1]
+                    + self.strength
+                        * self.fields[1].get(sample),
+            ];
+        }
+        point
+    }
+
+    pub fn warp3(&self, mut point: [f64; 3]) -> [f64; 3] {
+        for _ in 0..self.iterations {
+            let sample = [
+                point[0] * self.scale,
+                point[1] * self.scale,
+                point[2] * self.scale,
+            ];
+            point = [
+                point[0]
+                    + self.strength
+                        * self.fields[0].get(sample),
+                point[1]
+                    + self.strength
+                        * self.fields[1].get(sample),
+                point[2]
+                    + self.strength
+                        * self.fields[2].get(sample),
+            ];
+        }
+        point
+    }
+}
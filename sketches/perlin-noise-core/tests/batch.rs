@@ -0,0 +1,96 @@
+use nannou::noise::NoiseFn;
+use perlin_noise_core::{
+    batch::BatchNoise,
+    noise::{NoiseConfig, NoiseType},
+};
+
+const EPSILON: f64 = 1e-12;
+
+// the noise types with a batched version
+const BATCHED: [NoiseType; 7] = [
+    NoiseType::Perlin,
+    NoiseType::OpenSimplex,
+    NoiseType::Fbm,
+    NoiseType::Billow,
+    NoiseType::BasicMulti,
+    NoiseType::HybridMulti,
+    NoiseType::RidgedMulti,
+];
+
+#[test]
+fn batched_types_are_the_expected_ones() {
+    for noise_type in NoiseType::ALL {
+        let config = NoiseConfig {
+            noise_type,
+            ..NoiseConfig::default()
+        };
+        assert_eq!(
+            BatchNoise::new(&config).is_some(),
+            BATCHED.contains(&noise_type),
+            "{}",
+            noise_type.name()
+        );
+    }
+}
+
+#[test]
+fn batches_match_scalar_sampling_across_seeds() {
+    // `noise` adds the octave to the seed without wrapping,
+    // so the highest seeds stay an octave count below the
+    // limit
+    let seeds = (0..64).chain([
+        1234,
+        123_456_789,
+        0x8000_0000,
+        u32::MAX - 64,
+    ]);
+    for seed in seeds {
+        for noise_type in BATCHED {
+            let config = NoiseConfig {
+                noise_type,
+                seed,
+                ..NoiseConfig::default()
+            };
+            let batch = BatchNoise::new(&config).unwrap();
+            let noise = config.build();
+
+            // long enough to cross several cells, and not a
+            // multiple of the lanes
+            let (start, step) = ([-5.3, 2.7, -1.1], 0.173);
+            let mut row_2d = [0.; 41];
+            let mut row_3d = [0.; 41];
+            batch.row_2d(
+                [start[0], start[1]],
+                step,
+                &mut row_2d,
+            );
+            batch.row_3d(start, step, &mut row_3d);
+            for (i, (value_2d, value_3d)) in
+                row_2d.iter().zip(&row_3d).enumerate()
+            {
+                let x = start[0] + i as f64 * step;
+                let expected_2d = noise.get([x, start[1]]);
+                let expected_3d =
+                    noise.get([x, start[1], start[2]]);
+                assert!(
+                    (value_2d - expected_2d).abs() <= EPSILON,
+                    "{} seed {} at {}: {} in 2D, expected {}",
+                    noise_type.name(),
+                    seed,
+                    i,
+                    value_2d,
+                    expected_2d
+                );
+                assert!(
+                    (value_3d - expected_3d).abs() <= EPSILON,
+                    "{} seed {} at {}: {} in 3D, expected {}",
+                    noise_type.name(),
+                    seed,
+                    i,
+                    value_3d,
+                    expected_3d
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use perlin_noise_core::capture::CaptureConfig;
+use std::fs;
+
+#[test]
+fn captures_never_overwrite_earlier_ones() {
+    let directory = std::env::temp_dir()
+        .join("perlin-noise-core-captures");
+    let _ = fs::remove_dir_all(&directory);
+    for template in ["{sketch}-{seed}", "{sketch}-{n}"] {
+        let mut config = CaptureConfig::default();
+        config.directory = directory.clone();
+        config.template = template.to_string();
+        for _ in 0..3 {
+            let path = config
+                .next_path("sketch", Some(1), "png")
+                .unwrap();
+            assert!(!path.exists(), "{}", path.display());
+            fs::write(&path, template).unwrap();
+        }
+    }
+    let count = fs::read_dir(&directory).unwrap().count();
+    fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(count, 6);
+}
@@ -0,0 +1,126 @@
+use nannou::noise::NoiseFn;
+use perlin_noise_core::{
+    contour::{
+        contours, isolines, write_svg, ContourSettings,
+    },
+    noise::NoiseConfig,
+};
+use std::fs;
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 48;
+
+fn field(value: impl Fn(f64, f64) -> f64) -> Vec<f64> {
+    (0..WIDTH * HEIGHT)
+        .map(|index| {
+            value(
+                (index % WIDTH) as f64,
+                (index / WIDTH) as f64,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn a_peak_has_one_closed_isoline_around_it() {
+    let radius = 15.;
+    let peak =
+        field(|x, y| radius - (x - 30.).hypot(y - 20.));
+    let lines = isolines(&peak, WIDTH, 0.);
+    assert_eq!(lines.len(), 1);
+    let line = &lines[0];
+    assert!(line.len() > 50);
+    assert_eq!(line.first(), line.last());
+    for [x, y] in line {
+        // the field is interpolated linearly between samples
+        assert!(
+            ((x - 30.).hypot(y - 20.) - radius).abs() < 0.1
+        );
+    }
+}
+
+#[test]
+fn a_slope_has_one_open_isoline_across_it() {
+    let slope = field(|x, y| x + 0.5 * y);
+    let lines = isolines(&slope, WIDTH, 40.5);
+    assert_eq!(lines.len(), 1);
+    let line = &lines[0];
+    assert_ne!(line.first(), line.last());
+    for [x, y] in line {
+        assert!((x + 0.5 * y - 40.5).abs() < 1e-9);
+    }
+    // from one edge of the field to another
+    let on_edge = |[x, y]: [f64; 2]| {
+        x == 0.
+            || y == 0.
+            || x == (WIDTH - 1) as f64
+            || y == (HEIGHT - 1) as f64
+    };
+    assert!(on_edge(line[0]));
+    assert!(on_edge(*line.last().unwrap()));
+}
+
+#[test]
+fn isolines_of_noise_cross_their_level_between_samples() {
+    let noise = NoiseConfig::default().build();
+    let samples =
+        field(|x, y| noise.get([x / 16., y / 16.]));
+    let levels = ContourSettings::default().values();
+    assert_eq!(levels.len(), 9);
+    for contour in contours(&samples, WIDTH, &levels) {
+        for line in &contour.lines {
+            assert!(line.len() > 1);
+            // each point is on a line between two samples
+            for [x, y] in line {
+                assert!(x.fract() == 0. || y.fract() == 0.);
+            }
+            // closed lines end where they start, and open
+            // ones at the edges of the field
+            let (first, last) =
+                (line[0], line[line.len() - 1]);
+            if first != last {
+                for [x, y] in [first, last] {
+                    assert!(
+                        x == 0.
+                            || y == 0.
+                            || x == (WIDTH - 1) as f64
+                            || y == (HEIGHT - 1) as f64,
+                        "line ends inside the field at {}, {}",
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn svg_has_a_path_per_line() {
+    let peaks =
+        field(|x, y| (x / 8.).sin() * (y / 8.).sin());
+    let settings = ContourSettings::default();
+    let contours =
+        contours(&peaks, WIDTH, &settings.values());
+    let lines: usize = contours
+        .iter()
+        .map(|contour| contour.lines.len())
+        .sum();
+    assert!(lines > 0);
+
+    let path = std::env::temp_dir()
+        .join("perlin-noise-core-contours.svg");
+    write_svg(
+        &path,
+        WIDTH as u32,
+        HEIGHT as u32,
+        &settings,
+        &contours,
+    )
+    .unwrap();
+    let svg = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<path d=\"M").count(), lines);
+}
@@ -0,0 +1,145 @@
+use nannou::noise::NoiseFn;
+use perlin_noise_core::{
+    curl::{
+        arrows, curl_2d, curl_3d, gradient_2d, lic,
+        sampled_curl,
+    },
+    noise::{NoiseConfig, NoiseType},
+};
+
+// the spacing the curl's own differences are taken over,
+// where the divergence of a curl cancels exactly
+const H: f64 = 1e-4;
+const POINTS: [[f64; 3]; 3] =
+    [[0.1, 0.2, 0.3], [3.7, -1.2, 0.5], [-8., 2.5, 9.]];
+
+fn perlin() -> NoiseConfig {
+    NoiseConfig {
+        noise_type: NoiseType::Perlin,
+        ..NoiseConfig::default()
+    }
+}
+
+#[test]
+fn curl_2d_is_the_gradient_turned_a_quarter() {
+    let noise = NoiseConfig::default().build();
+    for [x, y, _] in POINTS {
+        let [gx, gy] = gradient_2d(&noise, [x, y]);
+        let [cx, cy] = curl_2d(&noise, [x, y]);
+        assert_eq!([cx, cy], [gy, -gx]);
+    }
+}
+
+#[test]
+fn curl_2d_has_no_divergence() {
+    let noise = NoiseConfig::default().build();
+    for [x, y, _] in POINTS {
+        let curl = |x, y| curl_2d(&noise, [x, y]);
+        let divergence = (curl(x + H, y)[0]
+            - curl(x - H, y)[0]
+            + curl(x, y + H)[1]
+            - curl(x, y - H)[1])
+            / (2. * H);
+        let scale = curl(x + H, y)[0].abs().max(1.) / H;
+        assert!(
+            divergence.abs() < 1e-9 * scale,
+            "divergence {} at {}, {}",
+            divergence,
+            x,
+            y
+        );
+    }
+}
+
+#[test]
+fn curl_3d_has_no_divergence() {
+    let noise = NoiseConfig::default().build();
+    for point in POINTS {
+        let mut divergence = 0.;
+        for axis in 0..3 {
+            let (mut ahead, mut behind) = (point, point);
+            ahead[axis] += H;
+            behind[axis] -= H;
+            divergence += (curl_3d(&noise, ahead)[axis]
+                - curl_3d(&noise, behind)[axis])
+                / (2. * H);
+        }
+        assert!(
+            divergence.abs() < 1e-3,
+            "divergence {} at {:?}",
+            divergence,
+            point
+        );
+    }
+}
+
+#[test]
+fn curl_3d_isnt_zero() {
+    let noise = perlin().build();
+    let curl = curl_3d(&noise, POINTS[0]);
+    assert!(curl
+        .iter()
+        .any(|component| component.abs() > 0.01));
+}
+
+#[test]
+fn sampled_curl_matches_curl_2d() {
+    let noise = perlin().build();
+    let (width, height, spacing) = (40, 30, 1e-3);
+    let samples: Vec<f64> = (0..width * height)
+        .map(|index| {
+            noise.get([
+                (index % width) as f64 * spacing + 0.3,
+                (index / width) as f64 * spacing + 0.2,
+            ])
+        })
+        .collect();
+    let field = sampled_curl(&samples, width, spacing);
+    for (index, [x, y]) in field.iter().enumerate() {
+        let point = [
+            (index % width) as f64 * spacing + 0.3,
+            (index / width) as f64 * spacing + 0.2,
+        ];
+        let [cx, cy] = curl_2d(&noise, point);
+        // one-sided at the edges
+        assert!((x - cx).abs() < 0.05, "{} {}", x, cx);
+        assert!((y - cy).abs() < 0.05, "{} {}", y, cy);
+    }
+}
+
+#[test]
+fn the_longest_arrow_is_as_long_as_their_spacing() {
+    let width = 100;
+    let field: Vec<[f64; 2]> = (0..width * 80)
+        .map(|index| [(index % width) as f64, 1.])
+        .collect();
+    let arrows = arrows(&field, width, 20);
+    assert_eq!(arrows.len(), 5 * 4);
+    let longest = arrows
+        .iter()
+        .map(|(_, [x, y])| x.hypot(*y))
+        .fold(0., f64::max);
+    assert!((longest - 20.).abs() < 1e-9);
+    assert_eq!(arrows[0].0, [10., 10.]);
+}
+
+#[test]
+fn lic_is_deterministic_and_in_range() {
+    let noise = NoiseConfig::default().build();
+    let width = 64;
+    let samples: Vec<f64> = (0..width * 48)
+        .map(|index| {
+            noise.get([
+                (index % width) as f64 / 32.,
+                (index / width) as f64 / 32.,
+            ])
+        })
+        .collect();
+    let field = sampled_curl(&samples, width, 1. / 32.);
+    let values = lic(&field, width, 10, 3);
+    assert!(values
+        .iter()
+        .all(|value| (0. ..=1.).contains(value)));
+    assert_eq!(values, lic(&field, width, 10, 3));
+    assert_ne!(values, lic(&field, width, 10, 4));
+}
@@ -0,0 +1,123 @@
+use nannou::noise::NoiseFn;
+use perlin_noise_core::{
+    curl::gradient_2d,
+    noise::{NoiseConfig, NoiseType},
+};
+use proptest::prelude::*;
+
+const H: f64 = 1e-5;
+
+fn config(noise_type: NoiseType, seed: u32) -> NoiseConfig {
+    NoiseConfig {
+        noise_type,
+        seed,
+        ..NoiseConfig::default()
+    }
+}
+
+const ANALYTIC: [NoiseType; 3] = [
+    NoiseType::Perlin,
+    NoiseType::OpenSimplex,
+    NoiseType::Fbm,
+];
+
+// the gradient by central differences, over a smaller
+// step than the fallback's
+fn differences<const D: usize>(
+    noise: &dyn NoiseFn<[f64; D]>,
+    point: [f64; D],
+) -> [f64; D] {
+    std::array::from_fn(|axis| {
+        let (mut ahead, mut behind) = (point, point);
+        ahead[axis] += H;
+        behind[axis] -= H;
+        (noise.get(ahead) - noise.get(behind)) / (2. * H)
+    })
+}
+
+proptest! {
+    #[test]
+    fn gradients_2d_match_finite_differences(
+        type_index in 0..ANALYTIC.len(),
+        seed in any::<u32>(),
+        x in -50f64..50.,
+        y in -50f64..50.,
+    ) {
+        let noise = config(ANALYTIC[type_index], seed).build();
+        prop_assert!(noise.has_analytic_gradient());
+        let (value, gradient) =
+            noise.get_with_gradient_2d([x, y]);
+        prop_assert!((value - noise.get([x, y])).abs() < 1e-12);
+        let expected = differences(&noise, [x, y]);
+        for (slope, expected) in gradient.iter().zip(expected) {
+            prop_assert!(
+                (slope - expected).abs() < 1e-4,
+                "{:?} against {:?}",
+                gradient,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn gradients_3d_match_finite_differences(
+        type_index in 0..ANALYTIC.len(),
+        seed in any::<u32>(),
+        x in -50f64..50.,
+        y in -50f64..50.,
+        z in -50f64..50.,
+    ) {
+        let noise = config(ANALYTIC[type_index], seed).build();
+        let (value, gradient) =
+            noise.get_with_gradient_3d([x, y, z]);
+        prop_assert!(
+            (value - noise.get([x, y, z])).abs() < 1e-12
+        );
+        let expected = differences(&noise, [x, y, z]);
+        for (slope, expected) in gradient.iter().zip(expected) {
+            prop_assert!(
+                (slope - expected).abs() < 1e-4,
+                "{:?} against {:?}",
+                gradient,
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn other_noise_falls_back_to_finite_differences() {
+    let mut warped = config(NoiseType::Perlin, 0);
+    warped.warp.iterations = 1;
+    for config in [
+        config(NoiseType::BasicMulti, 0),
+        config(NoiseType::SuperSimplex, 0),
+        warped,
+    ] {
+        let noise = config.build();
+        assert!(!noise.has_analytic_gradient());
+        let point = [0.3, 1.7];
+        let (value, gradient) =
+            noise.get_with_gradient_2d(point);
+        assert_eq!(value, noise.get(point));
+        assert_eq!(gradient, gradient_2d(&noise, point));
+    }
+}
+
+#[test]
+fn fbm_gradients_scale_with_frequency() {
+    let slow = config(NoiseType::Fbm, 3);
+    let fast = NoiseConfig {
+        frequency: slow.frequency * 2.,
+        ..slow.clone()
+    };
+    let (slow, fast) = (slow.build(), fast.build());
+    let (value, gradient) =
+        slow.get_with_gradient_2d([1.4, -0.6]);
+    let (fast_value, fast_gradient) =
+        fast.get_with_gradient_2d([0.7, -0.3]);
+    assert!((value - fast_value).abs() < 1e-12);
+    for (slow, fast) in gradient.iter().zip(fast_gradient) {
+        assert!((slow * 2. - fast).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,61 @@
+use perlin_noise_core::expr::parse;
+
+// the message and the text the error points at
+fn error(source: &str) -> (String, &str) {
+    let error = parse(source).unwrap_err();
+    (error.message, &source[error.span])
+}
+
+#[test]
+fn repeated_control_points_are_rejected() {
+    let (message, at) = error("terrace(perlin, 0.5, 0.5)");
+    assert!(message.contains("terrace"), "{}", message);
+    assert_eq!(at, "terrace(perlin, 0.5, 0.5)");
+
+    let source = "1 + curve(perlin, 0,0, 0,1, 0,2, 0,3)";
+    let (message, at) = error(source);
+    assert!(message.contains("curve"), "{}", message);
+    assert_eq!(at, "curve(perlin, 0,0, 0,1, 0,2, 0,3)");
+
+    assert!(parse("terrace(perlin, 0.5, -0.5)").is_ok());
+    assert!(
+        parse("curve(perlin, 0,0, 1,1, 2,2, 3,3)").is_ok()
+    );
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    let minuses = format!("{}perlin", "-".repeat(20000));
+    let calls = format!(
+        "{}perlin{}",
+        "abs(".repeat(2000),
+        ")".repeat(2000)
+    );
+    let parentheses = format!(
+        "{}perlin{}",
+        "(".repeat(2000),
+        ")".repeat(2000)
+    );
+    let sum = vec!["perlin"; 20000].join(" + ");
+    for source in [minuses, calls, parentheses, sum] {
+        let (message, _) = error(&source);
+        assert_eq!(message, "expression nested too deeply");
+    }
+}
+
+#[test]
+fn nesting_below_the_limit_builds() {
+    let calls = format!(
+        "{}perlin{}",
+        "abs(".repeat(120),
+        ")".repeat(120)
+    );
+    let sum = vec!["perlin"; 120].join(" - ");
+    for source in [calls, sum] {
+        let graph = parse(&source).unwrap();
+        let value = graph.with_noise(&mut |noise| {
+            noise.get([0.3, 0.6, 0.9])
+        });
+        assert!(value.is_finite());
+    }
+}
@@ -0,0 +1,123 @@
+use perlin_noise_core::{
+    flow::{
+        velocity, FlowConfig, FlowMode, Particles, Trails,
+    },
+    noise::NoiseConfig,
+};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+const NOISE_STEP: f64 = 100.;
+
+fn config(mode: FlowMode) -> FlowConfig {
+    FlowConfig {
+        mode,
+        particles: 500,
+        ..FlowConfig::default()
+    }
+}
+
+// the trails after `frames` frames of flow from `seed`
+fn run(
+    seed: u32,
+    config: &FlowConfig,
+    frames: usize,
+) -> Vec<f32> {
+    let noise = NoiseConfig {
+        seed,
+        ..NoiseConfig::default()
+    }
+    .build();
+    let mut particles =
+        Particles::new(seed, config, WIDTH, HEIGHT);
+    let mut trails = Trails::new(WIDTH, HEIGHT);
+    for frame in 0..frames {
+        trails.fade(config.fade);
+        particles.step(
+            &noise,
+            config,
+            NOISE_STEP,
+            frame as f64 * 0.01,
+            &mut trails,
+        );
+        for [x, y] in particles.positions() {
+            assert!(
+                (0. ..WIDTH as f64).contains(&x)
+                    && (0. ..HEIGHT as f64).contains(&y),
+                "particle outside the area at {}, {}",
+                x,
+                y
+            );
+        }
+    }
+    assert_eq!(particles.len(), config.particles);
+    trails.ink().to_vec()
+}
+
+#[test]
+fn angle_flow_moves_at_one_speed() {
+    let noise = NoiseConfig::default().build();
+    let config = config(FlowMode::Angle);
+    for point in
+        [[0.1, 0.2, 0.], [3.7, -1.2, 0.5], [-8., 2.5, 9.]]
+    {
+        let [dx, dy] = velocity(&noise, &config, point);
+        assert!((dx.hypot(dy) - 1.).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn curl_flow_has_no_divergence() {
+    let noise = NoiseConfig::default().build();
+    let config = config(FlowMode::Curl);
+    // over the spacing the flow's own differences are
+    // taken over, where the curl's cancel exactly
+    let h = 1e-4;
+    for point in
+        [[0.1, 0.2, 0.], [3.7, -1.2, 0.5], [-8., 2.5, 9.]]
+    {
+        let [x, y, z] = point;
+        let flow =
+            |x, y| velocity(&noise, &config, [x, y, z]);
+        let divergence = (flow(x + h, y)[0]
+            - flow(x - h, y)[0]
+            + flow(x, y + h)[1]
+            - flow(x, y - h)[1])
+            / (2. * h);
+        // against how fast the flow changes
+        let change =
+            (flow(x + h, y)[0] - flow(x - h, y)[0]).abs()
+                / (2. * h);
+        assert!(
+            divergence.abs() < 1e-6 * change.max(1.),
+            "divergence {} at {:?}",
+            divergence,
+            point
+        );
+    }
+}
+
+#[test]
+fn flow_is_deterministic_for_a_seed() {
+    for mode in FlowMode::ALL {
+        let config = config(mode);
+        let trails = run(7, &config, 50);
+        assert!(trails.iter().any(|&ink| ink > 0.));
+        assert_eq!(trails, run(7, &config, 50));
+        assert_ne!(trails, run(8, &config, 50));
+    }
+}
+
+#[test]
+fn trails_fade() {
+    let mut trails = Trails::new(WIDTH, HEIGHT);
+    trails.line([10.5, 10.5], [20.5, 10.5], 0.8);
+    let inked: Vec<usize> = (0..trails.ink().len())
+        .filter(|&index| trails.ink()[index] > 0.)
+        .collect();
+    assert_eq!(inked.len(), 10);
+    trails.fade(0.25);
+    for index in inked {
+        assert!((trails.ink()[index] - 0.6).abs() < 1e-6);
+    }
+}
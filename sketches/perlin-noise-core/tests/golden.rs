@@ -0,0 +1,295 @@
+//! Renders small fixed-seed images and compares them with the
+//! reference PNGs in `tests/golden/`.
+//!
+//! A pixel matches when none of its channels is more than
+//! `CHANNEL_TOLERANCE` off, which leaves room for floating
+//! point differences between platforms but not for a change
+//! in sampling or colour mapping. For each image that
+//! doesn't match, the render and a diff (mismatched pixels
+//! in red over the dimmed reference) are written to
+//! `target/tmp/golden/`.
+//!
+//! After an intended change, regenerate the references with
+//!
+//! ```text
+//! UPDATE_GOLDEN=1 cargo test -p perlin-noise-core --test golden
+//! ```
+use nannou::image::{self, Rgb, RgbImage};
+use perlin_noise_core::{
+    color::{ColorMap, ColorSettings},
+    noise::{NoiseConfig, NoiseType},
+    render::{render_2d, render_3d},
+    viewport::Viewport,
+};
+use std::{env, fs, path::PathBuf};
+
+const CHANNEL_TOLERANCE: u8 = 2;
+const SIZE: u32 = 64;
+// noise-space distance between pixels, so an image spans
+// four units
+const SCALE: f64 = 1. / 16.;
+const SEED: u32 = 42;
+// the 3D renders' slice
+const Z: f64 = 0.5;
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+fn output_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("golden")
+}
+
+fn noise_config(noise_type: NoiseType) -> NoiseConfig {
+    NoiseConfig {
+        noise_type,
+        seed: SEED,
+        ..NoiseConfig::default()
+    }
+}
+
+fn type_name(noise_type: NoiseType) -> String {
+    format!("{:?}", noise_type).to_lowercase()
+}
+
+fn render_2d_image(
+    config: &NoiseConfig,
+    colors: &ColorSettings,
+) -> RgbImage {
+    let mut image = RgbImage::new(SIZE, SIZE);
+    let mut samples = vec![0.; (SIZE * SIZE) as usize];
+    render_2d(
+        &config.build(),
+        &Viewport::new(SCALE),
+        &ColorMap::new(colors),
+        &mut image,
+        &mut samples,
+    );
+    image
+}
+
+fn render_3d_image(
+    config: &NoiseConfig,
+    colors: &ColorSettings,
+) -> RgbImage {
+    let mut image = RgbImage::new(SIZE, SIZE);
+    let mut samples = vec![0.; (SIZE * SIZE) as usize];
+    render_3d(
+        &config.build(),
+        None,
+        1. / SCALE,
+        Z,
+        &ColorMap::new(colors),
+        &mut image,
+        &mut samples,
+    );
+    image
+}
+
+/// Compare `image` with the reference called `name`,
+/// returning why it doesn't match. With `UPDATE_GOLDEN` set
+/// the reference is replaced instead.
+fn check(
+    name: &str,
+    image: &RgbImage,
+) -> Result<(), String> {
+    let path = golden_dir().join(format!("{}.png", name));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(golden_dir())
+            .map_err(|error| error.to_string())
+            .and_then(|_| {
+                image
+                    .save(&path)
+                    .map_err(|error| error.to_string())
+            })
+            .map_err(|error| {
+                format!(
+                    "{}: failed to save: {}",
+                    name, error
+                )
+            })?;
+        return Ok(());
+    }
+
+    let reference = match image::open(&path) {
+        Ok(reference) => reference.to_rgb8(),
+        Err(error) => {
+            return Err(format!(
+                "{}: no reference ({}), run with \
+                 UPDATE_GOLDEN=1 to create it",
+                name, error
+            ))
+        }
+    };
+    if reference.dimensions() != image.dimensions() {
+        return Err(format!(
+            "{}: rendered {:?}, reference is {:?}",
+            name,
+            image.dimensions(),
+            reference.dimensions()
+        ));
+    }
+
+    let mut mismatched = 0;
+    let mut worst = 0;
+    let diff = RgbImage::from_fn(SIZE, SIZE, |x, y| {
+        let expected = reference.get_pixel(x, y);
+        let actual = image.get_pixel(x, y);
+        let difference = expected
+            .0
+            .iter()
+            .zip(actual.0)
+            .map(|(&e, a)| e.abs_diff(a))
+            .max()
+            .unwrap();
+        worst = worst.max(difference);
+        if difference > CHANNEL_TOLERANCE {
+            mismatched += 1;
+            Rgb([255, 0, 0])
+        } else {
+            Rgb(expected.0.map(|channel| channel / 4))
+        }
+    });
+    if mismatched == 0 {
+        return Ok(());
+    }
+
+    let output = output_dir();
+    let actual_path = output.join(format!("{}.png", name));
+    let diff_path =
+        output.join(format!("{}-diff.png", name));
+    let saved = fs::create_dir_all(&output)
+        .map_err(|error| error.to_string())
+        .and_then(|_| {
+            image
+                .save(&actual_path)
+                .and_then(|_| diff.save(&diff_path))
+                .map_err(|error| error.to_string())
+        });
+    let written = match saved {
+        Ok(()) => format!(
+            "render at {}, diff at {}",
+            actual_path.display(),
+            diff_path.display()
+        ),
+        Err(error) => {
+            format!("failed to save the diff: {}", error)
+        }
+    };
+    Err(format!(
+        "{}: {} pixels differ by up to {}, {}",
+        name, mismatched, worst, written
+    ))
+}
+
+// check every case before failing, so one run reports all
+// the images a change broke
+fn check_all(cases: Vec<(String, RgbImage)>) {
+    let failures: Vec<String> = cases
+        .iter()
+        .filter_map(|(name, image)| {
+            check(name, image).err()
+        })
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} of {} images don't match their reference:\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn noise_types_2d() {
+    let colors = ColorSettings::default();
+    check_all(
+        NoiseType::ALL
+            .iter()
+            .map(|&noise_type| {
+                (
+                    format!("2d-{}", type_name(noise_type)),
+                    render_2d_image(
+                        &noise_config(noise_type),
+                        &colors,
+                    ),
+                )
+            })
+            .collect(),
+    );
+}
+
+#[test]
+fn grayscale_2d() {
+    let colors = ColorSettings {
+        grayscale: true,
+        ..ColorSettings::default()
+    };
+    check_all(
+        NoiseType::ALL
+            .iter()
+            .map(|&noise_type| {
+                (
+                    format!(
+                        "2d-{}-grayscale",
+                        type_name(noise_type)
+                    ),
+                    render_2d_image(
+                        &noise_config(noise_type),
+                        &colors,
+                    ),
+                )
+            })
+            .collect(),
+    );
+}
+
+#[test]
+fn hue_settings_2d() {
+    let colors = ColorSettings {
+        lightness: 70.,
+        chroma: 40.,
+        hue_center: 120.,
+        hue_range: 60.,
+        ..ColorSettings::default()
+    };
+    check_all(vec![(
+        "2d-fbm-hue".to_string(),
+        render_2d_image(
+            &noise_config(NoiseType::Fbm),
+            &colors,
+        ),
+    )]);
+}
+
+#[test]
+fn domain_warp_2d() {
+    let mut config = noise_config(NoiseType::Fbm);
+    config.warp.iterations = 2;
+    check_all(vec![(
+        "2d-fbm-warped".to_string(),
+        render_2d_image(&config, &ColorSettings::default()),
+    )]);
+}
+
+#[test]
+fn noise_types_3d() {
+    let colors = ColorSettings::default();
+    check_all(
+        NoiseType::ALL
+            .iter()
+            .map(|&noise_type| {
+                (
+                    format!("3d-{}", type_name(noise_type)),
+                    render_3d_image(
+                        &noise_config(noise_type),
+                        &colors,
+                    ),
+                )
+            })
+            .collect(),
+    );
+}
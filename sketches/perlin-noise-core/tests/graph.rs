@@ -0,0 +1,61 @@
+use perlin_noise_core::graph::{NodeKind, NoiseGraph};
+
+// the default generator feeding a node of `kind`
+fn modified(kind: NodeKind) -> NoiseGraph {
+    let mut graph = NoiseGraph::default();
+    let node = graph.add_node(kind, [200., 20.]);
+    graph.nodes[node].inputs[0] = Some(0);
+    graph.output = Some(node);
+    graph
+}
+
+fn source_value(point: [f64; 3]) -> f64 {
+    NoiseGraph::default()
+        .with_noise(&mut |noise| noise.get(point))
+}
+
+#[test]
+fn duplicate_terrace_points_leave_the_source() {
+    let graph = modified(NodeKind::Terrace {
+        control_points: vec![0.5, 0.5, 0.5],
+        invert: false,
+    });
+    let point = [0.3, 1.2, -0.4];
+    let value =
+        graph.with_noise(&mut |noise| noise.get(point));
+    assert_eq!(value, source_value(point));
+}
+
+#[test]
+fn duplicate_curve_inputs_leave_the_source() {
+    let graph = modified(NodeKind::Curve {
+        control_points: vec![
+            [0., 0.],
+            [0., 1.],
+            [0., 2.],
+            [0., 3.],
+        ],
+    });
+    let point = [0.3, 1.2, -0.4];
+    let value =
+        graph.with_noise(&mut |noise| noise.get(point));
+    assert_eq!(value, source_value(point));
+}
+
+#[test]
+fn distinct_points_still_shape_the_source() {
+    let terrace = modified(NodeKind::Terrace {
+        control_points: vec![-1., 1.],
+        invert: false,
+    });
+    let curve = modified(NodeKind::Curve {
+        control_points: vec![[-1., 0.5], [1., 0.5]],
+    });
+    let point = [0.3, 1.2, -0.4];
+    let source = source_value(point);
+    for graph in [terrace, curve] {
+        let value =
+            graph.with_noise(&mut |noise| noise.get(point));
+        assert_ne!(value, source);
+    }
+}
@@ -0,0 +1,61 @@
+use nannou::image::RgbImage;
+use perlin_noise_core::{
+    metadata::{load_state_from_png, save_png_with_state},
+    noise::NoiseConfig,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct State {
+    sketch: String,
+    noise: NoiseConfig,
+    name: String,
+}
+
+fn state(name: &str) -> State {
+    State {
+        sketch: "perlin-noise-2d".to_string(),
+        noise: NoiseConfig {
+            seed: 1234,
+            ..NoiseConfig::default()
+        },
+        name: name.to_string(),
+    }
+}
+
+fn save(file: &str, state: &State) -> PathBuf {
+    let path = std::env::temp_dir().join(file);
+    save_png_with_state(&path, &RgbImage::new(4, 3), state)
+        .unwrap();
+    path
+}
+
+#[test]
+fn state_round_trips() {
+    // Latin-1 fits a tEXt chunk, the rest needs iTXt
+    for name in ["café", "горы", "山"] {
+        let saved = state(name);
+        let path = save(
+            "perlin-noise-core-round-trip.png",
+            &saved,
+        );
+        let loaded: Result<State, _> =
+            load_state_from_png(&path, "perlin-noise-2d");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), saved);
+    }
+}
+
+#[test]
+fn other_sketches_states_are_rejected() {
+    let path = save(
+        "perlin-noise-core-other-sketch.png",
+        &state("hills"),
+    );
+    let loaded: Result<State, _> =
+        load_state_from_png(&path, "perlin-noise-3d");
+    fs::remove_file(&path).unwrap();
+    let error = loaded.err().unwrap().to_string();
+    assert!(error.contains("perlin-noise-2d"), "{}", error);
+}
@@ -0,0 +1,221 @@
+use perlin_noise_core::{
+    color::ColorSettings,
+    noise::NoiseConfig,
+    osc::{parse_packet, Control, OscArg, OscListener},
+};
+use std::{
+    net::UdpSocket,
+    thread,
+    time::{Duration, Instant},
+};
+
+// an OSC string: null-terminated and padded to 4 bytes
+fn osc_string(bytes: &mut Vec<u8>, string: &str) {
+    bytes.extend_from_slice(string.as_bytes());
+    bytes.push(0);
+    while !bytes.len().is_multiple_of(4) {
+        bytes.push(0);
+    }
+}
+
+fn message(address: &str, args: &[OscArg]) -> Vec<u8> {
+    let mut bytes = vec![];
+    osc_string(&mut bytes, address);
+    let tags: String = args
+        .iter()
+        .map(|arg| match arg {
+            OscArg::Int(_) => 'i',
+            OscArg::Long(_) => 'h',
+            OscArg::Float(_) => 'f',
+            OscArg::Double(_) => 'd',
+            OscArg::String(_) => 's',
+            OscArg::Blob(_) => 'b',
+            OscArg::Bool(true) => 'T',
+            OscArg::Bool(false) => 'F',
+            OscArg::Nil => 'N',
+            OscArg::Impulse => 'I',
+        })
+        .collect();
+    osc_string(&mut bytes, &format!(",{}", tags));
+    for arg in args {
+        match arg {
+            OscArg::Int(value) => bytes
+                .extend_from_slice(&value.to_be_bytes()),
+            OscArg::Long(value) => bytes
+                .extend_from_slice(&value.to_be_bytes()),
+            OscArg::Float(value) => bytes
+                .extend_from_slice(&value.to_be_bytes()),
+            OscArg::Double(value) => bytes
+                .extend_from_slice(&value.to_be_bytes()),
+            OscArg::String(value) => {
+                osc_string(&mut bytes, value)
+            }
+            OscArg::Blob(blob) => {
+                bytes.extend_from_slice(
+                    &(blob.len() as i32).to_be_bytes(),
+                );
+                bytes.extend_from_slice(blob);
+                while !bytes.len().is_multiple_of(4) {
+                    bytes.push(0);
+                }
+            }
+            // carried by the tag alone
+            OscArg::Bool(_)
+            | OscArg::Nil
+            | OscArg::Impulse => {}
+        }
+    }
+    bytes
+}
+
+fn bundle(elements: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = b"#bundle\0".to_vec();
+    // "immediately"
+    bytes.extend_from_slice(&1u64.to_be_bytes());
+    for element in elements {
+        bytes.extend_from_slice(
+            &(element.len() as i32).to_be_bytes(),
+        );
+        bytes.extend_from_slice(element);
+    }
+    bytes
+}
+
+// receive until `count` controls arrived, or give up after a
+// second
+fn receive_controls(
+    listener: &mut OscListener,
+    count: usize,
+) -> Vec<Control> {
+    let start = Instant::now();
+    let mut controls = vec![];
+    while controls.len() < count
+        && start.elapsed() < Duration::from_secs(1)
+    {
+        controls.extend(listener.controls());
+        thread::sleep(Duration::from_millis(5));
+    }
+    controls
+}
+
+#[test]
+fn controls_sent_over_udp() {
+    let mut listener =
+        OscListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    let packets = [
+        message("/noise/seed", &[OscArg::Int(42)]),
+        message(
+            "/color/hue_center",
+            &[OscArg::Float(120.5)],
+        ),
+        message("/noise/octaves", &[OscArg::Int(3)]),
+        message("/time/speed", &[OscArg::Double(2.)]),
+    ];
+    for packet in &packets {
+        sender.send_to(packet, address).unwrap();
+    }
+
+    let controls = receive_controls(&mut listener, 4);
+    assert_eq!(
+        controls,
+        [
+            Control::Seed(42),
+            Control::HueCenter(120.5),
+            Control::Octaves(3),
+            Control::TimeSpeed(2.),
+        ]
+    );
+
+    let mut noise = NoiseConfig::default();
+    let mut colors = ColorSettings::default();
+    for control in &controls {
+        control.apply(&mut noise, &mut colors);
+    }
+    assert_eq!(noise.seed, 42);
+    assert_eq!(noise.octaves, 3);
+    assert_eq!(colors.hue_center, 120.5);
+}
+
+#[test]
+fn bundles_and_bad_packets_over_udp() {
+    let mut listener =
+        OscListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    // malformed and unknown messages are skipped, the
+    // bundle after them still arrives
+    sender.send_to(b"/noise/seed\0,i", address).unwrap();
+    sender
+        .send_to(
+            &message("/unknown", &[OscArg::Int(1)]),
+            address,
+        )
+        .unwrap();
+    let inner = bundle(&[message(
+        "/color/grayscale",
+        &[OscArg::Bool(true)],
+    )]);
+    sender
+        .send_to(
+            &bundle(&[
+                message(
+                    "/color/lightness",
+                    &[OscArg::Int(70)],
+                ),
+                inner,
+            ]),
+            address,
+        )
+        .unwrap();
+
+    let controls = receive_controls(&mut listener, 2);
+    assert_eq!(
+        controls,
+        [Control::Lightness(70.), Control::Grayscale(true)]
+    );
+}
+
+#[test]
+fn parse_errors() {
+    assert!(parse_packet(b"").is_err());
+    assert!(parse_packet(b"noslash\0").is_err());
+    assert!(parse_packet(b"/a\0\0,x\0\0").is_err());
+    // a float tag with no float after it
+    assert!(parse_packet(b"/a\0\0,f\0\0").is_err());
+    // no type tags at all is an empty message
+    let messages = parse_packet(b"/a\0\0").unwrap();
+    assert_eq!(messages[0].address, "/a");
+    assert!(messages[0].args.is_empty());
+    let messages = parse_packet(&message(
+        "/s",
+        &[OscArg::String("hello".to_string())],
+    ))
+    .unwrap();
+    assert_eq!(
+        messages[0].args,
+        [OscArg::String("hello".to_string())]
+    );
+}
+
+#[test]
+fn every_argument_kind_parses() {
+    let args = [
+        OscArg::Int(-3),
+        OscArg::Long(1 << 40),
+        OscArg::Float(0.5),
+        OscArg::Double(-2.25),
+        OscArg::String("text".to_string()),
+        OscArg::Blob(vec![1, 2, 3]),
+        OscArg::Bool(true),
+        OscArg::Bool(false),
+        OscArg::Nil,
+        OscArg::Impulse,
+    ];
+    let messages =
+        parse_packet(&message("/all", &args)).unwrap();
+    assert_eq!(messages[0].args, args);
+}
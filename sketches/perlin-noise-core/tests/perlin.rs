@@ -0,0 +1,43 @@
+use nannou::noise::{Fbm, MultiFractal, NoiseFn, Seedable};
+use perlin_noise_core::perlin::Perlin;
+use proptest::prelude::*;
+
+// one octave of fBm at frequency 1 is noise's own Perlin,
+// which can't be named
+fn reference(seed: u32) -> Fbm {
+    Fbm::new()
+        .set_octaves(1)
+        .set_frequency(1.)
+        .set_seed(seed)
+}
+
+fn coordinate() -> impl Strategy<Value = f64> {
+    -1000.0..1000.0
+}
+
+proptest! {
+    #[test]
+    fn perlin_matches_noise(
+        seed in any::<u32>(),
+        x in coordinate(),
+        y in coordinate(),
+        z in coordinate(),
+        w in coordinate(),
+    ) {
+        let perlin = Perlin::new().set_seed(seed);
+        let reference = reference(seed);
+        prop_assert_eq!(perlin.seed(), seed);
+        prop_assert_eq!(
+            perlin.get([x, y]),
+            reference.get([x, y])
+        );
+        prop_assert_eq!(
+            perlin.get([x, y, z]),
+            reference.get([x, y, z])
+        );
+        prop_assert_eq!(
+            perlin.get([x, y, z, w]),
+            reference.get([x, y, z, w])
+        );
+    }
+}
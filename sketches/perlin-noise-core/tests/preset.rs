@@ -0,0 +1,39 @@
+use perlin_noise_core::preset::{
+    list_presets, load_preset, preset_path, save_preset,
+};
+use std::{fs, path::Path};
+
+#[test]
+fn presets_round_trip() {
+    let directory = std::env::temp_dir()
+        .join("perlin-noise-core-presets");
+    save_preset(&directory, "misty hills", &[1, 2, 3])
+        .unwrap();
+    let loaded: Vec<i32> =
+        load_preset(&directory, "misty hills").unwrap();
+    let names = list_presets(&directory);
+    fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(loaded, [1, 2, 3]);
+    assert_eq!(names, ["misty hills"]);
+}
+
+#[test]
+fn names_outside_the_directory_are_rejected() {
+    let directory = Path::new("presets/perlin-noise-2d");
+    for name in [
+        "",
+        " ",
+        "../escape",
+        "a/b",
+        "a\\b",
+        ".hidden",
+        "\n",
+    ] {
+        assert!(
+            preset_path(directory, name).is_err(),
+            "{:?}",
+            name
+        );
+        assert!(save_preset(directory, name, &0).is_err());
+    }
+}
@@ -0,0 +1,375 @@
+use nannou::{image::RgbImage, noise::NoiseFn};
+use perlin_noise_core::{
+    color::{ColorMap, ColorSettings},
+    expr,
+    noise::{NoiseConfig, NoiseType},
+    render::render_3d,
+    seed::{random_seed, seed_from_name, seed_name},
+    warp::WarpConfig,
+};
+use proptest::prelude::*;
+use std::thread;
+
+// values computed a different way may round differently in
+// the last bits
+const EPSILON: f64 = 1e-9;
+
+// the ranges the settings panel allows
+fn noise_config() -> impl Strategy<Value = NoiseConfig> {
+    (
+        prop::sample::select(NoiseType::ALL.to_vec()),
+        any::<u32>(),
+        1..=12usize,
+        0.1..=8.0,
+        1.0..=4.0,
+        0.0..=1.0,
+        warp_config(),
+    )
+        .prop_map(
+            |(
+                noise_type,
+                seed,
+                octaves,
+                frequency,
+                lacunarity,
+                persistence,
+                warp,
+            )| NoiseConfig {
+                noise_type,
+                seed,
+                octaves,
+                frequency,
+                lacunarity,
+                persistence,
+                warp,
+            },
+        )
+}
+
+fn warp_config() -> impl Strategy<Value = WarpConfig> {
+    (any::<u32>(), 0.0..=2.0, 0.1..=4.0, 0..=3usize)
+        .prop_map(|(seed, strength, scale, iterations)| {
+            WarpConfig {
+                seed,
+                strength,
+                scale,
+                iterations,
+            }
+        })
+}
+
+// the ranges of the colour sliders
+fn color_settings() -> impl Strategy<Value = ColorSettings>
+{
+    (
+        0.0..=100.0,
+        0.0..=181.0,
+        any::<bool>(),
+        -180.0..=180.0,
+        0.0..=180.0,
+    )
+        .prop_map(
+            |(
+                lightness,
+                chroma,
+                grayscale,
+                hue_center,
+                hue_range,
+            )| {
+                ColorSettings {
+                    lightness,
+                    chroma,
+                    grayscale,
+                    hue_center,
+                    hue_range,
+                }
+            },
+        )
+}
+
+fn coordinate() -> impl Strategy<Value = f64> {
+    -1000.0..1000.0
+}
+
+fn assert_in_bounds(config: &NoiseConfig, value: f64) {
+    let [low, high] = config.bounds();
+    assert!(
+        value.is_finite()
+            && value >= low - EPSILON
+            && value <= high + EPSILON,
+        "{:?} gave {}, outside {}..{}",
+        config,
+        value,
+        low,
+        high
+    );
+}
+
+proptest! {
+    #[test]
+    fn values_2d_are_in_bounds(
+        config in noise_config(),
+        x in coordinate(),
+        y in coordinate(),
+    ) {
+        let value = config.build().get([x, y]);
+        assert_in_bounds(&config, value);
+    }
+
+    #[test]
+    fn values_3d_are_in_bounds(
+        config in noise_config(),
+        x in coordinate(),
+        y in coordinate(),
+        z in coordinate(),
+    ) {
+        let value = config.build().get([x, y, z]);
+        assert_in_bounds(&config, value);
+    }
+
+    #[test]
+    fn rebuilt_noise_is_deterministic(
+        config in noise_config(),
+        x in coordinate(),
+        y in coordinate(),
+        z in coordinate(),
+    ) {
+        let first = config.build().get([x, y, z]);
+        let second = config.build().get([x, y, z]);
+        prop_assert_eq!(first.to_bits(), second.to_bits());
+    }
+
+    #[test]
+    fn noise_is_deterministic_across_threads(
+        config in noise_config(),
+        points in prop::collection::vec(
+            (coordinate(), coordinate(), coordinate()),
+            1..64,
+        ),
+    ) {
+        let noise = config.build();
+        let expected: Vec<u64> = points
+            .iter()
+            .map(|&(x, y, z)| noise.get([x, y, z]).to_bits())
+            .collect();
+        // one thread shares the noise, the other builds
+        // its own
+        thread::scope(|scope| {
+            let shared = scope.spawn(|| {
+                points
+                    .iter()
+                    .map(|&(x, y, z)| {
+                        noise.get([x, y, z]).to_bits()
+                    })
+                    .collect::<Vec<_>>()
+            });
+            let rebuilt = scope.spawn(|| {
+                let noise = config.build();
+                points
+                    .iter()
+                    .map(|&(x, y, z)| {
+                        noise.get([x, y, z]).to_bits()
+                    })
+                    .collect::<Vec<_>>()
+            });
+            assert_eq!(shared.join().unwrap(), expected);
+            assert_eq!(rebuilt.join().unwrap(), expected);
+        });
+    }
+
+    #[test]
+    fn rows_match_scalar_sampling(
+        config in noise_config(),
+        start in (coordinate(), coordinate(), coordinate()),
+        step in 0.001..1.0,
+        length in 1..20usize,
+    ) {
+        let noise = config.build();
+        let (x, y, z) = start;
+        let mut row_2d = vec![0.; length];
+        let mut row_3d = vec![0.; length];
+        noise.row_2d([x, y], step, &mut row_2d);
+        noise.row_3d([x, y, z], step, &mut row_3d);
+        for (i, (value_2d, value_3d)) in
+            row_2d.iter().zip(&row_3d).enumerate()
+        {
+            let x = x + i as f64 * step;
+            let expected_2d = noise.get([x, y]);
+            let expected_3d = noise.get([x, y, z]);
+            prop_assert!(
+                (value_2d - expected_2d).abs() <= EPSILON,
+                "{} at {}, expected {}", value_2d, i, expected_2d
+            );
+            prop_assert!(
+                (value_3d - expected_3d).abs() <= EPSILON,
+                "{} at {}, expected {}", value_3d, i, expected_3d
+            );
+        }
+    }
+
+    #[test]
+    fn seed_names_round_trip(name in "[ -~]{4,12}") {
+        let seed = seed_from_name(&name);
+        prop_assert_eq!(seed_name(seed), &name[..4]);
+        prop_assert_eq!(seed_from_name(&seed_name(seed)), seed);
+    }
+
+    #[test]
+    fn short_seed_names_are_zero_padded(name in "[ -~]{0,3}") {
+        let mut bytes = [0; 4];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        prop_assert_eq!(
+            seed_from_name(&name),
+            u32::from_be_bytes(bytes)
+        );
+    }
+
+    #[test]
+    fn grayscale_spans_the_channel_range(value in -4.0..4.0) {
+        let colors = ColorSettings {
+            grayscale: true,
+            ..ColorSettings::default()
+        };
+        let [gray, ..] = colors.color(value);
+        // -1..1 covers every level, and values outside it
+        // clip rather than wrap
+        if value <= -1. {
+            prop_assert_eq!(gray, 0);
+        } else if value >= 1. {
+            prop_assert_eq!(gray, 255);
+        } else {
+            let expected = (value + 1.) / 2. * 255.;
+            prop_assert!((gray as f64 - expected).abs() <= 1.);
+        }
+    }
+
+    #[test]
+    fn color_map_matches_exact_colors(
+        settings in color_settings(),
+        value in -4.0..4.0,
+    ) {
+        let exact = settings.color(value);
+        let mapped = ColorMap::new(&settings).color(value);
+        for (exact, mapped) in exact.iter().zip(mapped) {
+            prop_assert!(
+                exact.abs_diff(mapped) <= 1,
+                "{:?} from the table, {:?} converted",
+                mapped,
+                exact
+            );
+        }
+    }
+
+    #[test]
+    fn updated_color_maps_match_new_ones(
+        settings in color_settings(),
+        updated in color_settings(),
+        value in -4.0..4.0,
+    ) {
+        let mut colors = ColorMap::new(&settings);
+        colors.update(&updated);
+        prop_assert_eq!(
+            colors.color(value),
+            ColorMap::new(&updated).color(value)
+        );
+    }
+}
+
+proptest! {
+    // each case renders more than one graph chunk's worth
+    // of pixels
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn parallel_render_matches_sequential_sampling(
+        config in noise_config(),
+        z in coordinate(),
+        pixels_per_unit in 10.0..1000.0,
+    ) {
+        let (width, height) = (80, 60);
+        let mut image = RgbImage::new(width, height);
+        let mut samples = vec![0.; (width * height) as usize];
+        let colors = ColorMap::new(&ColorSettings::default());
+        let noise = config.build();
+        render_3d(
+            &noise,
+            None,
+            pixels_per_unit,
+            z,
+            &colors,
+            &mut image,
+            &mut samples,
+        );
+        // rows are sampled a pixel's width apart from their
+        // start
+        let step = 1. / pixels_per_unit;
+        for (index, sample) in samples.iter().enumerate() {
+            let x = (index % width as usize) as f64;
+            let y = (index / width as usize) as f64;
+            let expected = noise.get([
+                x * step,
+                y / pixels_per_unit,
+                z,
+            ]);
+            prop_assert!((sample - expected).abs() <= EPSILON);
+            let pixel = image.get_pixel(x as u32, y as u32);
+            prop_assert_eq!(pixel.0, colors.color(*sample));
+        }
+    }
+
+    #[test]
+    fn parallel_graph_render_matches_sequential_sampling(
+        seeds in (any::<u32>(), any::<u32>()),
+        z in coordinate(),
+    ) {
+        let graph = expr::parse(&format!(
+            "fbm(seed={}) + ridged(seed={}) * 0.5",
+            seeds.0, seeds.1
+        ))
+        .unwrap();
+        let (width, height) = (80, 60);
+        let mut image = RgbImage::new(width, height);
+        let mut samples = vec![0.; (width * height) as usize];
+        render_3d(
+            &NoiseConfig::default().build(),
+            Some(&graph),
+            100.,
+            z,
+            &ColorMap::new(&ColorSettings::default()),
+            &mut image,
+            &mut samples,
+        );
+        let expected: Vec<u64> = graph.with_noise(&mut |noise| {
+            (0..samples.len())
+                .map(|index| {
+                    let x = (index % width as usize) as f64;
+                    let y = (index / width as usize) as f64;
+                    noise.get([x / 100., y / 100., z]).to_bits()
+                })
+                .collect()
+        });
+        let actual: Vec<u64> =
+            samples.iter().map(|sample| sample.to_bits()).collect();
+        prop_assert_eq!(actual, expected);
+    }
+}
+
+#[test]
+fn seed_names_are_stable() {
+    assert_eq!(seed_from_name("abcd"), 0x6162_6364);
+    assert_eq!(seed_from_name("wild-otter"), 0x7769_6c64);
+    assert_eq!(seed_from_name("ab"), 0x6162_0000);
+    assert_eq!(seed_name(0x6162_6364), "abcd");
+}
+
+#[test]
+fn random_seeds_have_ascii_names() {
+    for _ in 0..100 {
+        let seed = random_seed();
+        assert!(
+            seed.to_be_bytes().is_ascii(),
+            "{:#x}",
+            seed
+        );
+    }
+}
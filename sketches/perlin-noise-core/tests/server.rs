@@ -0,0 +1,73 @@
+use perlin_noise_core::server::{Command, Server};
+use serde_json::json;
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    thread,
+    time::Duration,
+};
+
+// send a raw request and return the response's status code
+fn status(port: u16, headers: &str, body: &str) -> u16 {
+    let mut stream =
+        TcpStream::connect(("127.0.0.1", port)).unwrap();
+    write!(
+        stream,
+        "POST /state HTTP/1.1\r\n{}Content-Length: \
+         {}\r\nConnection: close\r\n\r\n{}",
+        headers,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response[9..12].parse().unwrap()
+}
+
+#[test]
+fn requests_from_other_sites_are_refused() {
+    let server = Server::bind(0).unwrap();
+    let port = server.address().port();
+    let host = format!("Host: 127.0.0.1:{}\r\n", port);
+    let json = "Content-Type: application/json\r\n";
+
+    // a page sending a simple request, which needs no
+    // preflight
+    let plain =
+        format!("{}Content-Type: text/plain\r\n", host);
+    assert_eq!(status(port, &plain, "{}"), 415);
+    assert_eq!(status(port, &host, "{}"), 415);
+    // a page on another origin, or on a name that resolves
+    // to localhost
+    let foreign = format!(
+        "{}{}Origin: https://example.com\r\n",
+        host, json
+    );
+    assert_eq!(status(port, &foreign, "{}"), 403);
+    let rebound =
+        format!("Host: example.com:{}\r\n{}", port, json);
+    assert_eq!(status(port, &rebound, "{}"), 403);
+    assert_eq!(status(port, json, "{}"), 403);
+    assert!(server.requests().is_empty());
+
+    let local = format!(
+        "Host: localhost:{}\r\n{}Origin: \
+         http://localhost:{}\r\n",
+        port, json, port
+    );
+    let client =
+        thread::spawn(move || status(port, &local, "{}"));
+    let request = loop {
+        if let Some(request) = server.requests().pop() {
+            break request;
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    assert!(matches!(
+        request.command,
+        Command::SetState(_)
+    ));
+    request.respond(Ok(json!({})));
+    assert_eq!(client.join().unwrap(), 200);
+}
@@ -0,0 +1,34 @@
+use perlin_noise_core::{
+    color::ColorSettings,
+    noise::NoiseConfig,
+    state::{State2d, State3d},
+    viewport::Viewport,
+};
+
+#[test]
+fn states_saved_before_newer_settings_load() {
+    let noise =
+        serde_json::to_string(&NoiseConfig::default())
+            .unwrap();
+    let settings =
+        serde_json::to_string(&ColorSettings::default())
+            .unwrap();
+
+    let state: State2d = serde_json::from_str(&format!(
+        r#"{{"sketch": "perlin-noise-2d", "noise": {},
+            "settings": {}}}"#,
+        noise, settings
+    ))
+    .unwrap();
+    assert_eq!(state.viewport, Viewport::new(1. / 500.));
+    assert!(state.graph.is_none());
+
+    let state: State3d = serde_json::from_str(&format!(
+        r#"{{"sketch": "perlin-noise-3d", "noise": {},
+            "settings": {}, "z": 2.5}}"#,
+        noise, settings
+    ))
+    .unwrap();
+    assert_eq!(state.z, 2.5);
+    assert!(state.timeline.tracks.is_empty());
+}
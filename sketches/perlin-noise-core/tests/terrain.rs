@@ -0,0 +1,254 @@
+use nannou::noise::NoiseFn;
+use perlin_noise_core::{
+    noise::NoiseConfig,
+    terrain::{
+        erode, terrain_map, ErosionCache, ErosionConfig,
+        GreenChannel, MapSettings, TerrainMap,
+    },
+};
+
+const WIDTH: usize = 120;
+const HEIGHT: usize = 80;
+const SPACING: f64 = 1. / 100.;
+
+fn heightmap() -> Vec<f64> {
+    let noise = NoiseConfig::default().build();
+    (0..WIDTH * HEIGHT)
+        .map(|index| {
+            let x = (index % WIDTH) as f64 * SPACING;
+            let y = (index / WIDTH) as f64 * SPACING;
+            noise.get([x, y])
+        })
+        .collect()
+}
+
+fn eroded(config: &ErosionConfig) -> Vec<f64> {
+    let mut heights = heightmap();
+    erode(config, &mut heights, WIDTH, SPACING);
+    heights
+}
+
+fn hydraulic(seed: u32) -> ErosionConfig {
+    ErosionConfig {
+        seed,
+        droplets: 5000,
+        ..ErosionConfig::default()
+    }
+}
+
+#[test]
+fn disabled_erosion_leaves_heights_alone() {
+    assert_eq!(
+        eroded(&ErosionConfig::default()),
+        heightmap()
+    );
+}
+
+#[test]
+fn erosion_is_deterministic() {
+    let config = ErosionConfig {
+        thermal_iterations: 10,
+        ..hydraulic(7)
+    };
+    assert_eq!(eroded(&config), eroded(&config));
+}
+
+#[test]
+fn hydraulic_erosion_depends_on_the_seed() {
+    let first = eroded(&hydraulic(1));
+    assert_ne!(first, heightmap());
+    assert_ne!(first, eroded(&hydraulic(2)));
+}
+
+#[test]
+fn cached_erosion_is_reused_for_the_same_key() {
+    let config = hydraulic(3);
+    let mut cache = ErosionCache::default();
+    let mut heights = heightmap();
+    cache.erode(1, &config, &mut heights, WIDTH, SPACING);
+    assert_eq!(heights, eroded(&config));
+
+    // the cached heights stand in for whatever is passed
+    let mut flat = vec![0.; WIDTH * HEIGHT];
+    cache.erode(1, &config, &mut flat, WIDTH, SPACING);
+    assert_eq!(flat, heights);
+
+    // another key or other settings erode again
+    let mut flat = vec![0.; WIDTH * HEIGHT];
+    cache.erode(2, &config, &mut flat, WIDTH, SPACING);
+    assert_ne!(flat, heights);
+    let mut reseeded = heightmap();
+    cache.erode(
+        2,
+        &hydraulic(4),
+        &mut reseeded,
+        WIDTH,
+        SPACING,
+    );
+    assert_eq!(reseeded, eroded(&hydraulic(4)));
+}
+
+#[test]
+fn thermal_erosion_keeps_material() {
+    let config = ErosionConfig {
+        thermal_iterations: 50,
+        ..ErosionConfig::default()
+    };
+    let total =
+        |heights: &[f64]| heights.iter().sum::<f64>();
+    assert!(
+        (total(&heightmap()) - total(&eroded(&config)))
+            .abs()
+            < 1e-9
+    );
+}
+
+#[test]
+fn thermal_erosion_levels_slopes_to_the_talus_angle() {
+    // a spike on flat ground, slumping into a cone
+    const SIZE: usize = 21;
+    let mut heights = vec![0.; SIZE * SIZE];
+    heights[SIZE * SIZE / 2] = 4.;
+    let config = ErosionConfig {
+        thermal_iterations: 1000,
+        talus_angle: 30.,
+        ..ErosionConfig::default()
+    };
+    erode(&config, &mut heights, SIZE, 1.);
+
+    let talus = 30f64.to_radians().tan();
+    for y in 0..SIZE {
+        for x in 0..SIZE - 1 {
+            let index = y * SIZE + x;
+            let slope =
+                (heights[index + 1] - heights[index]).abs();
+            assert!(
+                slope < talus * 1.01,
+                "slope of {} at {}, {}, talus {}",
+                slope,
+                x,
+                y,
+                talus
+            );
+        }
+    }
+}
+
+// a heightmap of `height(x, y)` at `SPACING` apart
+fn surface(height: impl Fn(f64, f64) -> f64) -> Vec<f64> {
+    (0..WIDTH * HEIGHT)
+        .map(|index| {
+            let x = (index % WIDTH) as f64 * SPACING;
+            let y = (index / WIDTH) as f64 * SPACING;
+            height(x, y)
+        })
+        .collect()
+}
+
+fn map_of(
+    settings: &MapSettings,
+    heights: &[f64],
+) -> Vec<f64> {
+    terrain_map(settings, heights, WIDTH, SPACING)
+}
+
+fn settings(map: TerrainMap) -> MapSettings {
+    MapSettings {
+        map,
+        ..MapSettings::default()
+    }
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-6,
+        "{} isn't {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn normals_of_a_plane_face_away_from_its_slope() {
+    // rising to the right and down the image
+    let plane = surface(|x, y| 0.5 * x + 0.25 * y);
+    let length =
+        (0.5f64.powi(2) + 0.25f64.powi(2) + 1.).sqrt();
+    for (green, sign) in [
+        (GreenChannel::OpenGl, 1.),
+        (GreenChannel::DirectX, -1.),
+    ] {
+        let normals = map_of(
+            &MapSettings {
+                green,
+                ..settings(TerrainMap::Normal)
+            },
+            &plane,
+        );
+        assert_eq!(normals.len(), plane.len() * 3);
+        for normal in normals.chunks(3) {
+            assert_close(normal[0], -0.5 / length);
+            // the slope faces up the image, which is +y in
+            // OpenGL's convention
+            assert_close(normal[1], sign * 0.25 / length);
+            assert_close(normal[2], 1. / length);
+        }
+    }
+}
+
+#[test]
+fn normal_strength_scales_slopes() {
+    let plane = surface(|x, _| x);
+    let normals = map_of(
+        &MapSettings {
+            normal_strength: 2.,
+            ..settings(TerrainMap::Normal)
+        },
+        &plane,
+    );
+    let length = 5f64.sqrt();
+    assert_close(normals[0], -2. / length);
+    assert_close(normals[2], 1. / length);
+}
+
+#[test]
+fn slopes_are_in_degrees() {
+    let plane = surface(|x, y| 0.6 * x + 0.8 * y);
+    for slope in
+        map_of(&settings(TerrainMap::Slope), &plane)
+    {
+        assert_close(slope, 45.);
+    }
+}
+
+#[test]
+fn hillshade_lights_slopes_facing_the_sun() {
+    let flat = surface(|_, _| 0.);
+    let sun = MapSettings {
+        sun_azimuth: 90.,
+        sun_elevation: 30.,
+        ..settings(TerrainMap::Hillshade)
+    };
+    for shade in map_of(&sun, &flat) {
+        assert_close(shade, 0.5);
+    }
+    // the sun is to the right, so a slope falling to the
+    // right faces it and one rising to the right faces away
+    let facing = map_of(&sun, &surface(|x, _| -x));
+    let away = map_of(&sun, &surface(|x, _| x));
+    assert!(facing[0] > 0.5);
+    assert!(away[0] < 0.5);
+}
+
+#[test]
+fn curvature_is_the_laplacian() {
+    let plane = surface(|x, y| 0.3 * x - 0.7 * y);
+    let bowl = surface(|x, y| x * x + 2. * y * y);
+    let curvature = settings(TerrainMap::Curvature);
+    for value in map_of(&curvature, &plane) {
+        assert!(value.abs() < 1e-6);
+    }
+    for value in map_of(&curvature, &bowl) {
+        assert_close(value, 6.);
+    }
+}
@@ -0,0 +1,206 @@
+use nannou::noise::NoiseFn;
+use perlin_noise_core::{
+    expr,
+    noise::NoiseConfig,
+    tiles::{TileCache, TileSource},
+    viewport::Viewport,
+};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+const WIDTH: usize = 300;
+const HEIGHT: usize = 200;
+// a power of two, so tiles' samples land on pixels
+const SCALE: f64 = 1. / 512.;
+const EPSILON: f64 = 1e-9;
+
+// render until every tile is sampled
+fn render_complete(
+    cache: &mut TileCache,
+    source: &TileSource,
+    viewport: &Viewport,
+) -> Vec<f64> {
+    let mut samples = vec![0.; WIDTH * HEIGHT];
+    let start = Instant::now();
+    while !cache.render(
+        source,
+        viewport,
+        WIDTH,
+        &mut samples,
+    ) {
+        assert!(
+            start.elapsed() < Duration::from_secs(30),
+            "tiles weren't sampled"
+        );
+        thread::sleep(Duration::from_millis(1));
+        cache.receive();
+    }
+    samples
+}
+
+fn assert_samples_match(
+    samples: &[f64],
+    viewport: &Viewport,
+    noise: &dyn NoiseFn<[f64; 2]>,
+) {
+    for (index, sample) in samples.iter().enumerate() {
+        let x = (index % WIDTH) as f64;
+        let y = (index / WIDTH) as f64;
+        let expected = noise.get(viewport.point(x, y));
+        assert!(
+            (sample - expected).abs() <= EPSILON,
+            "{} at {}, {}, expected {}",
+            sample,
+            x,
+            y,
+            expected
+        );
+    }
+}
+
+#[test]
+fn tiles_match_sampling_at_pixels() {
+    let config = NoiseConfig::default();
+    let source = TileSource::Noise(config.clone());
+    let mut viewport = Viewport::new(SCALE);
+    // across the origin, where tile coordinates go negative
+    viewport.pan(150., 100.);
+    let mut cache = TileCache::new(64);
+    let samples =
+        render_complete(&mut cache, &source, &viewport);
+    assert_samples_match(
+        &samples,
+        &viewport,
+        &config.build(),
+    );
+}
+
+#[test]
+fn graph_tiles_match_sampling_at_pixels() {
+    let graph =
+        expr::parse("fbm(seed=3) + ridged(seed=4) * 0.5")
+            .unwrap();
+    let source = TileSource::Graph(graph.clone());
+    let viewport = Viewport::new(SCALE);
+    let mut cache = TileCache::new(64);
+    let samples =
+        render_complete(&mut cache, &source, &viewport);
+    graph.with_noise(&mut |noise| {
+        assert_samples_match(&samples, &viewport, noise)
+    });
+}
+
+#[test]
+fn off_grid_views_show_the_nearest_samples() {
+    let config = NoiseConfig::default();
+    let noise = config.build();
+    let source = TileSource::Noise(config);
+    // the 2D sketch's default scale, between two levels,
+    // panned by a fraction of a pixel
+    let mut viewport = Viewport::new(1. / 500.);
+    viewport.pan(150.3, 100.7);
+    let spacing = 1. / 512.;
+    let mut cache = TileCache::new(64);
+    let samples =
+        render_complete(&mut cache, &source, &viewport);
+    for (index, sample) in samples.iter().enumerate() {
+        let x = (index % WIDTH) as f64;
+        let y = (index / WIDTH) as f64;
+        let point = viewport.point(x, y);
+        let nearest = point.map(|coordinate| {
+            (coordinate / spacing).round() * spacing
+        });
+        let expected = noise.get(nearest);
+        assert!(
+            (sample - expected).abs() <= EPSILON,
+            "{} at {}, {}, expected {}",
+            sample,
+            x,
+            y,
+            expected
+        );
+    }
+}
+
+#[test]
+fn panning_back_reuses_tiles() {
+    let source = TileSource::Noise(NoiseConfig::default());
+    let start = Viewport::new(SCALE);
+    let mut cache = TileCache::new(64);
+    let expected =
+        render_complete(&mut cache, &source, &start);
+
+    let mut panned = start;
+    panned.pan(-1000., 0.);
+    render_complete(&mut cache, &source, &panned);
+
+    let mut samples = vec![0.; WIDTH * HEIGHT];
+    assert!(cache.render(
+        &source,
+        &start,
+        WIDTH,
+        &mut samples
+    ));
+    assert_eq!(samples, expected);
+}
+
+#[test]
+fn zooming_in_draws_coarser_tiles_first() {
+    let source = TileSource::Noise(NoiseConfig::default());
+    let viewport = Viewport::new(SCALE);
+    let mut cache = TileCache::new(64);
+    let coarse =
+        render_complete(&mut cache, &source, &viewport);
+
+    let mut zoomed = viewport;
+    zoomed.zoom_about(0., 0., 2.);
+    let mut samples = vec![0.; WIDTH * HEIGHT];
+    assert!(!cache.render(
+        &source,
+        &zoomed,
+        WIDTH,
+        &mut samples
+    ));
+    // until the sharper tiles arrive each pixel of the
+    // zoomed in view shows the nearest pixel of the coarser
+    // one, halves going right and down
+    for (index, sample) in samples.iter().enumerate() {
+        let (x, y) = (index % WIDTH, index / WIDTH);
+        assert_eq!(
+            *sample,
+            coarse[y.div_ceil(2) * WIDTH + x.div_ceil(2)]
+        );
+    }
+
+    let sharp =
+        render_complete(&mut cache, &source, &zoomed);
+    assert_samples_match(
+        &sharp,
+        &zoomed,
+        &NoiseConfig::default().build(),
+    );
+}
+
+#[test]
+fn other_parameters_have_their_own_tiles() {
+    let viewport = Viewport::new(SCALE);
+    let mut cache = TileCache::new(64);
+    for seed in [1, 2, 1] {
+        let config = NoiseConfig {
+            seed,
+            ..NoiseConfig::default()
+        };
+        let samples = render_complete(
+            &mut cache,
+            &TileSource::Noise(config.clone()),
+            &viewport,
+        );
+        assert_samples_match(
+            &samples,
+            &viewport,
+            &config.build(),
+        );
+    }
+}
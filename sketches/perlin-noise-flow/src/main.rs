@@ -0,0 +1,558 @@
+use nannou::{
+    glam::Vec2, image::DynamicImage, prelude::*,
+    wgpu::Texture,
+};
+use nannou_egui::{self, egui, Egui};
+use perlin_noise_core::{
+    capture::{CaptureConfig, CaptureWindow},
+    color::{ColorMap, ColorSettings},
+    config::{draw_config_error, ConfigFile},
+    flow::{FlowConfig, Particles, Trails},
+    metadata::{load_state_from_png, save_png_with_state},
+    noise::{Noise, NoiseConfig},
+    preset::{
+        list_presets, load_preset, preset_dir, save_preset,
+    },
+    seed::{random_seed, seed_name},
+    ui::{
+        capture_settings, flow_settings, noise_settings,
+        preset_settings, PresetAction,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const SKETCH: &str = "perlin-noise-flow";
+
+// interesting variables
+const NOISE_STEP: f64 = 300.;
+// how far along z the flow moves each frame
+const Z_STEP: f64 = 1. / 1000.;
+
+// the constants above and the window size, overridden by
+// perlin-noise-flow.toml next to the binary while the
+// sketch runs
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default)]
+struct SketchConfig {
+    noise_step: f64,
+    z_step: f64,
+    window_width: u32,
+    window_height: u32,
+}
+
+impl Default for SketchConfig {
+    fn default() -> Self {
+        SketchConfig {
+            noise_step: NOISE_STEP,
+            z_step: Z_STEP,
+            window_width: 1200,
+            window_height: 800,
+        }
+    }
+}
+
+fn main() {
+    nannou::app(model).update(update).run();
+}
+
+struct Model {
+    config: SketchConfig,
+    config_file: ConfigFile,
+    settings: ColorSettings,
+    // `settings` with their table of hues, brought up to
+    // date before each redraw
+    colors: ColorMap,
+    egui: Egui,
+    image_window: WindowId,
+    settings_window: WindowId,
+    capture: CaptureConfig,
+    // path typed into the "load from PNG" field
+    load_path: String,
+    preset_name: String,
+    presets: Vec<String>,
+    // multiplier on `config.z_step`
+    speed: f64,
+
+    noise_config: NoiseConfig,
+    noise: Noise,
+    flow: FlowConfig,
+    particles: Particles,
+    trails: Trails,
+    // set when the trails should be cleared and the
+    // particles placed from the seed again
+    should_restart: bool,
+    image: DynamicImage,
+    size: Vec2,
+    z: f64,
+}
+
+// everything needed to start the flow of a capture again,
+// stored in the PNG's text chunks
+#[derive(Serialize, Deserialize)]
+struct SketchState {
+    sketch: String,
+    noise: NoiseConfig,
+    settings: ColorSettings,
+    flow: FlowConfig,
+    z: f64,
+}
+
+impl Model {
+    fn new(
+        config: SketchConfig,
+        config_file: ConfigFile,
+        egui: Egui,
+        image_window: WindowId,
+        settings_window: WindowId,
+    ) -> Self {
+        let noise_config = NoiseConfig::default();
+        let flow = FlowConfig::default();
+
+        let presets = list_presets(&preset_dir(SKETCH));
+
+        Model {
+            config,
+            config_file,
+            settings: ColorSettings::default(),
+            colors: ColorMap::new(&ColorSettings::default()),
+            egui,
+            image_window,
+            settings_window,
+            capture: CaptureConfig::from_args(),
+            load_path: String::new(),
+            preset_name: String::new(),
+            presets,
+            speed: 1.,
+            noise: noise_config.build(),
+            particles: Particles::new(
+                noise_config.seed,
+                &flow,
+                10,
+                10,
+            ),
+            noise_config,
+            flow,
+            trails: Trails::new(10, 10),
+            should_restart: true,
+            image: DynamicImage::new_rgb8(10, 10),
+            size: Vec2::new(10., 10.),
+            z: 0.,
+        }
+    }
+    fn update_noise(&mut self, noise_config: NoiseConfig) {
+        if noise_config.seed != self.noise_config.seed {
+            self.should_restart = true;
+        }
+        self.noise = noise_config.build();
+        self.noise_config = noise_config;
+    }
+    fn state(&self, app: &App) -> SketchState {
+        SketchState {
+            sketch: app.exe_name().unwrap(),
+            noise: self.noise_config.clone(),
+            settings: self.settings.clone(),
+            flow: self.flow.clone(),
+            z: self.z,
+        }
+    }
+    fn restore(&mut self, state: SketchState) {
+        self.settings = state.settings;
+        self.flow = state.flow;
+        self.z = state.z;
+        self.update_noise(state.noise);
+        self.should_restart = true;
+    }
+    // clear the trails and place the particles from the
+    // seed again, at the window's size
+    fn restart(&mut self) {
+        let (width, height) = (
+            self.size.x.floor() as u32,
+            self.size.y.floor() as u32,
+        );
+        self.image = DynamicImage::new_rgb8(width, height);
+        self.trails = Trails::new(width, height);
+        self.particles = Particles::new(
+            self.noise_config.seed,
+            &self.flow,
+            width,
+            height,
+        );
+        self.should_restart = false;
+    }
+    // move the particles a frame on and draw their trails
+    fn advance(&mut self) {
+        self.trails.fade(self.flow.fade);
+        self.particles.step(
+            &self.noise,
+            &self.flow,
+            self.config.noise_step,
+            self.z,
+            &mut self.trails,
+        );
+        self.z += self.config.z_step * self.speed;
+
+        self.colors.update(&self.settings);
+        if let Some(image) = self.image.as_mut_rgb8() {
+            self.trails.draw(&self.colors, image);
+        }
+    }
+}
+
+fn model(app: &App) -> Model {
+    let mut config_file = ConfigFile::from_args(SKETCH);
+    let config: SketchConfig =
+        config_file.poll().unwrap_or_default();
+    if let Some(error) = &config_file.error {
+        eprintln!("{}", error);
+    }
+
+    let image_window = app
+        .new_window()
+        .size(config.window_width, config.window_height)
+        .view(view)
+        .key_pressed(key_pressed)
+        .dropped_file(dropped_file)
+        .build()
+        .unwrap();
+
+    let window_id = app
+        .new_window()
+        .view(egui_view)
+        .raw_event(raw_window_event)
+        .build()
+        .unwrap();
+    let window = app.window(window_id).unwrap();
+
+    let egui = Egui::from_window(&window);
+
+    Model::new(
+        config,
+        config_file,
+        egui,
+        image_window,
+        window_id,
+    )
+}
+
+fn raw_window_event(
+    _app: &App,
+    model: &mut Model,
+    event: &nannou::winit::event::WindowEvent,
+) {
+    // Let egui handle things like keyboard and mouse input.
+    model.egui.handle_raw_event(event);
+}
+
+fn update(app: &App, model: &mut Model, update: Update) {
+    if let Some(config) = model.config_file.poll() {
+        apply_config(app, model, config);
+    }
+
+    let win_rect =
+        app.window(model.image_window).unwrap().rect();
+    if model.size != win_rect.wh() {
+        model.size = win_rect.wh();
+        model.should_restart = true;
+    }
+    if model.should_restart {
+        model.restart();
+    }
+    model.advance();
+
+    let egui = &mut model.egui;
+    let settings = &mut model.settings;
+    let load_path = &mut model.load_path;
+    let capture_config = &mut model.capture;
+    let preset_name = &mut model.preset_name;
+    let presets = &model.presets;
+    let mut noise_config = model.noise_config.clone();
+    let mut flow = model.flow.clone();
+    let mut load_clicked = false;
+    let mut capture_clicked = false;
+    let mut clear_clicked = false;
+    let mut preset_action = None;
+
+    egui.set_elapsed_time(update.since_start);
+    let ctx = egui.begin_frame();
+
+    egui::Window::new("Settings").show(&ctx, |ui| {
+        noise_settings(ui, &mut noise_config);
+        if ui.button("random seed").clicked() {
+            noise_config.seed = random_seed();
+        }
+
+        // Lightness slider
+        let slider = egui::Slider::new(
+            &mut settings.lightness,
+            0.0..=100.0,
+        );
+        ui.label("Lightness:");
+        ui.add(slider);
+
+        // Chroma slider
+        ui.label("Chroma:");
+        ui.add(egui::Slider::new(
+            &mut settings.chroma,
+            0.0..=181.0,
+        ));
+
+        ui.label("Hue Center:");
+        ui.add(egui::Slider::new(
+            &mut settings.hue_center,
+            -180.0..=180.0,
+        ));
+
+        ui.label("Hue range:");
+        ui.add(egui::Slider::new(
+            &mut settings.hue_range,
+            0.0..=180.0,
+        ));
+
+        ui.add(egui::Checkbox::new(
+            &mut settings.grayscale,
+            "Grayscale",
+        ));
+
+        // how fast the flow changes
+        ui.label("Speed:");
+        ui.add(egui::Slider::new(
+            &mut model.speed,
+            0.0..=10.0,
+        ));
+
+        ui.label(app.fps());
+
+        ui.separator();
+        flow_settings(ui, &mut flow);
+        clear_clicked = ui.button("clear").clicked();
+
+        ui.separator();
+        ui.label("Load from PNG:");
+        ui.text_edit_singleline(load_path);
+        load_clicked = ui.button("load").clicked();
+
+        ui.separator();
+        preset_action = ui
+            .collapsing("Presets", |ui| {
+                preset_settings(ui, preset_name, presets)
+            })
+            .body_returned
+            .flatten();
+
+        ui.separator();
+        capture_clicked = ui
+            .collapsing("Capture", |ui| {
+                capture_settings(
+                    ui,
+                    capture_config,
+                    &CaptureWindow::ALL,
+                )
+            })
+            .body_returned
+            .unwrap_or(false);
+    });
+    // end the egui frame, releasing the borrow of the model
+    drop(ctx);
+
+    if noise_config != model.noise_config {
+        model.update_noise(noise_config);
+    }
+    // other settings apply to the particles as they are
+    if flow.particles != model.flow.particles
+        || clear_clicked
+    {
+        model.should_restart = true;
+    }
+    model.flow = flow;
+    if load_clicked {
+        let path = PathBuf::from(&model.load_path);
+        load_png(model, &path);
+    }
+    if capture_clicked {
+        capture(app, model);
+    }
+    match preset_action {
+        Some(PresetAction::Save(name)) => {
+            let directory = preset_dir(SKETCH);
+            if let Err(error) = save_preset(
+                &directory,
+                &name,
+                &model.state(app),
+            ) {
+                eprintln!(
+                    "failed to save preset: {}",
+                    error
+                );
+            }
+            model.presets = list_presets(&directory);
+        }
+        Some(PresetAction::Load(name)) => {
+            match load_preset(&preset_dir(SKETCH), &name) {
+                Ok(state) => model.restore(state),
+                Err(error) => {
+                    eprintln!(
+                        "failed to load preset: {}",
+                        error
+                    )
+                }
+            }
+        }
+        None => {}
+    }
+}
+
+// apply a reloaded config. The flow moves every frame, so
+// only the window size needs applying.
+fn apply_config(
+    app: &App,
+    model: &mut Model,
+    config: SketchConfig,
+) {
+    if (config.window_width, config.window_height)
+        != (
+            model.config.window_width,
+            model.config.window_height,
+        )
+    {
+        // update() notices the new size
+        app.window(model.image_window)
+            .unwrap()
+            .set_inner_size_points(
+                config.window_width as f32,
+                config.window_height as f32,
+            );
+    }
+    model.config = config;
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let background = rgb(0.439, 0.039, 0.467);
+
+    // set up containing rectangles
+    let win_rect = app.window_rect();
+    let win_p = win_rect.pad(25.0);
+
+    let draw = app.draw();
+    draw.background().color(background);
+
+    let texture = Texture::from_image(app, &model.image);
+    draw.texture(&texture);
+
+    // display noise seed
+    draw.text(&seed_name(model.noise_config.seed))
+        .font_size(48)
+        .wh(win_p.wh())
+        .right_justify()
+        .align_text_bottom()
+        .color(background);
+
+    if let Some(error) = &model.config_file.error {
+        draw_config_error(&draw, win_rect, error);
+    }
+
+    // draw to frame
+    draw.to_frame(app, &frame).unwrap();
+}
+
+fn egui_view(_app: &App, model: &Model, frame: Frame) {
+    model.egui.draw_to_frame(&frame).unwrap();
+}
+
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if key == Key::S {
+        capture(app, model);
+    }
+    if key == Key::R {
+        randomize_seed(model);
+    }
+    if key == Key::C {
+        model.should_restart = true;
+    }
+}
+
+// save the configured capture target. The trails are
+// saved with the sketch state embedded, so the flow can be
+// started again from it later.
+//
+// Returns where the capture goes, or `None` if it failed.
+fn capture(
+    app: &App,
+    model: &mut Model,
+) -> Option<PathBuf> {
+    let path = match model.capture.next_path(
+        &app.exe_name().unwrap(),
+        Some(model.noise_config.seed),
+        "png",
+    ) {
+        Ok(path) => path,
+        Err(error) => {
+            eprintln!("failed to capture image: {}", error);
+            return None;
+        }
+    };
+
+    match model.capture.window {
+        CaptureWindow::Image => {
+            let image = match model.image.as_rgb8() {
+                Some(image) => image,
+                None => {
+                    eprintln!(
+                        "failed to capture image: the trails \
+                         image isn't RGB"
+                    );
+                    return None;
+                }
+            };
+            if let Err(error) = save_png_with_state(
+                &path,
+                image,
+                &model.state(app),
+            ) {
+                eprintln!(
+                    "failed to capture image: {}",
+                    error
+                );
+                return None;
+            }
+        }
+        CaptureWindow::Noise => app
+            .window(model.image_window)
+            .unwrap()
+            .capture_frame(&path),
+        CaptureWindow::Settings => app
+            .window(model.settings_window)
+            .unwrap()
+            .capture_frame(&path),
+    }
+    Some(path)
+}
+
+fn load_png(model: &mut Model, path: &Path) {
+    match load_state_from_png(path, SKETCH) {
+        Ok(state) => model.restore(state),
+        Err(error) => eprintln!(
+            "failed to load {}: {}",
+            path.display(),
+            error
+        ),
+    }
+}
+
+fn dropped_file(
+    _app: &App,
+    model: &mut Model,
+    path: PathBuf,
+) {
+    load_png(model, &path);
+}
+
+fn randomize_seed(model: &mut Model) {
+    // reseed the noise, keeping the rest of its settings,
+    // which starts the particles again
+    let noise_config = NoiseConfig {
+        seed: random_seed(),
+        ..model.noise_config.clone()
+    };
+    model.update_noise(noise_config);
+}